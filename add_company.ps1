@@ -24,12 +24,16 @@ $createDatabaseQuery = "CREATE DATABASE $company_name;"
 $tableFilesPath = Join-Path (Get-Location).Path -ChildPath "structure\tables"
 $tableFilesArr = Get-ChildItem $tableFilesPath -Filter *.sql | Sort-Object {
     $order = @{
-        'procedures' = 1
-        'machines'   = 2
-        'roles'      = 3
-        'users'      = 4
-        'tasks'      = 5
-        'reports'    = 6
+        'procedures'   = 1
+        'machines'     = 2
+        'roles'        = 3
+        'users'        = 4
+        'tasks'        = 5
+        'reports'      = 6
+        'measurements' = 7
+        'maintenance'  = 8
+        'parts'        = 9
+        'costs'        = 10
     }
     $order[$_.BaseName.ToLower()]
 }
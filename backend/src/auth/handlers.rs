@@ -11,22 +11,36 @@ use axum_extra::extract::{
     cookie::{Cookie, SameSite},
     CookieJar,
 };
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, encode, jwk::JwkSet, DecodingKey, EncodingKey, Header, Validation};
 use rand_core::{OsRng, RngCore};
-use sqlx::{query, query_as};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    auth::models::{LoginToken, TokenClaims},
+    auth::models::{LoginToken, TokenClaims, LOGIN_AUDIENCE},
+    field_vec,
     machines::facilities::Facility,
-    user_from_id,
+    update_field, user_from_id,
     users::{models::User, roles::models::Role},
-    utils::errors::{ApiError, ForbiddenReason},
+    utils::{
+        db::{Field, IntoField, Nullable},
+        errors::{ApiError, ConflictReason, ForbiddenReason, InputInvalidReason},
+    },
     AppState,
 };
 
-use super::models::{LoginEmail, LoginKind, LoginOTPUser, LoginPasswordUser};
+use super::{
+    impersonation::models::Impersonation,
+    login_history::{
+        models::{ClientInfo, LoginMethod},
+        record_login,
+    },
+    models::{
+        LoginEmail, LoginKind, LoginOTPUser, LoginPasswordUser, Me, NotificationPreferences,
+        Preferences, UpdateMe,
+    },
+};
 
 pub async fn logout() -> impl IntoResponse {
     let cookie = Cookie::build(("token", ""))
@@ -39,8 +53,129 @@ pub async fn logout() -> impl IntoResponse {
     AppendHeaders([(header::SET_COOKIE, cookie)])
 }
 
-pub async fn me(Extension(user): Extension<User>) -> Json<User> {
-    Json(user)
+pub async fn jwks(State(app_state): State<Arc<AppState>>) -> Json<JwkSet> {
+    Json(app_state.keys.jwks())
+}
+
+async fn user_preferences(conn: &mut PgConnection, user_id: Uuid) -> Result<Preferences, ApiError> {
+    let preferences = query!(
+        r#"
+        SELECT
+            u.language,
+            u.notify_task_assigned
+        FROM
+            users u
+        WHERE
+            u.id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Preferences {
+        language: preferences.language,
+        notifications: NotificationPreferences {
+            task_assigned: preferences.notify_task_assigned,
+        },
+    })
+}
+
+// Language tags like "en" or "de-AT"
+
+fn is_language_tag(language: &str) -> bool {
+    let mut parts = language.split('-');
+
+    let primary_ok = parts.next().is_some_and(|primary| {
+        (2..=3).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_lowercase())
+    });
+
+    primary_ok
+        && language.len() <= 16
+        && parts.all(|part| {
+            (2..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+pub async fn me(
+    Extension(user): Extension<User>,
+    impersonation: Option<Extension<Impersonation>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Me>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let preferences = user_preferences(&mut conn, user.id).await?;
+
+    Ok(Json(Me {
+        user,
+        impersonation: impersonation.map(|Extension(impersonation)| impersonation),
+        preferences,
+    }))
+}
+
+// Needs no permission, only touches the fields in UpdateMe
+
+pub async fn update_me(
+    Extension(user): Extension<User>,
+    impersonation: Option<Extension<Impersonation>>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateMe>,
+) -> Result<Json<Me>, ApiError> {
+    if let Some(language) = &body.language {
+        if !is_language_tag(language) {
+            return Err(ApiError::InputInvalid(InputInvalidReason::InvalidLanguage));
+        }
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    if let Nullable::Value(phone) = &body.phone {
+        let phone_taken = query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM users WHERE phone = $1 AND id <> $2) AS "exists!""#,
+            phone,
+            user.id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if phone_taken {
+            return Err(ApiError::Conflict(ConflictReason::PhoneTaken));
+        }
+    }
+
+    let fields = field_vec![
+        phone => body.phone,
+        occupation => body.occupation,
+        language => body.language,
+        notify_task_assigned => body.notifications.task_assigned
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE users SET");
+    let mut separated_list = query_builder.separated(",");
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(user.id);
+
+    query_builder.build().execute(&mut *tx).await?;
+
+    let updated_user = user_from_id!(user.id).fetch_one(&mut *tx).await?;
+    let preferences = user_preferences(&mut tx, user.id).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(Me {
+        user: updated_user,
+        impersonation: impersonation.map(|Extension(impersonation)| impersonation),
+        preferences,
+    }))
 }
 
 pub async fn login_initiate(
@@ -59,29 +194,12 @@ pub async fn login_initiate(
                 r.name,
                 r.level,
                 r.has_password,
-                r.user_view,
-                r.user_create,
-                r.user_edit,
-                r.user_delete,
-                r.machine_view,
-                r.machine_create,
-                r.machine_edit,
-                r.machine_delete,
-                r.task_view,
-                r.task_create,
-                r.task_edit,
-                r.task_delete,
-                r.report_view,
-                r.report_create,
-                r.report_edit,
-                r.report_delete,
-                r.facility_view,
-                r.facility_create,
-                r.facility_edit,
-                r.facility_delete
+                r.global_access,
+                role_permission_keys(r.id)
             ) AS "role!: Role",
             u.active,
-            u.last_login
+            u.last_login,
+            u.service_account
         FROM
             users u
         INNER JOIN
@@ -100,6 +218,10 @@ pub async fn login_initiate(
         Err(ApiError::Forbidden(ForbiddenReason::AccountDeactivated))?
     }
 
+    if user.service_account {
+        Err(ApiError::Forbidden(ForbiddenReason::ServiceAccount))?
+    }
+
     if user.role.has_password {
         let cookie = Cookie::build(("auth_token", ""))
             .path("/")
@@ -137,6 +259,7 @@ pub async fn login_initiate(
 
     let claims = LoginToken {
         sub: user.id.to_string(),
+        aud: LOGIN_AUDIENCE.to_owned(),
         iat,
         exp,
         hash,
@@ -163,6 +286,7 @@ pub async fn login_initiate(
 
 pub async fn login_password(
     State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
     Json(body): Json<LoginPasswordUser>,
 ) -> Result<impl IntoResponse, ApiError> {
     body.validate()?;
@@ -178,28 +302,11 @@ pub async fn login_password(
                     r.name,
                     r.level,
                     r.has_password,
-                    r.user_view,
-                    r.user_create,
-                    r.user_edit,
-                    r.user_delete,
-                    r.machine_view,
-                    r.machine_create,
-                    r.machine_edit,
-                    r.machine_delete,
-                    r.task_view,
-                    r.task_create,
-                    r.task_edit,
-                    r.task_delete,
-                    r.report_view,
-                    r.report_create,
-                    r.report_edit,
-                    r.report_delete,
-                    r.facility_view,
-                    r.facility_create,
-                    r.facility_edit,
-                    r.facility_delete
+                    r.global_access,
+                    role_permission_keys(r.id)
                 ) AS "role!: Role",
-                u.active
+                u.active,
+                u.service_account
             FROM
                 users u
             INNER JOIN
@@ -219,9 +326,21 @@ pub async fn login_password(
     }
 
     if !user.active {
+        record_login(
+            &app_state.db,
+            user.id,
+            LoginMethod::Password,
+            false,
+            &client,
+        )
+        .await?;
         return Err(ApiError::Forbidden(ForbiddenReason::AccountDeactivated));
     }
 
+    if user.service_account {
+        return Err(ApiError::Forbidden(ForbiddenReason::ServiceAccount));
+    }
+
     let stored_password = match user.password {
         Some(pw) => pw,
         None => {
@@ -239,9 +358,19 @@ pub async fn login_password(
     };
 
     if !passwords_match {
+        record_login(
+            &app_state.db,
+            user.id,
+            LoginMethod::Password,
+            false,
+            &client,
+        )
+        .await?;
         return Err(ApiError::Forbidden(ForbiddenReason::IncorrectPassword));
     }
 
+    record_login(&app_state.db, user.id, LoginMethod::Password, true, &client).await?;
+
     let now = chrono::Utc::now();
     let iat = now.timestamp() as usize;
     let exp = (now + chrono::Duration::minutes(app_state.env.jwt_expires_in)).timestamp() as usize;
@@ -249,13 +378,10 @@ pub async fn login_password(
         sub: user.id.to_string(),
         exp,
         iat,
+        impersonation: None,
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(app_state.env.jwt_secret.as_ref()),
-    )?;
+    let token = app_state.keys.encode(&claims)?;
 
     let cookie = Cookie::build(("token", token.to_owned()))
         .path("/")
@@ -272,6 +398,7 @@ pub async fn login_password(
 
 pub async fn login_otp(
     State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
     cookie_jar: CookieJar,
     Json(body): Json<LoginOTPUser>,
 ) -> Result<impl IntoResponse, ApiError> {
@@ -281,13 +408,18 @@ pub async fn login_otp(
 
     let token = token.ok_or_else(|| ApiError::Unauthorized)?;
 
+    let mut validation = Validation::default();
+    validation.set_audience(&[LOGIN_AUDIENCE]);
+
     let claims = decode::<LoginToken>(
         &token,
         &DecodingKey::from_secret(app_state.env.jwt_pwl_secret.as_ref()),
-        &Validation::default(),
+        &validation,
     )?
     .claims;
 
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
     let codes_match = match PasswordHash::new(&claims.hash) {
         Ok(stored_hash) => Argon2::default()
             .verify_password(body.code.as_bytes(), &stored_hash)
@@ -296,11 +428,10 @@ pub async fn login_otp(
     };
 
     if !codes_match {
+        record_login(&app_state.db, user_id, LoginMethod::Otp, false, &client).await?;
         return Err(ApiError::Forbidden(ForbiddenReason::IncorrectCode));
     }
 
-    let user_id = Uuid::parse_str(&claims.sub)?;
-
     let user = user_from_id!(user_id).fetch_one(&app_state.db).await?;
 
     if user.role.has_password {
@@ -308,9 +439,16 @@ pub async fn login_otp(
     }
 
     if !user.active {
+        record_login(&app_state.db, user.id, LoginMethod::Otp, false, &client).await?;
         return Err(ApiError::Forbidden(ForbiddenReason::AccountDeactivated));
     }
 
+    if user.service_account {
+        return Err(ApiError::Forbidden(ForbiddenReason::ServiceAccount));
+    }
+
+    record_login(&app_state.db, user.id, LoginMethod::Otp, true, &client).await?;
+
     let now = chrono::Utc::now();
     let iat = now.timestamp() as usize;
     let exp = (now + chrono::Duration::minutes(app_state.env.jwt_expires_in)).timestamp() as usize;
@@ -318,13 +456,10 @@ pub async fn login_otp(
         sub: user.id.to_string(),
         iat,
         exp,
+        impersonation: None,
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(app_state.env.jwt_secret.as_ref()),
-    )?;
+    let token = app_state.keys.encode(&claims)?;
 
     let token_cookie = Cookie::build(("token", token.to_owned()))
         .path("/")
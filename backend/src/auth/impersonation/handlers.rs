@@ -0,0 +1,394 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderName, StatusCode},
+    response::{AppendHeaders, IntoResponse},
+    Extension, Json,
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use sqlx::{query, query_as, query_scalar, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        login_history::models::ClientInfo,
+        models::TokenClaims,
+        permissions::{Require, UserImpersonate},
+    },
+    machines::facilities::Facility,
+    user_from_id,
+    users::{
+        models::{ShortUser, User},
+        roles::models::Role,
+    },
+    utils::errors::{ApiError, ForbiddenReason},
+    AppState,
+};
+
+use super::models::{
+    DeleteImpersonation, Impersonation, NewImpersonation, QueryImpersonations,
+    DEFAULT_IMPERSONATION_MINUTES, MAX_IMPERSONATION_MINUTES,
+};
+
+// Checked on every request made with an impersonation token, so ending the session or
+// taking the permission away from the administrator cuts it off immediately
+
+pub async fn active_impersonation(
+    db: &PgPool,
+    id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<Impersonation>, ApiError> {
+    let impersonation = query_as!(
+        Impersonation,
+        r#"
+        SELECT
+            i.id,
+            (
+                a.id,
+                a.first_name,
+                a.last_name,
+                a.email,
+                a.image
+            ) AS "admin!: ShortUser",
+            i.user_id,
+            i.reason,
+            i.ip,
+            i.user_agent,
+            i.created,
+            i.expires_at,
+            i.ended_at,
+            i.ended_by
+        FROM
+            impersonations i
+        INNER JOIN
+            users a
+        ON
+            i.admin_id = a.id
+        WHERE
+            i.id = $1
+        AND
+            i.user_id = $2
+        AND
+            i.ended_at IS NULL
+        AND
+            i.expires_at > NOW()
+        AND
+            a.active
+        AND
+            'user.impersonate' = ANY(role_permission_keys(a.role))
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(impersonation)
+}
+
+pub async fn index(
+    _: Require<UserImpersonate>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryImpersonations>,
+) -> Result<Json<Vec<Impersonation>>, ApiError> {
+    let impersonations = query_as!(
+        Impersonation,
+        r#"
+        SELECT
+            i.id,
+            (
+                a.id,
+                a.first_name,
+                a.last_name,
+                a.email,
+                a.image
+            ) AS "admin!: ShortUser",
+            i.user_id,
+            i.reason,
+            i.ip,
+            i.user_agent,
+            i.created,
+            i.expires_at,
+            i.ended_at,
+            i.ended_by
+        FROM
+            impersonations i
+        INNER JOIN
+            users a
+        ON
+            i.admin_id = a.id
+        WHERE
+            ($1::UUID IS NULL OR i.admin_id = $1)
+        AND
+            ($2::UUID IS NULL OR i.user_id = $2)
+        ORDER BY
+            i.created DESC
+        LIMIT
+            $3
+        "#,
+        params.admin_id,
+        params.user_id,
+        params.limit.unwrap_or(50).clamp(1, 500)
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(impersonations))
+}
+
+// Same level rule as editing a user, you can only act as users below your own role
+
+pub async fn create(
+    _: Require<UserImpersonate>,
+    Extension(user): Extension<User>,
+    current: Option<Extension<Impersonation>>,
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    cookie_jar: CookieJar,
+    Json(body): Json<NewImpersonation>,
+) -> Result<impl IntoResponse, ApiError> {
+    if current.is_some() {
+        return Err(ApiError::Forbidden(ForbiddenReason::Impersonating));
+    }
+
+    if user.service_account {
+        return Err(ApiError::Forbidden(ForbiddenReason::ServiceAccount));
+    }
+
+    let target_user = user_from_id!(body.user_id).fetch_one(&app_state.db).await?;
+
+    if target_user.role.level <= user.role.level {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    user.check_facility(
+        target_user
+            .facility
+            .as_ref()
+            .and_then(|facility| facility.id),
+    )?;
+
+    if !target_user.active {
+        return Err(ApiError::Forbidden(ForbiddenReason::AccountDeactivated));
+    }
+
+    if target_user.service_account {
+        return Err(ApiError::Forbidden(ForbiddenReason::ServiceAccount));
+    }
+
+    let minutes = body
+        .minutes
+        .unwrap_or(DEFAULT_IMPERSONATION_MINUTES)
+        .clamp(1, MAX_IMPERSONATION_MINUTES);
+
+    let now = chrono::Utc::now();
+    let expires_at = now + chrono::Duration::minutes(minutes);
+
+    let impersonation_id = query_scalar!(
+        r#"
+        INSERT INTO
+            impersonations
+        (
+            admin_id,
+            user_id,
+            reason,
+            ip,
+            user_agent,
+            expires_at
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            $6
+        )
+        RETURNING
+            id
+        "#,
+        user.id,
+        target_user.id,
+        body.reason,
+        client.ip,
+        client.user_agent,
+        expires_at
+    )
+    .fetch_one(&app_state.db)
+    .await?;
+
+    let impersonation = active_impersonation(&app_state.db, impersonation_id, target_user.id)
+        .await?
+        .ok_or_else(|| {
+            ApiError::GeneralOversight("Created impersonation wasn't active".to_owned())
+        })?;
+
+    let claims = TokenClaims {
+        sub: target_user.id.to_string(),
+        iat: now.timestamp() as usize,
+        exp: expires_at.timestamp() as usize,
+        impersonation: Some(impersonation_id),
+    };
+
+    let token = app_state.keys.encode(&claims)?;
+
+    let mut cookies: Vec<(HeaderName, String)> = vec![(
+        header::SET_COOKIE,
+        Cookie::build(("token", token))
+            .path("/")
+            .max_age(time::Duration::minutes(minutes))
+            .same_site(SameSite::Lax)
+            .http_only(true)
+            .to_string(),
+    )];
+
+    // Keep the administrators own session around so it can be restored when they stop
+
+    if let Some(admin_token) = cookie_jar.get("token") {
+        cookies.push((
+            header::SET_COOKIE,
+            Cookie::build(("admin_token", admin_token.value().to_owned()))
+                .path("/")
+                .max_age(time::Duration::minutes(app_state.env.jwt_expires_in))
+                .same_site(SameSite::Lax)
+                .http_only(true)
+                .to_string(),
+        ));
+    }
+
+    info!(
+        impersonation = %impersonation_id,
+        admin = %user.id,
+        user = %target_user.id,
+        minutes,
+        "Started impersonation"
+    );
+
+    Ok((
+        AppendHeaders(cookies),
+        (StatusCode::CREATED, Json(impersonation)),
+    ))
+}
+
+// With an id another session is revoked, without one the current session is ended
+
+pub async fn delete(
+    Extension(user): Extension<User>,
+    current: Option<Extension<Impersonation>>,
+    State(app_state): State<Arc<AppState>>,
+    cookie_jar: CookieJar,
+    Query(params): Query<DeleteImpersonation>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut cookies: Vec<(HeaderName, String)> = Vec::new();
+
+    let result = match (params.id, current) {
+        (Some(id), None) => {
+            if !user.role.has::<UserImpersonate>() {
+                return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+            }
+
+            let admin = query!(
+                r#"
+                SELECT
+                    a.id,
+                    r.level
+                FROM
+                    impersonations i
+                INNER JOIN
+                    users a
+                ON
+                    i.admin_id = a.id
+                INNER JOIN
+                    roles r
+                ON
+                    a.role = r.id
+                WHERE
+                    i.id = $1
+                "#,
+                id
+            )
+            .fetch_one(&app_state.db)
+            .await?;
+
+            if admin.id != user.id && admin.level <= user.role.level {
+                return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+            }
+
+            info!(impersonation = %id, revoked_by = %user.id, "Revoked impersonation");
+
+            query!(
+                r#"
+                UPDATE
+                    impersonations
+                SET
+                    ended_at = NOW(),
+                    ended_by = $2
+                WHERE
+                    id = $1
+                AND
+                    ended_at IS NULL
+                "#,
+                id,
+                user.id
+            )
+            .execute(&app_state.db)
+            .await?
+        }
+        (None, Some(Extension(impersonation))) => {
+            let token_cookie = match cookie_jar.get("admin_token") {
+                Some(admin_token) => Cookie::build(("token", admin_token.value().to_owned()))
+                    .path("/")
+                    .max_age(time::Duration::minutes(app_state.env.jwt_expires_in))
+                    .same_site(SameSite::Lax)
+                    .http_only(true)
+                    .to_string(),
+                None => Cookie::build(("token", ""))
+                    .path("/")
+                    .max_age(time::Duration::hours(-1))
+                    .same_site(SameSite::Lax)
+                    .http_only(true)
+                    .to_string(),
+            };
+
+            let admin_token_cookie = Cookie::build(("admin_token", ""))
+                .path("/")
+                .max_age(time::Duration::hours(-1))
+                .same_site(SameSite::Lax)
+                .http_only(true)
+                .to_string();
+
+            cookies.push((header::SET_COOKIE, token_cookie));
+            cookies.push((header::SET_COOKIE, admin_token_cookie));
+
+            info!(impersonation = %impersonation.id, user = %user.id, "Stopped impersonation");
+
+            query!(
+                r#"
+                UPDATE
+                    impersonations
+                SET
+                    ended_at = NOW(),
+                    ended_by = admin_id
+                WHERE
+                    id = $1
+                "#,
+                impersonation.id
+            )
+            .execute(&app_state.db)
+            .await?
+        }
+        (Some(_), Some(_)) => return Err(ApiError::Forbidden(ForbiddenReason::Impersonating)),
+        (None, None) => return Ok((AppendHeaders(cookies), StatusCode::NOT_FOUND)),
+    };
+
+    match result.rows_affected() {
+        1 => Ok((AppendHeaders(cookies), StatusCode::NO_CONTENT)),
+        _ => Ok((AppendHeaders(cookies), StatusCode::NOT_FOUND)),
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::users::models::ShortUser;
+
+pub const DEFAULT_IMPERSONATION_MINUTES: i64 = 15;
+pub const MAX_IMPERSONATION_MINUTES: i64 = 60;
+
+#[derive(Serialize, Clone)]
+pub struct Impersonation {
+    pub id: Uuid,
+    pub admin: ShortUser,
+    pub user_id: Uuid,
+    pub reason: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub ended_by: Option<Uuid>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryImpersonations {
+    pub admin_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewImpersonation {
+    pub user_id: Uuid,
+    pub reason: Option<String>,
+    pub minutes: Option<i64>,
+}
+
+// Delete, without an id the current session is ended
+
+#[derive(Deserialize)]
+pub struct DeleteImpersonation {
+    pub id: Option<Uuid>,
+}
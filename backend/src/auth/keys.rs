@@ -0,0 +1,217 @@
+use std::{fs, path::Path};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::{pkcs8::DecodePublicKey, VerifyingKey};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rsa::{traits::PublicKeyParts, RsaPublicKey};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{config::Config, utils::errors::ApiError};
+
+#[derive(Clone)]
+struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    jwk: Jwk,
+}
+
+///
+/// Keys used to sign and verify session tokens
+///
+/// Every `<kid>.pub.pem` in JWT_KEYS_DIR is trusted for verification, its
+/// `<kid>.key.pem` is only needed while it is the active key. Rotating means
+/// adding a new pair, pointing JWT_ACTIVE_KID at it and removing the old pair
+/// once JWT_EXPIRED_IN has passed.
+///
+/// Tokens without a kid are signed with JWT_SECRET. Once an active key is set
+/// they are only accepted while JWT_SECRET_FALLBACK is "true", so sessions
+/// issued before the switch can run out and the secret can then be retired.
+///
+#[derive(Clone)]
+pub struct KeyRing {
+    keys: Vec<SigningKey>,
+    active: Option<usize>,
+    secret: String,
+    secret_fallback: bool,
+}
+
+impl KeyRing {
+    pub fn init(config: &Config) -> KeyRing {
+        let keys = match &config.jwt_keys_dir {
+            Some(dir) => load_keys(Path::new(dir)),
+            None => Vec::new(),
+        };
+
+        let active = config.jwt_active_kid.as_ref().map(|kid| {
+            let index = keys
+                .iter()
+                .position(|key| &key.kid == kid)
+                .expect("JWT_ACTIVE_KID must match a key in JWT_KEYS_DIR");
+
+            if keys[index].encoding.is_none() {
+                panic!("JWT_ACTIVE_KID must have a private key");
+            }
+
+            index
+        });
+
+        KeyRing {
+            keys,
+            active,
+            secret: config.jwt_secret.to_owned(),
+            secret_fallback: active.is_none() || config.jwt_secret_fallback,
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, ApiError> {
+        let active_key = self.active.map(|index| &self.keys[index]);
+
+        let token = match active_key {
+            Some(SigningKey {
+                kid,
+                algorithm,
+                encoding: Some(encoding),
+                ..
+            }) => {
+                let mut header = Header::new(*algorithm);
+                header.kid = Some(kid.to_owned());
+                encode(&header, claims, encoding)?
+            }
+            _ => encode(
+                &Header::default(),
+                claims,
+                &EncodingKey::from_secret(self.secret.as_ref()),
+            )?,
+        };
+
+        Ok(token)
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<T, ApiError> {
+        let header = decode_header(token)?;
+
+        let claims = match header.kid {
+            Some(kid) => {
+                let key = self
+                    .keys
+                    .iter()
+                    .find(|key| key.kid == kid)
+                    .ok_or(ApiError::Unauthorized)?;
+
+                decode::<T>(token, &key.decoding, &Validation::new(key.algorithm))?.claims
+            }
+            None if self.secret_fallback => {
+                decode::<T>(
+                    token,
+                    &DecodingKey::from_secret(self.secret.as_ref()),
+                    &Validation::default(),
+                )?
+                .claims
+            }
+            None => return Err(ApiError::Unauthorized),
+        };
+
+        Ok(claims)
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+fn load_keys(dir: &Path) -> Vec<SigningKey> {
+    let entries = fs::read_dir(dir).expect("Can't read JWT_KEYS_DIR");
+
+    let mut keys = Vec::new();
+
+    for entry in entries {
+        let path = entry.expect("Can't read JWT_KEYS_DIR").path();
+
+        let kid = match path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".pub.pem"))
+        {
+            Some(kid) => kid.to_owned(),
+            None => continue,
+        };
+
+        let public_pem = fs::read_to_string(&path).expect("Can't read public key");
+        let private_pem = fs::read_to_string(dir.join(format!("{kid}.key.pem"))).ok();
+
+        keys.push(load_key(kid, &public_pem, private_pem));
+    }
+
+    keys
+}
+
+fn load_key(kid: String, public_pem: &str, private_pem: Option<String>) -> SigningKey {
+    let (algorithm, key_algorithm, parameters) =
+        if let Ok(public_key) = RsaPublicKey::from_public_key_pem(public_pem) {
+            (
+                Algorithm::RS256,
+                KeyAlgorithm::RS256,
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be()),
+                    e: URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be()),
+                }),
+            )
+        } else if let Ok(public_key) = VerifyingKey::from_public_key_pem(public_pem) {
+            (
+                Algorithm::EdDSA,
+                KeyAlgorithm::EdDSA,
+                AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(public_key.as_bytes()),
+                }),
+            )
+        } else {
+            panic!("Key {kid} is neither an RSA nor an Ed25519 public key");
+        };
+
+    let (encoding, decoding) = match algorithm {
+        Algorithm::EdDSA => (
+            private_pem.map(|pem| {
+                EncodingKey::from_ed_pem(pem.as_bytes()).expect("Invalid Ed25519 private key")
+            }),
+            DecodingKey::from_ed_pem(public_pem.as_bytes()).expect("Invalid Ed25519 public key"),
+        ),
+        _ => (
+            private_pem.map(|pem| {
+                EncodingKey::from_rsa_pem(pem.as_bytes()).expect("Invalid RSA private key")
+            }),
+            DecodingKey::from_rsa_pem(public_pem.as_bytes()).expect("Invalid RSA public key"),
+        ),
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.to_owned()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+
+    SigningKey {
+        kid,
+        algorithm,
+        encoding,
+        decoding,
+        jwk,
+    }
+}
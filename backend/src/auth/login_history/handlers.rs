@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use sqlx::{query, query_as, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::UserView,
+    machines::facilities::Facility,
+    user_from_id,
+    users::{models::User, roles::models::Role},
+    utils::errors::{ApiError, ForbiddenReason},
+    AppState,
+};
+
+use super::models::{ClientInfo, LoginAttempt, LoginMethod, QueryLoginHistory};
+
+// Successful attempts also move users.last_login forward
+
+pub async fn record_login(
+    db: &PgPool,
+    user_id: Uuid,
+    method: LoginMethod,
+    success: bool,
+    client: &ClientInfo,
+) -> Result<(), ApiError> {
+    query!(
+        r#"
+        INSERT INTO
+            login_history
+        (
+            user_id,
+            method,
+            success,
+            ip,
+            user_agent
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5
+        )
+        "#,
+        user_id,
+        method as LoginMethod,
+        success,
+        client.ip,
+        client.user_agent
+    )
+    .execute(db)
+    .await?;
+
+    if success {
+        query!(
+            r#"UPDATE users SET last_login = NOW() WHERE id = $1"#,
+            user_id
+        )
+        .execute(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn index(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryLoginHistory>,
+) -> Result<Json<Vec<LoginAttempt>>, ApiError> {
+    let user_id = params.user_id.unwrap_or(user.id);
+
+    // Others need the same reach as for their details and a role below the own one
+
+    if user_id != user.id {
+        if !user.role.has::<UserView>() {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+
+        let target_user = user_from_id!(user_id).fetch_one(&app_state.db).await?;
+
+        user.check_facility(target_user.facility.and_then(|facility| facility.id))?;
+
+        if target_user.role.level <= user.role.level {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+    }
+
+    let attempts = query_as!(
+        LoginAttempt,
+        r#"
+        SELECT
+            lh.id,
+            lh.user_id,
+            lh.method AS "method: LoginMethod",
+            lh.success,
+            lh.ip,
+            lh.user_agent,
+            lh.created
+        FROM
+            login_history lh
+        WHERE
+            lh.user_id = $1
+        ORDER BY
+            lh.created DESC
+        LIMIT
+            $2
+        "#,
+        user_id,
+        params.limit.unwrap_or(50).clamp(1, 500)
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(attempts))
+}
@@ -0,0 +1,66 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
+use uuid::Uuid;
+
+#[derive(Serialize, Type, Clone, Copy, Debug)]
+#[sqlx(type_name = "login_method", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum LoginMethod {
+    Password,
+    Otp,
+    Oidc,
+}
+
+#[derive(Serialize)]
+pub struct LoginAttempt {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub method: LoginMethod,
+    pub success: bool,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+// Where a login attempt came from
+
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|user_agent| user_agent.to_str().ok())
+            .map(|user_agent| user_agent.chars().take(512).collect());
+
+        Ok(ClientInfo { ip, user_agent })
+    }
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryLoginHistory {
+    pub user_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
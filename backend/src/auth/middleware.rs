@@ -8,20 +8,97 @@ use axum::{
     response::Response,
 };
 
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use axum_extra::extract::cookie::CookieJar;
-use jsonwebtoken::{decode, DecodingKey, Validation};
-use sqlx::query_as;
+use sqlx::{query, query_as};
+use tracing::info;
 use uuid::Uuid;
 
 use crate::{
-    auth::models::TokenClaims,
+    auth::{impersonation::active_impersonation, models::TokenClaims},
     machines::facilities::Facility,
     user_from_id,
-    users::{models::User, roles::models::Role},
+    users::{
+        api_keys::{models::API_KEY_PREFIX, ApiKey},
+        models::User,
+        roles::models::Role,
+    },
     utils::errors::{ApiError, ForbiddenReason},
     AppState,
 };
 
+// Api keys look like ssk_<prefix>_<secret> where only the secret is hashed
+
+async fn user_from_api_key(app_state: &AppState, key: &str) -> Result<User, ApiError> {
+    let (prefix, secret) = key.split_once('_').ok_or(ApiError::Unauthorized)?;
+
+    let api_key = query_as!(
+        ApiKey,
+        r#"
+        SELECT
+            ak.id,
+            ak.user_id,
+            ak.name,
+            ak.prefix,
+            ak.hash,
+            ak.revoked,
+            ak.created,
+            ak.expires_at,
+            ak.last_used,
+            api_key_permission_keys(ak.id) AS "permissions!"
+        FROM
+            api_keys ak
+        WHERE
+            ak.prefix = $1
+        "#,
+        prefix
+    )
+    .fetch_optional(&app_state.db)
+    .await?
+    .ok_or(ApiError::Unauthorized)?;
+
+    let keys_match = match PasswordHash::new(&api_key.hash) {
+        Ok(stored_hash) => Argon2::default()
+            .verify_password(secret.as_bytes(), &stored_hash)
+            .is_ok(),
+        Err(_) => false,
+    };
+
+    if !keys_match {
+        return Err(ApiError::Unauthorized);
+    }
+
+    if api_key.revoked {
+        return Err(ApiError::Forbidden(ForbiddenReason::ApiKeyRevoked));
+    }
+
+    if api_key
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= chrono::Utc::now())
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::ApiKeyExpired));
+    }
+
+    let mut user: User = user_from_id!(api_key.user_id)
+        .fetch_one(&app_state.db)
+        .await?;
+
+    if !user.service_account {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    query!(
+        r#"UPDATE api_keys SET last_used = NOW() WHERE id = $1"#,
+        api_key.id
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    user.role = api_key.restrict(user.role);
+
+    Ok(user)
+}
+
 pub async fn auth(
     cookie_jar: CookieJar,
     State(app_state): State<Arc<AppState>>,
@@ -46,16 +123,37 @@ pub async fn auth(
 
     let token = token.ok_or_else(|| ApiError::Unauthorized)?;
 
-    let claims = decode::<TokenClaims>(
-        &token,
-        &DecodingKey::from_secret(app_state.env.jwt_secret.as_ref()),
-        &Validation::default(),
-    )?
-    .claims;
+    let user = match token.strip_prefix(API_KEY_PREFIX) {
+        Some(key) => user_from_api_key(&app_state, key).await?,
+        None => {
+            let claims = app_state.keys.decode::<TokenClaims>(&token)?;
+
+            let user_id = Uuid::parse_str(&claims.sub)?;
+
+            let user = user_from_id!(user_id).fetch_one(&app_state.db).await?;
+
+            // Every request made while acting as someone else ends up in the logs
+
+            if let Some(impersonation_id) = claims.impersonation {
+                let impersonation = active_impersonation(&app_state.db, impersonation_id, user_id)
+                    .await?
+                    .ok_or(ApiError::Unauthorized)?;
+
+                info!(
+                    impersonation = %impersonation.id,
+                    admin = ?impersonation.admin.id,
+                    user = %user_id,
+                    method = %req.method(),
+                    uri = %req.uri(),
+                    "Impersonated request"
+                );
 
-    let user_id = Uuid::parse_str(&claims.sub)?;
+                req.extensions_mut().insert(impersonation);
+            }
 
-    let user: User = user_from_id!(user_id).fetch_one(&app_state.db).await?;
+            user
+        }
+    };
 
     if !user.active {
         Err(ApiError::Forbidden(ForbiddenReason::AccountDeactivated))?
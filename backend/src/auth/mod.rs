@@ -1,10 +1,17 @@
 pub mod handlers;
+pub mod impersonation;
+pub mod keys;
+pub mod login_history;
 pub mod middleware;
 pub mod models;
+pub mod oidc;
+pub mod permissions;
 
+pub use handlers::jwks;
 pub use handlers::login_initiate;
 pub use handlers::login_otp;
 pub use handlers::login_password;
 pub use handlers::logout;
 pub use handlers::me;
+pub use handlers::update_me;
 pub use middleware::auth;
@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 use validator::Validate;
 
+use crate::{users::models::User, utils::db::Nullable};
+
+use super::impersonation::models::Impersonation;
+
 // Struct for jwt
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -8,13 +13,58 @@ pub struct TokenClaims {
     pub sub: String,
     pub iat: usize,
     pub exp: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonation: Option<Uuid>,
+}
+
+// The logged in user, with the session behind it when an administrator is acting as them
+
+#[derive(Serialize)]
+pub struct Me {
+    #[serde(flatten)]
+    pub user: User,
+    pub impersonation: Option<Impersonation>,
+    pub preferences: Preferences,
+}
+
+#[derive(Serialize)]
+pub struct Preferences {
+    pub language: String,
+    pub notifications: NotificationPreferences,
+}
+
+#[derive(Serialize)]
+pub struct NotificationPreferences {
+    pub task_assigned: bool,
+}
+
+// The fields a user may change on their own account, role, active flag and facility
+// stay with users::update
+
+#[derive(Deserialize)]
+pub struct UpdateMe {
+    #[serde(default)]
+    pub phone: Nullable<String>,
+    #[serde(default)]
+    pub occupation: Nullable<String>,
+    pub language: Option<String>,
+    #[serde(default)]
+    pub notifications: UpdateNotificationPreferences,
+}
+
+#[derive(Deserialize, Default)]
+pub struct UpdateNotificationPreferences {
+    pub task_assigned: Option<bool>,
 }
 
 // Temporary jwt token that is used to enable pwl login
 
+pub const LOGIN_AUDIENCE: &str = "login";
+
 #[derive(Serialize, Deserialize)]
 pub struct LoginToken {
     pub sub: String,
+    pub aud: String,
     pub iat: usize,
     pub exp: usize,
     pub hash: String,
@@ -0,0 +1,382 @@
+use std::{str::FromStr, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{AppendHeaders, IntoResponse, Redirect},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header, encode, errors::ErrorKind, jwk::JwkSet, Algorithm, DecodingKey,
+    EncodingKey, Header, Validation,
+};
+use rand_core::{OsRng, RngCore};
+use reqwest::{Client, Url};
+use sha2::{Digest, Sha256};
+use sqlx::{query_as, query_scalar, PgPool};
+
+use crate::{
+    auth::{
+        login_history::{
+            models::{ClientInfo, LoginMethod},
+            record_login,
+        },
+        models::TokenClaims,
+    },
+    config::OidcConfig,
+    machines::facilities::Facility,
+    user_from_id,
+    users::{models::User, roles::models::Role},
+    utils::errors::{ApiError, ForbiddenReason},
+    AppState,
+};
+
+use super::models::{
+    IdTokenClaims, OidcCallback, OidcLoginToken, ProviderMetadata, TokenResponse,
+    ID_TOKEN_ALGORITHMS, OIDC_LOGIN_AUDIENCE,
+};
+
+fn random_string(length: usize) -> String {
+    let mut buffer = vec![0u8; length];
+
+    OsRng.fill_bytes(&mut buffer);
+
+    URL_SAFE_NO_PAD.encode(buffer)
+}
+
+// Provisioned users can't set a password, so the default role has to work without one
+
+pub async fn check_default_role(db: &PgPool, oidc: Option<&OidcConfig>) {
+    let Some(role_id) = oidc.and_then(|oidc| oidc.default_role) else {
+        return;
+    };
+
+    let has_password = query_scalar!("SELECT has_password FROM roles WHERE id = $1", role_id)
+        .fetch_optional(db)
+        .await
+        .expect("Can't read OIDC_DEFAULT_ROLE");
+
+    match has_password {
+        None => panic!("OIDC_DEFAULT_ROLE must be an existing role"),
+        Some(true) => panic!("OIDC_DEFAULT_ROLE must be a role without a password"),
+        Some(false) => {}
+    }
+}
+
+fn oidc_config(app_state: &AppState) -> Result<&OidcConfig, ApiError> {
+    app_state
+        .env
+        .oidc
+        .as_ref()
+        .ok_or(ApiError::Forbidden(ForbiddenReason::SsoDisabled))
+}
+
+pub(super) async fn discover(
+    http: &Client,
+    oidc: &OidcConfig,
+) -> Result<ProviderMetadata, ApiError> {
+    let metadata = http
+        .get(format!(
+            "{}/.well-known/openid-configuration",
+            oidc.issuer_url
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<ProviderMetadata>()
+        .await?;
+
+    Ok(metadata)
+}
+
+// Redeems the authorization code and validates the returned id token
+
+pub(super) async fn id_token_claims(
+    http: &Client,
+    oidc: &OidcConfig,
+    metadata: &ProviderMetadata,
+    code: &str,
+    verifier: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, ApiError> {
+    let token_response = http
+        .post(&metadata.token_endpoint)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &oidc.redirect_url),
+            ("client_id", &oidc.client_id),
+            ("client_secret", &oidc.client_secret),
+            ("code_verifier", verifier),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<TokenResponse>()
+        .await?;
+
+    let jwks = http
+        .get(&metadata.jwks_uri)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<JwkSet>()
+        .await?;
+
+    let id_header = decode_header(&token_response.id_token)?;
+
+    let jwk = match &id_header.kid {
+        Some(kid) => jwks.find(kid),
+        None => jwks.keys.first(),
+    }
+    .ok_or(ApiError::Unauthorized)?;
+
+    // The header comes from whoever sent the token, so the key decides the algorithm
+    // and only asymmetric ones are accepted
+
+    let algorithm = match jwk.common.key_algorithm {
+        Some(key_algorithm) => Algorithm::from_str(&key_algorithm.to_string())?,
+        None => id_header.alg,
+    };
+
+    if algorithm != id_header.alg || !ID_TOKEN_ALGORITHMS.contains(&algorithm) {
+        return Err(ApiError::InvalidToken(ErrorKind::InvalidAlgorithm.into()));
+    }
+
+    let mut validation = Validation::new(algorithm);
+    validation.set_audience(&[&oidc.client_id]);
+    validation.set_issuer(&[&metadata.issuer]);
+
+    let claims = decode::<IdTokenClaims>(
+        &token_response.id_token,
+        &DecodingKey::from_jwk(jwk)?,
+        &validation,
+    )?
+    .claims;
+
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(ApiError::Forbidden(ForbiddenReason::InvalidLoginState));
+    }
+
+    // Users are matched by email, so an unverified or unconfirmed address can't be trusted
+
+    if claims.email_verified != Some(true) {
+        return Err(ApiError::Forbidden(ForbiddenReason::EmailNotVerified));
+    }
+
+    Ok(claims)
+}
+
+pub async fn login_oidc(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let oidc = oidc_config(&app_state)?;
+
+    let metadata = discover(&app_state.http, oidc).await?;
+
+    let state = random_string(16);
+    let nonce = random_string(16);
+    let verifier = random_string(32);
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+    let url = Url::parse_with_params(
+        &metadata.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", &oidc.client_id),
+            ("redirect_uri", &oidc.redirect_url),
+            ("scope", "openid email profile"),
+            ("state", &state),
+            ("nonce", &nonce),
+            ("code_challenge", &challenge),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|err| {
+        ApiError::GeneralOversight(format!("Invalid authorization endpoint, error: {err}"))
+    })?;
+
+    let now = chrono::Utc::now();
+    let iat = now.timestamp() as usize;
+    let exp = (now + chrono::Duration::minutes(10)).timestamp() as usize;
+
+    let claims = OidcLoginToken {
+        aud: OIDC_LOGIN_AUDIENCE.to_owned(),
+        state,
+        nonce,
+        verifier,
+        iat,
+        exp,
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(app_state.env.jwt_pwl_secret.as_ref()),
+    )?;
+
+    let cookie = Cookie::build(("oidc_token", token))
+        .path("/")
+        .max_age(time::Duration::minutes(10))
+        .same_site(SameSite::Lax)
+        .http_only(true)
+        .to_string();
+
+    Ok((
+        AppendHeaders([(header::SET_COOKIE, cookie)]),
+        Redirect::to(url.as_str()),
+    ))
+}
+
+pub async fn login_oidc_callback(
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    cookie_jar: CookieJar,
+    Query(params): Query<OidcCallback>,
+) -> Result<impl IntoResponse, ApiError> {
+    let oidc = oidc_config(&app_state)?;
+
+    let token = cookie_jar
+        .get("oidc_token")
+        .map(|cookie| cookie.value().to_string());
+
+    let token = token.ok_or_else(|| ApiError::Unauthorized)?;
+
+    let mut validation = Validation::default();
+    validation.set_audience(&[OIDC_LOGIN_AUDIENCE]);
+
+    let login = decode::<OidcLoginToken>(
+        &token,
+        &DecodingKey::from_secret(app_state.env.jwt_pwl_secret.as_ref()),
+        &validation,
+    )?
+    .claims;
+
+    if login.state != params.state {
+        return Err(ApiError::Forbidden(ForbiddenReason::InvalidLoginState));
+    }
+
+    let metadata = discover(&app_state.http, oidc).await?;
+
+    let claims = id_token_claims(
+        &app_state.http,
+        oidc,
+        &metadata,
+        &params.code,
+        &login.verifier,
+        &login.nonce,
+    )
+    .await?;
+
+    let email = claims
+        .email
+        .ok_or(ApiError::Forbidden(ForbiddenReason::EmailNotVerified))?
+        .to_lowercase();
+
+    let user_id = query_scalar!("SELECT id FROM users WHERE email = $1", email)
+        .fetch_optional(&app_state.db)
+        .await?;
+
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => {
+            let role_id = oidc
+                .default_role
+                .ok_or(ApiError::Forbidden(ForbiddenReason::UnknownAccount))?;
+
+            // The role can have been changed since startup, provisioned users never have a password
+
+            let has_password =
+                query_scalar!("SELECT has_password FROM roles WHERE id = $1", role_id)
+                    .fetch_optional(&app_state.db)
+                    .await?;
+
+            if has_password != Some(false) {
+                return Err(ApiError::Forbidden(ForbiddenReason::SsoRoleUnusable));
+            }
+
+            let first_name = claims
+                .given_name
+                .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_owned());
+
+            query_scalar!(
+                r#"
+                INSERT INTO
+                    users
+                (
+                    first_name,
+                    last_name,
+                    email,
+                    role
+                )
+                VALUES
+                (
+                    $1,
+                    $2,
+                    $3,
+                    $4
+                )
+                RETURNING
+                    id
+                "#,
+                first_name,
+                claims.family_name.unwrap_or_default(),
+                email,
+                role_id
+            )
+            .fetch_one(&app_state.db)
+            .await?
+        }
+    };
+
+    let user = user_from_id!(user_id).fetch_one(&app_state.db).await?;
+
+    if !user.active {
+        record_login(&app_state.db, user.id, LoginMethod::Oidc, false, &client).await?;
+        return Err(ApiError::Forbidden(ForbiddenReason::AccountDeactivated));
+    }
+
+    if user.service_account {
+        return Err(ApiError::Forbidden(ForbiddenReason::ServiceAccount));
+    }
+
+    record_login(&app_state.db, user.id, LoginMethod::Oidc, true, &client).await?;
+
+    let now = chrono::Utc::now();
+    let iat = now.timestamp() as usize;
+    let exp = (now + chrono::Duration::minutes(app_state.env.jwt_expires_in)).timestamp() as usize;
+    let claims = TokenClaims {
+        sub: user.id.to_string(),
+        iat,
+        exp,
+        impersonation: None,
+    };
+
+    let token = app_state.keys.encode(&claims)?;
+
+    let token_cookie = Cookie::build(("token", token))
+        .path("/")
+        .max_age(time::Duration::minutes(app_state.env.jwt_expires_in))
+        .same_site(SameSite::Lax)
+        .http_only(true)
+        .to_string();
+
+    let oidc_token_cookie = Cookie::build(("oidc_token", ""))
+        .path("/")
+        .max_age(time::Duration::hours(-1))
+        .same_site(SameSite::Lax)
+        .http_only(true)
+        .to_string();
+
+    Ok((
+        AppendHeaders([
+            (header::SET_COOKIE, token_cookie),
+            (header::SET_COOKIE, oidc_token_cookie),
+        ]),
+        Redirect::to("/"),
+    ))
+}
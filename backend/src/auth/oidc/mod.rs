@@ -0,0 +1,9 @@
+pub mod handlers;
+pub mod models;
+
+pub use handlers::check_default_role;
+pub use handlers::login_oidc;
+pub use handlers::login_oidc_callback;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,62 @@
+use jsonwebtoken::Algorithm;
+use serde::{Deserialize, Serialize};
+
+// Subset of the provider metadata at /.well-known/openid-configuration
+
+#[derive(Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+// Signature algorithms accepted for id tokens
+
+pub const ID_TOKEN_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+// Temporary jwt token that carries the pkce verifier between redirect and callback
+
+pub const OIDC_LOGIN_AUDIENCE: &str = "oidc_login";
+
+#[derive(Serialize, Deserialize)]
+pub struct OidcLoginToken {
+    pub aud: String,
+    pub state: String,
+    pub nonce: String,
+    pub verifier: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+// Callback query from the identity provider
+
+#[derive(Deserialize)]
+pub struct OidcCallback {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Deserialize)]
+pub struct TokenResponse {
+    pub id_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct IdTokenClaims {
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub nonce: Option<String>,
+}
@@ -0,0 +1,247 @@
+use axum::{
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::{pkcs8::EncodePrivateKey, SigningKey};
+use jsonwebtoken::{
+    encode,
+    errors::ErrorKind,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, OctetKeyParameters, OctetKeyType, PublicKeyUse,
+    },
+    Algorithm, EncodingKey, Header,
+};
+use reqwest::Client;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::{
+    config::OidcConfig,
+    utils::errors::{ApiError, ForbiddenReason},
+};
+
+use super::handlers::{discover, id_token_claims};
+
+const CLIENT_ID: &str = "service-system";
+const NONCE: &str = "mock-nonce";
+
+const HMAC_SECRET: &[u8] = b"shared-secret";
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+fn sign(algorithm: Algorithm, claims: &Value, key: &EncodingKey) -> String {
+    let mut header = Header::new(algorithm);
+    header.kid = Some("mock".to_owned());
+
+    encode(&header, claims, key).unwrap()
+}
+
+fn sign_eddsa(claims: &Value) -> String {
+    let der = signing_key().to_pkcs8_der().unwrap();
+
+    sign(
+        Algorithm::EdDSA,
+        claims,
+        &EncodingKey::from_ed_der(der.as_bytes()),
+    )
+}
+
+fn eddsa_jwk() -> Jwk {
+    Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(KeyAlgorithm::EdDSA),
+            key_id: Some("mock".to_owned()),
+            ..Default::default()
+        },
+        algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(signing_key().verifying_key().as_bytes()),
+        }),
+    }
+}
+
+async fn mock_idp(claims: impl FnOnce(&str) -> Value) -> OidcConfig {
+    mock_idp_signed(claims, sign_eddsa, eddsa_jwk()).await
+}
+
+// Local identity provider that answers every code with the same id token
+
+async fn mock_idp_signed(
+    claims: impl FnOnce(&str) -> Value,
+    sign: impl FnOnce(&Value) -> String,
+    jwk: Jwk,
+) -> OidcConfig {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+    let id_token = sign(&claims(&issuer));
+
+    let jwks = JwkSet { keys: vec![jwk] };
+
+    let metadata = json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{issuer}/authorize"),
+        "token_endpoint": format!("{issuer}/token"),
+        "jwks_uri": format!("{issuer}/jwks"),
+    });
+
+    let app = Router::new()
+        .route(
+            "/.well-known/openid-configuration",
+            get(|| async move { Json(metadata) }),
+        )
+        .route(
+            "/token",
+            post(|| async move { Json(json!({ "id_token": id_token })) }),
+        )
+        .route("/jwks", get(|| async move { Json(jwks) }));
+
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    OidcConfig {
+        issuer_url: issuer,
+        client_id: CLIENT_ID.to_owned(),
+        client_secret: "mock-secret".to_owned(),
+        redirect_url: "http://localhost/api/login/oidc/callback".to_owned(),
+        default_role: None,
+    }
+}
+
+fn valid_claims(issuer: &str) -> Value {
+    let now = chrono::Utc::now().timestamp();
+
+    json!({
+        "iss": issuer,
+        "aud": CLIENT_ID,
+        "iat": now,
+        "exp": now + 300,
+        "nonce": NONCE,
+        "email": "sso@email.com",
+        "email_verified": true,
+        "given_name": "Single",
+        "family_name": "Sign-on",
+    })
+}
+
+fn without(mut claims: Value, field: &str) -> Value {
+    claims.as_object_mut().unwrap().remove(field);
+    claims
+}
+
+fn with(mut claims: Value, field: &str, value: Value) -> Value {
+    claims[field] = value;
+    claims
+}
+
+async fn login(oidc: &OidcConfig) -> Result<super::models::IdTokenClaims, ApiError> {
+    let http = Client::new();
+
+    let metadata = discover(&http, oidc).await?;
+
+    id_token_claims(&http, oidc, &metadata, "mock-code", "mock-verifier", NONCE).await
+}
+
+#[tokio::test]
+async fn accepts_valid_id_token() {
+    let oidc = mock_idp(valid_claims).await;
+
+    let claims = login(&oidc).await.unwrap();
+
+    assert_eq!(claims.email.as_deref(), Some("sso@email.com"));
+    assert_eq!(claims.given_name.as_deref(), Some("Single"));
+}
+
+#[tokio::test]
+async fn rejects_missing_email_verified() {
+    let oidc = mock_idp(|issuer| without(valid_claims(issuer), "email_verified")).await;
+
+    let result = login(&oidc).await;
+
+    assert!(matches!(
+        result,
+        Err(ApiError::Forbidden(ForbiddenReason::EmailNotVerified))
+    ));
+}
+
+#[tokio::test]
+async fn rejects_unverified_email() {
+    let oidc = mock_idp(|issuer| with(valid_claims(issuer), "email_verified", json!(false))).await;
+
+    let result = login(&oidc).await;
+
+    assert!(matches!(
+        result,
+        Err(ApiError::Forbidden(ForbiddenReason::EmailNotVerified))
+    ));
+}
+
+#[tokio::test]
+async fn rejects_other_nonce() {
+    let oidc = mock_idp(|issuer| with(valid_claims(issuer), "nonce", json!("replayed"))).await;
+
+    let result = login(&oidc).await;
+
+    assert!(matches!(
+        result,
+        Err(ApiError::Forbidden(ForbiddenReason::InvalidLoginState))
+    ));
+}
+
+#[tokio::test]
+async fn rejects_other_audience() {
+    let oidc = mock_idp(|issuer| with(valid_claims(issuer), "aud", json!("other-client"))).await;
+
+    let result = login(&oidc).await;
+
+    assert!(matches!(result, Err(ApiError::InvalidToken(_))));
+}
+
+#[tokio::test]
+async fn rejects_other_issuer() {
+    let oidc = mock_idp(|_| valid_claims("http://attacker.invalid")).await;
+
+    let result = login(&oidc).await;
+
+    assert!(matches!(result, Err(ApiError::InvalidToken(_))));
+}
+
+#[tokio::test]
+async fn rejects_symmetric_algorithm() {
+    // The key doesn't name an algorithm, so the one from the header must still be allowed
+    let jwk = Jwk {
+        common: CommonParameters {
+            key_id: Some("mock".to_owned()),
+            ..Default::default()
+        },
+        algorithm: AlgorithmParameters::OctetKey(OctetKeyParameters {
+            key_type: OctetKeyType::Octet,
+            value: URL_SAFE_NO_PAD.encode(HMAC_SECRET),
+        }),
+    };
+
+    let oidc = mock_idp_signed(
+        valid_claims,
+        |claims| {
+            sign(
+                Algorithm::HS256,
+                claims,
+                &EncodingKey::from_secret(HMAC_SECRET),
+            )
+        },
+        jwk,
+    )
+    .await;
+
+    let result = login(&oidc).await;
+
+    assert!(matches!(
+        result,
+        Err(ApiError::InvalidToken(err)) if *err.kind() == ErrorKind::InvalidAlgorithm
+    ));
+}
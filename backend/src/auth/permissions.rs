@@ -0,0 +1,158 @@
+use std::marker::PhantomData;
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+
+use crate::{
+    users::models::User,
+    utils::errors::{ApiError, ForbiddenReason},
+};
+
+// A resource.action key from the permissions table
+
+pub trait Permission {
+    const KEY: &'static str;
+}
+
+macro_rules! permissions {
+    ($($name:ident => $key:literal),* $(,)?) => {
+        $(
+            pub struct $name;
+
+            impl Permission for $name {
+                const KEY: &'static str = $key;
+            }
+        )*
+    };
+}
+
+permissions! {
+    UserView => "user.view",
+    UserCreate => "user.create",
+    UserEdit => "user.edit",
+    UserDelete => "user.delete",
+    UserImpersonate => "user.impersonate",
+    RoleView => "role.view",
+    RoleCreate => "role.create",
+    RoleEdit => "role.edit",
+    RoleDelete => "role.delete",
+    ApiKeyView => "api_key.view",
+    ApiKeyCreate => "api_key.create",
+    ApiKeyEdit => "api_key.edit",
+    ApiKeyDelete => "api_key.delete",
+    FacilityView => "facility.view",
+    FacilityCreate => "facility.create",
+    FacilityEdit => "facility.edit",
+    FacilityDelete => "facility.delete",
+    MachineView => "machine.view",
+    MachineCreate => "machine.create",
+    MachineEdit => "machine.edit",
+    MachineDelete => "machine.delete",
+    MachineTypeView => "machine_type.view",
+    MachineTypeCreate => "machine_type.create",
+    MachineTypeEdit => "machine_type.edit",
+    MachineTypeDelete => "machine_type.delete",
+    MachineStatusView => "machine_status.view",
+    MachineStatusCreate => "machine_status.create",
+    MachineStatusEdit => "machine_status.edit",
+    MachineStatusDelete => "machine_status.delete",
+    TaskView => "task.view",
+    TaskCreate => "task.create",
+    TaskEdit => "task.edit",
+    TaskDelete => "task.delete",
+    TaskEditOwn => "task.edit_own",
+    TaskDeleteOwn => "task.delete_own",
+    TaskEditStatusAssigned => "task.edit_status_assigned",
+    TaskTypeView => "task_type.view",
+    TaskTypeCreate => "task_type.create",
+    TaskTypeEdit => "task_type.edit",
+    TaskTypeDelete => "task_type.delete",
+    TaskStatusView => "task_status.view",
+    TaskStatusCreate => "task_status.create",
+    TaskStatusEdit => "task_status.edit",
+    TaskStatusDelete => "task_status.delete",
+    ReportView => "report.view",
+    ReportCreate => "report.create",
+    ReportEdit => "report.edit",
+    ReportDelete => "report.delete",
+    ReportEditOwn => "report.edit_own",
+    ReportDeleteOwn => "report.delete_own",
+    ReportTypeView => "report_type.view",
+    ReportTypeCreate => "report_type.create",
+    ReportTypeEdit => "report_type.edit",
+    ReportTypeDelete => "report_type.delete",
+    ReportStatusView => "report_status.view",
+    ReportStatusCreate => "report_status.create",
+    ReportStatusEdit => "report_status.edit",
+    ReportStatusDelete => "report_status.delete",
+    TeamView => "team.view",
+    TeamCreate => "team.create",
+    TeamEdit => "team.edit",
+    TeamDelete => "team.delete",
+    AvailabilityView => "availability.view",
+    AvailabilityCreate => "availability.create",
+    AvailabilityEdit => "availability.edit",
+    AvailabilityDelete => "availability.delete",
+    SkillView => "skill.view",
+    SkillCreate => "skill.create",
+    SkillEdit => "skill.edit",
+    SkillDelete => "skill.delete",
+    ChecklistView => "checklist.view",
+    ChecklistCreate => "checklist.create",
+    ChecklistEdit => "checklist.edit",
+    ChecklistDelete => "checklist.delete",
+    MeasurementView => "measurement.view",
+    MeasurementCreate => "measurement.create",
+    MeasurementEdit => "measurement.edit",
+    MeasurementDelete => "measurement.delete",
+    MaintenanceView => "maintenance.view",
+    MaintenanceCreate => "maintenance.create",
+    MaintenanceEdit => "maintenance.edit",
+    MaintenanceDelete => "maintenance.delete",
+    TimeEntryView => "time_entry.view",
+    TimeEntryCreate => "time_entry.create",
+    TimeEntryEdit => "time_entry.edit",
+    TimeEntryDelete => "time_entry.delete",
+    PartView => "part.view",
+    PartCreate => "part.create",
+    PartEdit => "part.edit",
+    PartDelete => "part.delete",
+    SupplierView => "supplier.view",
+    SupplierCreate => "supplier.create",
+    SupplierEdit => "supplier.edit",
+    SupplierDelete => "supplier.delete",
+    PurchaseRequestView => "purchase_request.view",
+    PurchaseRequestCreate => "purchase_request.create",
+    PurchaseRequestEdit => "purchase_request.edit",
+    PurchaseRequestDelete => "purchase_request.delete",
+    PurchaseRequestApprove => "purchase_request.approve",
+    CostView => "cost.view",
+    CostCreate => "cost.create",
+    CostEdit => "cost.edit",
+    CostDelete => "cost.delete",
+}
+
+///
+/// Rejects the request unless the role of the logged in user holds `P`
+///
+/// # Usage:
+/// pub async fn index(_: Require<MachineView>, ...) -> ...
+///
+pub struct Require<P: Permission>(PhantomData<P>);
+
+#[async_trait]
+impl<P: Permission, S: Send + Sync> FromRequestParts<S> for Require<P> {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user = parts
+            .extensions
+            .get::<User>()
+            .ok_or(ApiError::Unauthorized)?;
+
+        if !user.role.has::<P>() {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+
+        Ok(Require(PhantomData))
+    }
+}
@@ -1,3 +1,5 @@
+use uuid::Uuid;
+
 #[derive(Clone)]
 pub struct Config {
     pub database_url: String,
@@ -5,8 +7,25 @@ pub struct Config {
     pub jwt_pwl_secret: String,
     pub jwt_expires_in: i64,
     pub jwt_maxage: i32,
+    pub jwt_keys_dir: Option<String>,
+    pub jwt_active_kid: Option<String>,
+    pub jwt_secret_fallback: bool,
     pub frontend_url: String,
+    pub public_url: String,
     pub log_path: String,
+    pub upload_path: String,
+    pub oidc: Option<OidcConfig>,
+}
+
+// Single sign-on is only enabled when OIDC_ISSUER_URL is set
+
+#[derive(Clone)]
+pub struct OidcConfig {
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+    pub default_role: Option<Uuid>,
 }
 
 impl Config {
@@ -16,8 +35,14 @@ impl Config {
         let jwt_pwl_secret = std::env::var("JWT_PWL_SECRET").expect("JWT_PWL_SECRET must be set");
         let jwt_expires_in = std::env::var("JWT_EXPIRED_IN").expect("JWT_EXPIRED_IN must be set");
         let jwt_maxage = std::env::var("JWT_MAXAGE").expect("JWT_MAXAGE must be set");
+        let jwt_keys_dir = std::env::var("JWT_KEYS_DIR").ok();
+        let jwt_active_kid = std::env::var("JWT_ACTIVE_KID").ok();
+        let jwt_secret_fallback = std::env::var("JWT_SECRET_FALLBACK").unwrap_or_default();
         let frontend_url = std::env::var("FRONTEND_URL").expect("FRONTEND_URL must be set");
+        let public_url = std::env::var("PUBLIC_URL").unwrap_or_default();
         let log_path = std::env::var("LOG_PATH").expect("LOG_PATH must be set");
+        let upload_path = std::env::var("UPLOAD_PATH").unwrap_or("uploads".to_owned());
+        let oidc = std::env::var("OIDC_ISSUER_URL").ok().map(OidcConfig::init);
         Config {
             database_url,
             jwt_secret,
@@ -28,8 +53,34 @@ impl Config {
             jwt_maxage: jwt_maxage
                 .parse::<i32>()
                 .expect("Could not parse JWT_MAXAGE to i32"),
+            jwt_keys_dir,
+            jwt_active_kid,
+            jwt_secret_fallback: jwt_secret_fallback == "true",
             frontend_url,
+            public_url: public_url.trim_end_matches('/').to_owned(),
             log_path,
+            upload_path,
+            oidc,
+        }
+    }
+}
+
+impl OidcConfig {
+    fn init(issuer_url: String) -> OidcConfig {
+        let client_id = std::env::var("OIDC_CLIENT_ID").expect("OIDC_CLIENT_ID must be set");
+        let client_secret =
+            std::env::var("OIDC_CLIENT_SECRET").expect("OIDC_CLIENT_SECRET must be set");
+        let redirect_url =
+            std::env::var("OIDC_REDIRECT_URL").expect("OIDC_REDIRECT_URL must be set");
+        let default_role = std::env::var("OIDC_DEFAULT_ROLE").ok();
+        OidcConfig {
+            issuer_url: issuer_url.trim_end_matches('/').to_owned(),
+            client_id,
+            client_secret,
+            redirect_url,
+            default_role: default_role.map(|role| {
+                Uuid::parse_str(&role).expect("Could not parse OIDC_DEFAULT_ROLE to uuid")
+            }),
         }
     }
 }
@@ -0,0 +1,352 @@
+use std::{io::Cursor, sync::Arc};
+
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageFormat, ImageReader, Limits,
+};
+use sqlx::{query, query_as, query_scalar};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{MachineEdit, Require, UserEdit},
+    machines::{facilities::Facility, handlers::check_machine_scope},
+    user_from_id,
+    users::{models::User, roles::models::Role},
+    utils::errors::{ApiError, ForbiddenReason, InputInvalidReason},
+    AppState,
+};
+
+use super::models::{
+    DeleteImage, ImageKind, ProcessedImage, QueryImageUpload, QueryServeImage, UploadedImage,
+    IMAGE_SIZE, IMAGE_URI_PREFIX, MAX_IMAGE_DIMENSION, THUMBNAIL_SIZE,
+};
+
+// The format is sniffed from the bytes, the file name and content type of the upload
+// are not trusted
+
+fn process_image(bytes: Vec<u8>) -> Result<ProcessedImage, ApiError> {
+    let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+
+    match reader.format() {
+        Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP | ImageFormat::Gif) => {}
+        _ => return Err(ApiError::InputInvalid(InputInvalidReason::UnsupportedImage)),
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    reader.limits(limits);
+
+    // Phone cameras store rotation as metadata, bake it in before the metadata is dropped
+
+    let mut decoder = reader.into_decoder()?;
+    let orientation = image::ImageDecoder::orientation(&mut decoder)?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let thumbnail = image.resize_to_fill(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Lanczos3);
+
+    if image.width() > IMAGE_SIZE || image.height() > IMAGE_SIZE {
+        image = image.resize(IMAGE_SIZE, IMAGE_SIZE, FilterType::Lanczos3);
+    }
+
+    // Jpeg can't hold transparency, so images with an alpha channel stay png
+
+    let extension = match image.color().has_alpha() {
+        true => "png",
+        false => "jpg",
+    };
+
+    Ok(ProcessedImage {
+        extension,
+        image: encode_image(&image, extension)?,
+        thumbnail: encode_image(&thumbnail, extension)?,
+    })
+}
+
+fn encode_image(image: &DynamicImage, extension: &str) -> Result<Vec<u8>, ApiError> {
+    let mut bytes = Vec::new();
+
+    match extension {
+        "png" => image
+            .to_rgba8()
+            .write_with_encoder(PngEncoder::new(&mut bytes))?,
+        _ => image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, 85))?,
+    }
+
+    Ok(bytes)
+}
+
+fn image_key(kind: ImageKind, file: &str) -> String {
+    format!("{}/{file}", kind.as_str())
+}
+
+fn thumbnail_key(kind: ImageKind, file: &str) -> String {
+    format!("{}/thumbnails/{file}", kind.as_str())
+}
+
+async fn read_upload(mut multipart: Multipart) -> Result<Vec<u8>, ApiError> {
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() == Some("image") {
+            return Ok(field.bytes().await?.to_vec());
+        }
+    }
+
+    Err(ApiError::InputInvalid(InputInvalidReason::NoImageSupplied))
+}
+
+async fn store_image(
+    app_state: &AppState,
+    kind: ImageKind,
+    multipart: Multipart,
+) -> Result<UploadedImage, ApiError> {
+    let bytes = read_upload(multipart).await?;
+
+    let processed = tokio::task::spawn_blocking(move || process_image(bytes))
+        .await
+        .map_err(|error| ApiError::GeneralOversight(error.to_string()))??;
+
+    // Every upload gets a new name, which lets the files be cached forever
+
+    let file = format!("{}.{}", Uuid::new_v4(), processed.extension);
+
+    app_state
+        .storage
+        .put(&thumbnail_key(kind, &file), &processed.thumbnail)
+        .await?;
+    app_state
+        .storage
+        .put(&image_key(kind, &file), &processed.image)
+        .await?;
+
+    let image = format!("{IMAGE_URI_PREFIX}{}", image_key(kind, &file));
+
+    Ok(UploadedImage {
+        thumbnail: format!("{image}?thumbnail=true"),
+        image,
+    })
+}
+
+// Only called once the database no longer points at the image, a leftover file is
+// logged instead of failing the request
+
+async fn remove_image(app_state: &AppState, kind: ImageKind, uri: Option<String>) {
+    let Some(file) = uri.as_deref().and_then(|uri| {
+        uri.strip_prefix(IMAGE_URI_PREFIX)?
+            .strip_prefix(kind.as_str())?
+            .strip_prefix('/')
+    }) else {
+        return;
+    };
+
+    for key in [image_key(kind, file), thumbnail_key(kind, file)] {
+        if let Err(error) = app_state.storage.delete(&key).await {
+            warn!(key, %error, "Couldn't remove replaced image");
+        }
+    }
+}
+
+// Same rules as users::update, anyone may change their own picture
+
+async fn check_user_image(
+    app_state: &AppState,
+    user: &User,
+    target_id: Uuid,
+) -> Result<Option<String>, ApiError> {
+    if target_id == user.id {
+        return Ok(user.image.clone());
+    }
+
+    if !user.role.has::<UserEdit>() {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let target_user = user_from_id!(target_id).fetch_one(&app_state.db).await?;
+
+    if target_user.role.level <= user.role.level {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    user.check_facility(
+        target_user
+            .facility
+            .as_ref()
+            .and_then(|facility| facility.id),
+    )?;
+
+    Ok(target_user.image)
+}
+
+pub async fn upload_user_image(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryImageUpload>,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<UploadedImage>), ApiError> {
+    let previous = check_user_image(&app_state, &user, params.id).await?;
+
+    let uploaded = store_image(&app_state, ImageKind::Users, multipart).await?;
+
+    query!(
+        r#"UPDATE users SET image = $2 WHERE id = $1"#,
+        params.id,
+        uploaded.image
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    remove_image(&app_state, ImageKind::Users, previous).await;
+
+    Ok((StatusCode::CREATED, Json(uploaded)))
+}
+
+pub async fn delete_user_image(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteImage>,
+) -> Result<StatusCode, ApiError> {
+    let previous = check_user_image(&app_state, &user, params.id).await?;
+
+    if previous.is_none() {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+
+    query!(r#"UPDATE users SET image = NULL WHERE id = $1"#, params.id)
+        .execute(&app_state.db)
+        .await?;
+
+    remove_image(&app_state, ImageKind::Users, previous).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Shorthands for the profile page, same as passing your own id
+
+pub async fn upload_my_image(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<UploadedImage>), ApiError> {
+    let id = user.id;
+
+    upload_user_image(
+        Extension(user),
+        State(app_state),
+        Query(QueryImageUpload { id }),
+        multipart,
+    )
+    .await
+}
+
+pub async fn delete_my_image(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<StatusCode, ApiError> {
+    let id = user.id;
+
+    delete_user_image(Extension(user), State(app_state), Query(DeleteImage { id })).await
+}
+
+pub async fn upload_machine_image(
+    _: Require<MachineEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryImageUpload>,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<UploadedImage>), ApiError> {
+    // The connection is given back before the upload is decoded
+
+    let previous = {
+        let mut conn = app_state.db.acquire().await?;
+
+        check_machine_scope(&mut conn, &user, params.id).await?;
+
+        query_scalar!(r#"SELECT image FROM machines WHERE id = $1"#, params.id)
+            .fetch_one(&mut *conn)
+            .await?
+    };
+
+    let uploaded = store_image(&app_state, ImageKind::Machines, multipart).await?;
+
+    query!(
+        r#"UPDATE machines SET image = $2 WHERE id = $1"#,
+        params.id,
+        uploaded.image
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    remove_image(&app_state, ImageKind::Machines, previous).await;
+
+    Ok((StatusCode::CREATED, Json(uploaded)))
+}
+
+pub async fn delete_machine_image(
+    _: Require<MachineEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteImage>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_machine_scope(&mut conn, &user, params.id).await?;
+
+    let previous = query_scalar!(r#"SELECT image FROM machines WHERE id = $1"#, params.id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if previous.is_none() {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+
+    query!(
+        r#"UPDATE machines SET image = NULL WHERE id = $1"#,
+        params.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    remove_image(&app_state, ImageKind::Machines, previous).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn serve(
+    State(app_state): State<Arc<AppState>>,
+    Path((kind, file)): Path<(ImageKind, String)>,
+    Query(params): Query<QueryServeImage>,
+) -> Result<impl IntoResponse, ApiError> {
+    let content_type = match file.rsplit_once('.') {
+        Some((_, "jpg")) => "image/jpeg",
+        Some((_, "png")) => "image/png",
+        _ => return Err(ApiError::StorageError(std::io::ErrorKind::NotFound.into())),
+    };
+
+    let key = match params.thumbnail.unwrap_or(false) {
+        true => thumbnail_key(kind, &file),
+        false => image_key(kind, &file),
+    };
+
+    let bytes = app_state.storage.get(&key).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (
+                header::CACHE_CONTROL,
+                "private, max-age=31536000, immutable",
+            ),
+        ],
+        bytes,
+    ))
+}
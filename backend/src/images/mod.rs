@@ -0,0 +1,10 @@
+pub mod handlers;
+pub mod models;
+
+pub use handlers::delete_machine_image;
+pub use handlers::delete_my_image;
+pub use handlers::delete_user_image;
+pub use handlers::serve;
+pub use handlers::upload_machine_image;
+pub use handlers::upload_my_image;
+pub use handlers::upload_user_image;
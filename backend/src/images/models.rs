@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Uploads are scaled down to fit IMAGE_SIZE and cropped to a square thumbnail
+
+pub const IMAGE_SIZE: u32 = 1024;
+pub const THUMBNAIL_SIZE: u32 = 256;
+pub const MAX_IMAGE_DIMENSION: u32 = 12000;
+pub const MAX_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+pub const IMAGE_URI_PREFIX: &str = "/api/auth/images/";
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageKind {
+    Users,
+    Machines,
+}
+
+impl ImageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageKind::Users => "users",
+            ImageKind::Machines => "machines",
+        }
+    }
+}
+
+pub struct ProcessedImage {
+    pub extension: &'static str,
+    pub image: Vec<u8>,
+    pub thumbnail: Vec<u8>,
+}
+
+// Upload
+
+#[derive(Deserialize)]
+pub struct QueryImageUpload {
+    pub id: Uuid,
+}
+
+#[derive(Serialize)]
+pub struct UploadedImage {
+    pub image: String,
+    pub thumbnail: String,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteImage {
+    pub id: Uuid,
+}
+
+// Serve
+
+#[derive(Deserialize)]
+pub struct QueryServeImage {
+    pub thumbnail: Option<bool>,
+}
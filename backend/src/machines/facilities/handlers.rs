@@ -3,17 +3,16 @@ use std::sync::Arc;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Extension, Json,
+    Json,
 };
 use sqlx::{query, query_as, Postgres, QueryBuilder};
 
 use crate::{
+    auth::permissions::{FacilityCreate, FacilityDelete, FacilityEdit, FacilityView, Require},
     field_vec,
     machines::facilities::Facility,
     update_field,
-    users::models::User,
     utils::{
-        check_permission,
         db::{Field, IntoField},
         errors::{ApiError, InputInvalidReason},
     },
@@ -23,12 +22,10 @@ use crate::{
 use super::models::{NewFacility, QueryFacility, UpdateFacility};
 
 pub async fn details(
-    Extension(user): Extension<User>,
+    _: Require<FacilityView>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryFacility>,
 ) -> Result<Json<Facility>, ApiError> {
-    check_permission(user.role.facility_view)?;
-
     let facility = query_as!(
         Facility,
         r#"
@@ -48,11 +45,9 @@ pub async fn details(
 }
 
 pub async fn index(
-    Extension(user): Extension<User>,
+    _: Require<FacilityView>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<Facility>>, ApiError> {
-    check_permission(user.role.facility_view)?;
-
     let facilities = query_as!(
         Facility,
         r#"
@@ -69,12 +64,10 @@ pub async fn index(
 }
 
 pub async fn create(
-    Extension(user): Extension<User>,
+    _: Require<FacilityCreate>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewFacility>,
 ) -> Result<(StatusCode, Json<Facility>), ApiError> {
-    check_permission(user.role.facility_create)?;
-
     let facility = query_as!(
         Facility,
         r#"
@@ -102,12 +95,10 @@ pub async fn create(
 }
 
 pub async fn update(
-    Extension(user): Extension<User>,
+    _: Require<FacilityEdit>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateFacility>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.facility_edit)?;
-
     let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE facilities SET");
     let mut separated_list = query_builder.separated(",");
 
@@ -136,12 +127,10 @@ pub async fn update(
 }
 
 pub async fn delete(
-    Extension(user): Extension<User>,
+    _: Require<FacilityDelete>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryFacility>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.facility_delete)?;
-
     let result = query!(r#"DELETE FROM facilities WHERE id = $1"#, params.id)
         .execute(&app_state.db)
         .await?;
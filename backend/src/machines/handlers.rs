@@ -5,14 +5,16 @@ use axum::{
     http::StatusCode,
     Extension, Json,
 };
-use sqlx::{query, query_as, Postgres, QueryBuilder};
+use chrono::{Duration, Utc};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
 
 use crate::{
+    auth::permissions::{MachineCreate, MachineDelete, MachineEdit, MachineView, Require},
     field_vec, update_field,
-    users::models::User,
+    users::models::{ShortUser, User},
     utils::{
-        check_permission,
-        db::{Field, IntoField},
+        db::{Field, IntoField, Nullable},
         errors::{ApiError, InputInvalidReason},
     },
     AppState,
@@ -20,18 +22,137 @@ use crate::{
 
 use super::{
     facilities::Facility,
+    locations::handlers::{check_location, location_change, location_facility},
     machine_statuses::MachineStatus,
     machine_types::MachineType,
-    models::{DeleteMachine, Machine, NewMachine, QueryMachine, UpdateMachine},
+    models::{
+        DeleteMachine, Machine, MachineAncestor, MachineMove, MachineNode, NewMachine,
+        QueryMachine, QueryMachineTree, QueryMachines, UpdateMachine,
+    },
 };
 
+// Tasks and reports on a machine belong to the machine's facility
+
+pub async fn check_machine_scope(
+    conn: &mut PgConnection,
+    user: &User,
+    machine_id: Uuid,
+) -> Result<(), ApiError> {
+    if user.facility_scope().is_none() {
+        return Ok(());
+    }
+
+    let facility = query_scalar!(r#"SELECT facility FROM machines WHERE id = $1"#, machine_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    user.check_facility(facility)
+}
+
+// The parent has to be reachable for the user and can't be the machine or one of its
+// components
+
+async fn check_machine_parent(
+    conn: &mut PgConnection,
+    user: &User,
+    machine_id: Uuid,
+    parent: Uuid,
+) -> Result<(), ApiError> {
+    check_machine_scope(conn, user, parent).await?;
+
+    let cycle = query_scalar!(
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT
+                $1::UUID AS id
+            UNION
+            SELECT
+                m.parent
+            FROM
+                machines m
+            INNER JOIN
+                ancestors a
+            ON
+                m.id = a.id
+            WHERE
+                m.parent IS NOT NULL
+        )
+        SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = $2) AS "cycle!"
+        "#,
+        parent,
+        machine_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    match cycle {
+        true => Err(ApiError::InputInvalid(InputInvalidReason::MachineCycle)),
+        false => Ok(()),
+    }
+}
+
+// Components stay in the facility of their parent, so a hierarchy never spans facilities
+
+async fn check_parent_facility(
+    conn: &mut PgConnection,
+    parent: Uuid,
+    facility: Option<Uuid>,
+) -> Result<(), ApiError> {
+    let parent_facility = query_scalar!(r#"SELECT facility FROM machines WHERE id = $1"#, parent)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if parent_facility != facility {
+        return Err(ApiError::InputInvalid(
+            InputInvalidReason::MachineFacilityMismatch,
+        ));
+    }
+
+    Ok(())
+}
+
+async fn record_move(
+    conn: &mut PgConnection,
+    user: &User,
+    machine_id: Uuid,
+    from_location: Option<Uuid>,
+    to_location: Option<Uuid>,
+) -> Result<(), ApiError> {
+    query!(
+        r#"
+        INSERT INTO
+            machine_location_history
+        (
+            machine_id,
+            from_location,
+            to_location,
+            moved_by
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4
+        )
+        "#,
+        machine_id,
+        from_location,
+        to_location,
+        user.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn details(
+    _: Require<MachineView>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryMachine>,
 ) -> Result<Json<Machine>, ApiError> {
-    check_permission(user.role.machine_view)?;
-
     let machine = query_as!(
         Machine,
         r#"
@@ -45,7 +166,8 @@ pub async fn details(
             ) AS "machine_type!: MachineType",
             (
                 ms.id,
-                ms.name
+                ms.name,
+                ms.down
             ) AS "status!: MachineStatus",
             m.created,
             m.edited,
@@ -54,7 +176,9 @@ pub async fn details(
                 f.name,
                 f.address
             ) AS "facility?: Facility",
-            m.image
+            m.image,
+            m.parent,
+            m.location
         FROM
             machines m
         INNER JOIN 
@@ -71,8 +195,11 @@ pub async fn details(
             m.facility = f.id
         WHERE
                 m.id = $1
+        AND
+            ($2::UUID[] IS NULL OR m.facility = ANY($2))
         "#,
-        params.id
+        params.id,
+        user.facility_scope()
     )
     .fetch_one(&app_state.db)
     .await?;
@@ -81,11 +208,11 @@ pub async fn details(
 }
 
 pub async fn index(
+    _: Require<MachineView>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryMachines>,
 ) -> Result<Json<Vec<Machine>>, ApiError> {
-    check_permission(user.role.machine_view)?;
-
     let machines = query_as!(
         Machine,
         r#"
@@ -99,7 +226,8 @@ pub async fn index(
             ) AS "machine_type!: MachineType",
             (
                 ms.id,
-                ms.name
+                ms.name,
+                ms.down
             ) AS "status!: MachineStatus",
             m.created,
             m.edited,
@@ -108,7 +236,9 @@ pub async fn index(
                 f.name,
                 f.address
             ) AS "facility?: Facility",
-            m.image
+            m.image,
+            m.parent,
+            m.location
         FROM
             machines m
         INNER JOIN 
@@ -123,7 +253,13 @@ pub async fn index(
             facilities f
         ON
             m.facility = f.id
+        WHERE
+            ($1::UUID[] IS NULL OR m.facility = ANY($1))
+        AND
+            ($2::UUID IS NULL OR m.location IN (SELECT location_subtree($2)))
         "#,
+        user.facility_scope(),
+        params.location_id
     )
     .fetch_all(&app_state.db)
     .await?;
@@ -132,11 +268,34 @@ pub async fn index(
 }
 
 pub async fn create(
+    _: Require<MachineCreate>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewMachine>,
 ) -> Result<(StatusCode, Json<Machine>), ApiError> {
-    check_permission(user.role.machine_create)?;
+    let mut tx = app_state.db.begin().await?;
+
+    let facility = match (body.facility, body.location, body.parent) {
+        (None, Some(location), _) => Some(location_facility(&mut tx, location).await?),
+        (None, None, Some(parent)) => {
+            query_scalar!(r#"SELECT facility FROM machines WHERE id = $1"#, parent)
+                .fetch_one(&mut *tx)
+                .await?
+        }
+        _ => body.facility,
+    };
+
+    user.check_facility(facility)?;
+
+    if let Some(parent) = body.parent {
+        check_machine_scope(&mut tx, &user, parent).await?;
+
+        check_parent_facility(&mut tx, parent, facility).await?;
+    }
+
+    if let Some(location) = body.location {
+        check_location(&mut tx, location, facility).await?;
+    }
 
     let machine = query_as!(
         Machine,
@@ -152,7 +311,9 @@ pub async fn create(
                 make,
                 machine_type,
                 status,
-                facility
+                facility,
+                parent,
+                location
             )
             VALUES
             (
@@ -160,7 +321,9 @@ pub async fn create(
                 $2,
                 $3,
                 $4,
-                $5
+                $5,
+                $6,
+                $7
             )
             RETURNING *
         )
@@ -174,7 +337,8 @@ pub async fn create(
             ) AS "machine_type!: MachineType",
             (
                 ms.id,
-                ms.name
+                ms.name,
+                ms.down
             ) AS "status!: MachineStatus",
             m.created,
             m.edited,
@@ -183,7 +347,9 @@ pub async fn create(
                 f.name,
                 f.address
             ) AS "facility?: Facility",
-            m.image
+            m.image,
+            m.parent,
+            m.location
         FROM
             new_machine m
         INNER JOIN 
@@ -203,23 +369,108 @@ pub async fn create(
         body.make,
         body.machine_type,
         body.status,
-        body.facility
+        facility,
+        body.parent,
+        body.location
     )
-    .fetch_one(&app_state.db)
+    .fetch_one(&mut *tx)
     .await?;
 
+    if body.location.is_some() {
+        record_move(&mut tx, &user, machine.id, None, body.location).await?;
+    }
+
+    tx.commit().await?;
+
     Ok((StatusCode::CREATED, Json(machine)))
 }
 
 pub async fn update(
+    _: Require<MachineEdit>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateMachine>,
 ) -> Result<Json<Machine>, ApiError> {
-    check_permission(user.role.machine_edit)?;
+    user.check_facility_change(&body.facility)?;
 
     let mut tx = app_state.db.begin().await?;
 
+    check_machine_scope(&mut tx, &user, body.id).await?;
+
+    if let Nullable::Value(parent) = body.parent {
+        check_machine_parent(&mut tx, &user, body.id, parent).await?;
+    }
+
+    let current = query!(
+        r#"SELECT facility, location, parent FROM machines WHERE id = $1 FOR UPDATE"#,
+        body.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let facility = match body.facility {
+        Nullable::Absent => current.facility,
+        Nullable::Null => None,
+        Nullable::Value(facility) => Some(facility),
+    };
+
+    let parent = match body.parent {
+        Nullable::Absent => current.parent,
+        Nullable::Null => None,
+        Nullable::Value(parent) => Some(parent),
+    };
+
+    let moved =
+        !matches!(body.parent, Nullable::Absent) || !matches!(body.facility, Nullable::Absent);
+
+    if let Some(parent) = parent.filter(|_| moved) {
+        check_parent_facility(&mut tx, parent, facility).await?;
+    }
+
+    // Components have to be detached before their parent moves to another facility
+
+    if !matches!(body.facility, Nullable::Absent) {
+        let split = query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT
+                    1
+                FROM
+                    machines m
+                WHERE
+                    m.parent = $1
+                AND
+                    m.facility IS DISTINCT FROM $2
+            ) AS "split!"
+            "#,
+            body.id,
+            facility
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if split {
+            return Err(ApiError::InputInvalid(
+                InputInvalidReason::MachineFacilityMismatch,
+            ));
+        }
+    }
+
+    let location = location_change(
+        &mut tx,
+        current.facility,
+        current.location,
+        &body.facility,
+        body.location,
+    )
+    .await?;
+
+    let new_location = match &location {
+        Nullable::Absent => current.location,
+        Nullable::Null => None,
+        Nullable::Value(location) => Some(*location),
+    };
+
     let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE machines SET");
     let mut separated_list = query_builder.separated(",");
 
@@ -228,7 +479,9 @@ pub async fn update(
         make => body.make,
         machine_type => body.machine_type,
         status => body.status,
-        facility => body.facility
+        facility => body.facility,
+        parent => body.parent,
+        location => location
     ];
 
     if fields.len() < 1 {
@@ -250,6 +503,10 @@ pub async fn update(
         ));
     }
 
+    if new_location != current.location {
+        record_move(&mut tx, &user, body.id, current.location, new_location).await?;
+    }
+
     let machine = query_as!(
         Machine,
         r#"
@@ -263,7 +520,8 @@ pub async fn update(
             ) AS "machine_type!: MachineType",
             (
                 ms.id,
-                ms.name
+                ms.name,
+                ms.down
             ) AS "status!: MachineStatus",
             m.created,
             m.edited,
@@ -272,7 +530,9 @@ pub async fn update(
                 f.name,
                 f.address
             ) AS "facility?: Facility",
-            m.image
+            m.image,
+            m.parent,
+            m.location
         FROM
             machines m
         INNER JOIN 
@@ -301,18 +561,308 @@ pub async fn update(
 }
 
 pub async fn delete(
+    _: Require<MachineDelete>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<DeleteMachine>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.machine_delete)?;
-
-    let result = query!(r#"DELETE FROM machines WHERE id = $1"#, params.id)
-        .execute(&app_state.db)
-        .await?;
+    let result = query!(
+        r#"DELETE FROM machines WHERE id = $1 AND ($2::UUID[] IS NULL OR facility = ANY($2))"#,
+        params.id,
+        user.facility_scope()
+    )
+    .execute(&app_state.db)
+    .await?;
 
     match result.rows_affected() {
         1 => Ok(StatusCode::NO_CONTENT),
         _ => Ok(StatusCode::NOT_FOUND),
     }
 }
+
+pub async fn tree(
+    _: Require<MachineView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryMachineTree>,
+) -> Result<Json<Vec<MachineNode>>, ApiError> {
+    let to = params.to.unwrap_or_else(Utc::now);
+    let from = params.from.unwrap_or(to - Duration::days(30));
+
+    if to <= from {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidTimeRange));
+    }
+
+    let nodes = query_as!(
+        MachineNode,
+        r#"
+        WITH RECURSIVE tree AS (
+            SELECT
+                m.id,
+                0 AS depth,
+                ARRAY[m.id] AS path,
+                ARRAY[m.name::TEXT] AS sort_path
+            FROM
+                machines m
+            WHERE
+                (($1::UUID IS NULL AND m.parent IS NULL) OR m.id = $1)
+            AND
+                ($4::UUID[] IS NULL OR m.facility = ANY($4))
+            UNION ALL
+            SELECT
+                c.id,
+                t.depth + 1,
+                t.path || c.id,
+                t.sort_path || c.name::TEXT
+            FROM
+                machines c
+            INNER JOIN
+                tree t
+            ON
+                c.parent = t.id
+            WHERE
+                ($4::UUID[] IS NULL OR c.facility = ANY($4))
+        ),
+        work AS (
+            SELECT
+                tree.id,
+                (
+                    SELECT
+                        count(*)
+                    FROM
+                        tasks tk
+                    INNER JOIN
+                        task_statuses ts
+                    ON
+                        tk.status = ts.id
+                    WHERE
+                        tk.machine = tree.id
+                    AND
+                        NOT tk.archived
+                    AND
+                        NOT ts.terminal
+                ) AS open_tasks,
+                (
+                    SELECT
+                        count(*)
+                    FROM
+                        reports r
+                    WHERE
+                        r.machine = tree.id
+                    AND
+                        NOT r.archived
+                ) AS open_reports,
+                (
+                    SELECT
+                        range_agg(
+                            tstzrange(
+                                GREATEST(h.started_at, $2),
+                                LEAST(COALESCE(h.ended_at, NOW()), $3)
+                            )
+                        )
+                    FROM
+                        machine_status_history h
+                    INNER JOIN
+                        machine_statuses hs
+                    ON
+                        h.status = hs.id
+                    WHERE
+                        h.machine_id = tree.id
+                    AND
+                        hs.down
+                    AND
+                        h.started_at < $3
+                    AND
+                        COALESCE(h.ended_at, NOW()) > $2
+                ) AS down_ranges
+            FROM
+                tree
+        )
+        SELECT
+            m.id,
+            m.name,
+            m.parent,
+            tree.depth AS "depth!",
+            (
+                ms.id,
+                ms.name,
+                ms.down
+            ) AS "status!: MachineStatus",
+            m.facility,
+            w.open_tasks AS "open_tasks!",
+            w.open_reports AS "open_reports!",
+            (
+                SELECT
+                    COALESCE(floor(sum(EXTRACT(EPOCH FROM upper(r) - lower(r))) / 60), 0)::BIGINT
+                FROM
+                    unnest(w.down_ranges) r
+            ) AS "downtime_minutes!",
+            totals.open_tasks AS "total_open_tasks!",
+            totals.open_reports AS "total_open_reports!",
+            totals.down_components AS "down_components!",
+            (
+                SELECT
+                    COALESCE(floor(sum(EXTRACT(EPOCH FROM upper(r) - lower(r))) / 60), 0)::BIGINT
+                FROM
+                    unnest(totals.down_ranges) r
+            ) AS "total_downtime_minutes!"
+        FROM
+            tree
+        INNER JOIN
+            work w
+        ON
+            tree.id = w.id
+        INNER JOIN
+            machines m
+        ON
+            tree.id = m.id
+        INNER JOIN
+            machine_statuses ms
+        ON
+            m.status = ms.id
+        CROSS JOIN LATERAL (
+            SELECT
+                sum(sw.open_tasks)::BIGINT AS open_tasks,
+                sum(sw.open_reports)::BIGINT AS open_reports,
+                count(*) FILTER (WHERE sms.down AND st.id <> tree.id) AS down_components,
+                range_agg(sw.down_ranges) AS down_ranges
+            FROM
+                tree st
+            INNER JOIN
+                work sw
+            ON
+                st.id = sw.id
+            INNER JOIN
+                machines sm
+            ON
+                st.id = sm.id
+            INNER JOIN
+                machine_statuses sms
+            ON
+                sm.status = sms.id
+            WHERE
+                tree.id = ANY(st.path)
+        ) totals
+        ORDER BY
+            tree.sort_path
+        "#,
+        params.id,
+        from,
+        to,
+        user.facility_scope()
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(nodes))
+}
+
+pub async fn ancestors(
+    _: Require<MachineView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryMachine>,
+) -> Result<Json<Vec<MachineAncestor>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_machine_scope(&mut conn, &user, params.id).await?;
+
+    let ancestors = query_as!(
+        MachineAncestor,
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT
+                m.parent AS id,
+                1 AS distance
+            FROM
+                machines m
+            WHERE
+                m.id = $1
+            UNION ALL
+            SELECT
+                m.parent,
+                a.distance + 1
+            FROM
+                machines m
+            INNER JOIN
+                ancestors a
+            ON
+                m.id = a.id
+        )
+        SELECT
+            m.id,
+            m.name,
+            (max(a.distance) OVER () - a.distance)::INT AS "depth!"
+        FROM
+            ancestors a
+        INNER JOIN
+            machines m
+        ON
+            a.id = m.id
+        WHERE
+            ($2::UUID[] IS NULL OR m.facility = ANY($2))
+        ORDER BY
+            a.distance DESC
+        "#,
+        params.id,
+        user.facility_scope()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(Json(ancestors))
+}
+
+pub async fn location_history(
+    _: Require<MachineView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryMachine>,
+) -> Result<Json<Vec<MachineMove>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_machine_scope(&mut conn, &user, params.id).await?;
+
+    let moves = query_as!(
+        MachineMove,
+        r#"
+        SELECT
+            mlh.id,
+            mlh.from_location,
+            fl.name AS "from_name?",
+            mlh.to_location,
+            tl.name AS "to_name?",
+            (
+                u.id,
+                u.first_name,
+                u.last_name,
+                u.email,
+                u.image
+            ) AS "moved_by?: ShortUser",
+            mlh.moved_at
+        FROM
+            machine_location_history mlh
+        LEFT JOIN
+            locations fl
+        ON
+            mlh.from_location = fl.id
+        LEFT JOIN
+            locations tl
+        ON
+            mlh.to_location = tl.id
+        LEFT JOIN
+            users u
+        ON
+            mlh.moved_by = u.id
+        WHERE
+            mlh.machine_id = $1
+        ORDER BY
+            mlh.moved_at DESC
+        "#,
+        params.id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(Json(moves))
+}
@@ -0,0 +1,341 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{FacilityCreate, FacilityDelete, FacilityEdit, FacilityView, Require},
+    field_vec, update_field,
+    users::models::User,
+    utils::{
+        db::{Field, IntoField, Nullable},
+        errors::{ApiError, ConflictReason, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    DeleteLocation, Location, NewLocation, QueryLocation, QueryLocations, UpdateLocation,
+};
+
+async fn fetch_locations(
+    conn: &mut PgConnection,
+    location_id: Option<Uuid>,
+    facility_id: Option<Uuid>,
+    facilities: Option<&[Uuid]>,
+) -> Result<Vec<Location>, ApiError> {
+    let locations = query_as!(
+        Location,
+        r#"
+        WITH RECURSIVE tree AS (
+            SELECT
+                l.id,
+                0 AS depth,
+                l.name::TEXT AS path
+            FROM
+                locations l
+            WHERE
+                l.parent IS NULL
+            UNION ALL
+            SELECT
+                l.id,
+                t.depth + 1,
+                t.path || ' / ' || l.name
+            FROM
+                locations l
+            INNER JOIN
+                tree t
+            ON
+                l.parent = t.id
+        )
+        SELECT
+            l.id,
+            l.facility_id,
+            l.parent,
+            l.kind,
+            l.name,
+            tree.path AS "path!",
+            tree.depth AS "depth!",
+            l.created
+        FROM
+            tree
+        INNER JOIN
+            locations l
+        ON
+            tree.id = l.id
+        WHERE
+            ($1::UUID IS NULL OR l.id = $1)
+        AND
+            ($2::UUID IS NULL OR l.facility_id = $2)
+        AND
+            ($3::UUID[] IS NULL OR l.facility_id = ANY($3))
+        ORDER BY
+            l.facility_id,
+            tree.path
+        "#,
+        location_id,
+        facility_id,
+        facilities
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(locations)
+}
+
+pub async fn location_facility(
+    conn: &mut PgConnection,
+    location_id: Uuid,
+) -> Result<Uuid, ApiError> {
+    let facility_id = query_scalar!(
+        r#"SELECT facility_id FROM locations WHERE id = $1"#,
+        location_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(facility_id)
+}
+
+// Machines, users and sub-locations can only be placed at a location of their own facility
+
+pub async fn check_location(
+    conn: &mut PgConnection,
+    location_id: Uuid,
+    facility: Option<Uuid>,
+) -> Result<(), ApiError> {
+    let facility_id = location_facility(conn, location_id).await?;
+
+    match facility == Some(facility_id) {
+        true => Ok(()),
+        false => Err(ApiError::InputInvalid(
+            InputInvalidReason::LocationOutsideFacility,
+        )),
+    }
+}
+
+pub async fn details(
+    _: Require<FacilityView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryLocation>,
+) -> Result<Json<Location>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let location = fetch_locations(&mut conn, Some(params.id), None, user.facility_scope())
+        .await?
+        .pop()
+        .ok_or(ApiError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    Ok(Json(location))
+}
+
+pub async fn index(
+    _: Require<FacilityView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryLocations>,
+) -> Result<Json<Vec<Location>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let locations =
+        fetch_locations(&mut conn, None, params.facility_id, user.facility_scope()).await?;
+
+    Ok(Json(locations))
+}
+
+pub async fn create(
+    _: Require<FacilityCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewLocation>,
+) -> Result<(StatusCode, Json<Location>), ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let facility_id = match (body.facility_id, body.parent) {
+        (Some(facility_id), _) => facility_id,
+        (None, Some(parent)) => location_facility(&mut tx, parent).await?,
+        (None, None) => {
+            return Err(ApiError::InputInvalid(
+                InputInvalidReason::LocationWithoutFacility,
+            ))
+        }
+    };
+
+    user.check_facility(Some(facility_id))?;
+
+    if let Some(parent) = body.parent {
+        check_location(&mut tx, parent, Some(facility_id)).await?;
+    }
+
+    let location_id = query_scalar!(
+        r#"
+        INSERT INTO
+            locations
+        (
+            facility_id,
+            parent,
+            kind,
+            name
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4
+        )
+        RETURNING
+            id
+        "#,
+        facility_id,
+        body.parent,
+        body.kind.as_str(),
+        body.name
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let location = fetch_locations(&mut tx, Some(location_id), None, None)
+        .await?
+        .pop()
+        .ok_or(ApiError::GeneralOversight(
+            "Created location wasn't found".to_owned(),
+        ))?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(location)))
+}
+
+pub async fn update(
+    _: Require<FacilityEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateLocation>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let facility_id = location_facility(&mut tx, body.id).await?;
+
+    user.check_facility(Some(facility_id))?;
+
+    if let Nullable::Value(parent) = body.parent {
+        check_location(&mut tx, parent, Some(facility_id)).await?;
+
+        let cycle = query_scalar!(
+            r#"SELECT $2 IN (SELECT location_subtree($1)) AS "cycle!""#,
+            body.id,
+            parent
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if cycle {
+            return Err(ApiError::InputInvalid(InputInvalidReason::LocationCycle));
+        }
+    }
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE locations SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        name => body.name,
+        kind => body.kind.map(|kind| kind.as_str().to_owned()),
+        parent => body.parent
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    query_builder.build().execute(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete(
+    _: Require<FacilityDelete>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteLocation>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let facility_id = location_facility(&mut tx, params.id).await?;
+
+    user.check_facility(Some(facility_id))?;
+
+    // Deleting would clear the location of the machines without a move in their history
+
+    let in_use = query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT
+                1
+            FROM
+                machines m
+            WHERE
+                m.location IN (SELECT location_subtree($1))
+        ) AS "in_use!"
+        "#,
+        params.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if in_use {
+        return Err(ApiError::Conflict(ConflictReason::LocationInUse));
+    }
+
+    query!(r#"DELETE FROM locations WHERE id = $1"#, params.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Resolves the location a facility change leaves a machine or user at, moving to another
+// facility clears the old location unless a new one is given
+
+pub async fn location_change(
+    conn: &mut PgConnection,
+    current_facility: Option<Uuid>,
+    current_location: Option<Uuid>,
+    facility: &Nullable<Uuid>,
+    location: Nullable<Uuid>,
+) -> Result<Nullable<Uuid>, ApiError> {
+    let facility = match facility {
+        Nullable::Absent => current_facility,
+        Nullable::Null => None,
+        Nullable::Value(facility) => Some(*facility),
+    };
+
+    match location {
+        Nullable::Value(location) => {
+            check_location(conn, location, facility).await?;
+
+            Ok(Nullable::Value(location))
+        }
+        Nullable::Absent if current_location.is_some() && facility != current_facility => {
+            Ok(Nullable::Null)
+        }
+        location => Ok(location),
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::utils::db::Nullable;
+
+// The path names every location above it, like "Hall 1 / Ground floor / Paint shop"
+
+#[derive(Serialize)]
+pub struct Location {
+    pub id: Uuid,
+    pub facility_id: Uuid,
+    pub parent: Option<Uuid>,
+    pub kind: String,
+    pub name: String,
+    pub path: String,
+    pub depth: i32,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LocationKind {
+    Building,
+    Floor,
+    Area,
+}
+
+impl LocationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LocationKind::Building => "building",
+            LocationKind::Floor => "floor",
+            LocationKind::Area => "area",
+        }
+    }
+}
+
+// Details
+
+#[derive(Deserialize)]
+pub struct QueryLocation {
+    pub id: Uuid,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryLocations {
+    pub facility_id: Option<Uuid>,
+}
+
+// Create, without a facility the location is put in the facility of its parent
+
+#[derive(Deserialize)]
+pub struct NewLocation {
+    pub facility_id: Option<Uuid>,
+    pub parent: Option<Uuid>,
+    pub kind: LocationKind,
+    pub name: String,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateLocation {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub kind: Option<LocationKind>,
+    #[serde(default)]
+    pub parent: Nullable<Uuid>,
+}
+
+// Delete, everything inside the location goes with it
+
+#[derive(Deserialize)]
+pub struct DeleteLocation {
+    pub id: Uuid,
+}
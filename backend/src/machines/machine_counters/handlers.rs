@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, query_scalar};
+
+use crate::{
+    auth::permissions::{MachineEdit, MachineView, Require},
+    machines::handlers::check_machine_scope,
+    tasks::maintenance_rules::handlers::trigger_maintenance,
+    users::models::User,
+    utils::errors::{ApiError, InputInvalidReason},
+    AppState,
+};
+
+use super::models::{
+    DeleteMachineCounter, MachineCounter, NewMachineCounter, QueryMachineCounters,
+    UpdateMachineCounter, UpdatedMachineCounter,
+};
+
+pub async fn index(
+    _: Require<MachineView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryMachineCounters>,
+) -> Result<Json<Vec<MachineCounter>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_machine_scope(&mut conn, &user, params.machine_id).await?;
+
+    let counters = query_as!(
+        MachineCounter,
+        r#"
+        SELECT
+            mc.id,
+            mc.machine_id,
+            mc.name,
+            mc.unit,
+            mc.value,
+            mc.created,
+            mc.edited
+        FROM
+            machine_counters mc
+        WHERE
+            mc.machine_id = $1
+        ORDER BY
+            mc.name
+        "#,
+        params.machine_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(Json(counters))
+}
+
+pub async fn create(
+    _: Require<MachineEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewMachineCounter>,
+) -> Result<(StatusCode, Json<MachineCounter>), ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_machine_scope(&mut conn, &user, body.machine_id).await?;
+
+    let counter = query_as!(
+        MachineCounter,
+        r#"
+        INSERT INTO
+            machine_counters
+        (
+            machine_id,
+            name,
+            unit,
+            value
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4
+        )
+        RETURNING
+            id,
+            machine_id,
+            name,
+            unit,
+            value,
+            created,
+            edited
+        "#,
+        body.machine_id,
+        body.name,
+        body.unit,
+        body.value.unwrap_or(0.0)
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(counter)))
+}
+
+// Usage reported by machines or their integrations, any maintenance rule the new value
+// reaches opens its task in the same transaction
+
+pub async fn update(
+    _: Require<MachineEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateMachineCounter>,
+) -> Result<Json<UpdatedMachineCounter>, ApiError> {
+    if body.value.is_some() && body.increment.is_some() {
+        return Err(ApiError::InputInvalid(
+            InputInvalidReason::AmbiguousCounterUpdate,
+        ));
+    }
+
+    if body.name.is_none()
+        && body.unit.is_none()
+        && body.value.is_none()
+        && body.increment.is_none()
+    {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    let machine_id = query_scalar!(
+        r#"SELECT machine_id FROM machine_counters WHERE id = $1 FOR UPDATE"#,
+        body.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    check_machine_scope(&mut tx, &user, machine_id).await?;
+
+    let counter = query_as!(
+        MachineCounter,
+        r#"
+        UPDATE
+            machine_counters
+        SET
+            name = COALESCE($2, name),
+            unit = COALESCE($3, unit),
+            value = COALESCE($4, value + $5, value)
+        WHERE
+            id = $1
+        RETURNING
+            id,
+            machine_id,
+            name,
+            unit,
+            value,
+            created,
+            edited
+        "#,
+        body.id,
+        body.name,
+        body.unit,
+        body.value,
+        body.increment
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let tasks = match body.value.is_some() || body.increment.is_some() {
+        true => trigger_maintenance(&mut tx, &user, body.id).await?,
+        false => Vec::new(),
+    };
+
+    tx.commit().await?;
+
+    Ok(Json(UpdatedMachineCounter { counter, tasks }))
+}
+
+pub async fn delete(
+    _: Require<MachineEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteMachineCounter>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let machine_id = query_scalar!(
+        r#"SELECT machine_id FROM machine_counters WHERE id = $1"#,
+        params.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    check_machine_scope(&mut conn, &user, machine_id).await?;
+
+    let result = query!(r#"DELETE FROM machine_counters WHERE id = $1"#, params.id)
+        .execute(&mut *conn)
+        .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
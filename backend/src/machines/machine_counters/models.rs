@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::tasks::models::CreatedTask;
+
+#[derive(Serialize)]
+pub struct MachineCounter {
+    pub id: Uuid,
+    pub machine_id: Uuid,
+    pub name: String,
+    pub unit: String,
+    pub value: f64,
+    pub created: DateTime<Utc>,
+    pub edited: DateTime<Utc>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryMachineCounters {
+    pub machine_id: Uuid,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewMachineCounter {
+    pub machine_id: Uuid,
+    pub name: String,
+    pub unit: String,
+    pub value: Option<f64>,
+}
+
+// Update, the counter is either set to a value or increased by the usage since the last update
+
+#[derive(Deserialize)]
+pub struct UpdateMachineCounter {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub unit: Option<String>,
+    pub value: Option<f64>,
+    pub increment: Option<f64>,
+}
+
+// The tasks opened by maintenance rules the update pushed over their threshold
+
+#[derive(Serialize)]
+pub struct UpdatedMachineCounter {
+    pub counter: MachineCounter,
+    pub tasks: Vec<CreatedTask>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteMachineCounter {
+    pub id: Uuid,
+}
@@ -3,13 +3,15 @@ use std::sync::Arc;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Extension, Json,
+    Json,
 };
 use sqlx::{query, query_as};
 
 use crate::{
-    users::models::User,
-    utils::{check_permission, errors::ApiError},
+    auth::permissions::{
+        MachineStatusCreate, MachineStatusDelete, MachineStatusEdit, MachineStatusView, Require,
+    },
+    utils::errors::ApiError,
     AppState,
 };
 
@@ -19,12 +21,10 @@ use super::{
 };
 
 pub async fn details(
-    Extension(user): Extension<User>,
+    _: Require<MachineStatusView>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryMachineStatus>,
 ) -> Result<Json<MachineStatus>, ApiError> {
-    check_permission(user.role.machine_view)?;
-
     let machine_status = query_as!(
         MachineStatus,
         r#"
@@ -44,11 +44,9 @@ pub async fn details(
 }
 
 pub async fn index(
-    Extension(user): Extension<User>,
+    _: Require<MachineStatusView>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<MachineStatus>>, ApiError> {
-    check_permission(user.role.machine_view)?;
-
     let machine_statuses = query_as!(
         MachineStatus,
         r#"
@@ -65,28 +63,29 @@ pub async fn index(
 }
 
 pub async fn create(
-    Extension(user): Extension<User>,
+    _: Require<MachineStatusCreate>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewMachineStatus>,
 ) -> Result<(StatusCode, Json<MachineStatus>), ApiError> {
-    check_permission(user.role.machine_create)?;
-
     let machine_status = query_as!(
         MachineStatus,
         r#"
         INSERT INTO
             machine_statuses
         (
-            name
+            name,
+            down
         )
         VALUES
         (
-            $1
+            $1,
+            $2
         )
         RETURNING
             *
         "#,
-        body.name
+        body.name,
+        body.down.unwrap_or(false)
     )
     .fetch_one(&app_state.db)
     .await?;
@@ -95,22 +94,22 @@ pub async fn create(
 }
 
 pub async fn update(
-    Extension(user): Extension<User>,
+    _: Require<MachineStatusEdit>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateMachineStatus>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.machine_edit)?;
-
     let result = query!(
         r#"
         UPDATE 
             machine_statuses ms
         SET
-            name = $1
+            name = COALESCE($1, name),
+            down = COALESCE($2, down)
         WHERE
-            ms.id = $2
+            ms.id = $3
         "#,
         body.name,
+        body.down,
         body.id
     )
     .execute(&app_state.db)
@@ -123,12 +122,10 @@ pub async fn update(
 }
 
 pub async fn delete(
-    Extension(user): Extension<User>,
+    _: Require<MachineStatusDelete>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryMachineStatus>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.machine_delete)?;
-
     let result = query!(r#"DELETE FROM machine_statuses WHERE id = $1"#, params.id)
         .execute(&app_state.db)
         .await?;
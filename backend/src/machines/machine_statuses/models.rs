@@ -6,6 +6,7 @@ use uuid::Uuid;
 pub struct MachineStatus {
     pub id: Uuid,
     pub name: String,
+    pub down: bool,
 }
 
 // Details
@@ -15,11 +16,12 @@ pub struct QueryMachineStatus {
     pub id: Uuid,
 }
 
-// Create
+// Create, machines in a down status count as downtime
 
 #[derive(Deserialize)]
 pub struct NewMachineStatus {
     pub name: String,
+    pub down: Option<bool>,
 }
 
 // Update
@@ -27,5 +29,6 @@ pub struct NewMachineStatus {
 #[derive(Deserialize)]
 pub struct UpdateMachineStatus {
     pub id: Uuid,
-    pub name: String,
+    pub name: Option<String>,
+    pub down: Option<bool>,
 }
@@ -0,0 +1,5 @@
+pub mod handlers;
+pub mod models;
+
+pub use handlers::create;
+pub use handlers::delete;
@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct MachineTypeChecklist {
+    pub machine_type_id: Uuid,
+    pub template_id: Uuid,
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Json};
+use sqlx::query;
+
+use crate::{
+    auth::permissions::{PartEdit, Require},
+    utils::errors::ApiError,
+    AppState,
+};
+
+use super::models::MachineTypePart;
+
+pub async fn create(
+    _: Require<PartEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<MachineTypePart>,
+) -> Result<StatusCode, ApiError> {
+    let result = query!(
+        r#"
+        INSERT INTO
+            machine_type_parts
+        (
+            machine_type_id,
+            part_id
+        )
+        VALUES
+        (
+            $1,
+            $2
+        )
+        ON CONFLICT DO NOTHING
+        "#,
+        body.machine_type_id,
+        body.part_id
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::CREATED),
+        _ => Ok(StatusCode::OK),
+    }
+}
+
+pub async fn delete(
+    _: Require<PartEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<MachineTypePart>,
+) -> Result<StatusCode, ApiError> {
+    let result = query!(
+        r#"
+        DELETE FROM
+            machine_type_parts
+        WHERE
+            machine_type_id = $1
+        AND
+            part_id = $2
+        "#,
+        body.machine_type_id,
+        body.part_id
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
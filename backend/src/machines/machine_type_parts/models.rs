@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct MachineTypePart {
+    pub machine_type_id: Uuid,
+    pub part_id: Uuid,
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Json};
+use sqlx::query;
+
+use crate::{
+    auth::permissions::{MachineTypeEdit, Require},
+    utils::errors::ApiError,
+    AppState,
+};
+
+use super::models::MachineTypeSkill;
+
+pub async fn create(
+    _: Require<MachineTypeEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<MachineTypeSkill>,
+) -> Result<StatusCode, ApiError> {
+    let result = query!(
+        r#"
+        INSERT INTO
+            machine_type_skills
+        (
+            machine_type_id,
+            skill_id
+        )
+        VALUES
+        (
+            $1,
+            $2
+        )
+        ON CONFLICT DO NOTHING
+        "#,
+        body.machine_type_id,
+        body.skill_id
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::CREATED),
+        _ => Ok(StatusCode::OK),
+    }
+}
+
+pub async fn delete(
+    _: Require<MachineTypeEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<MachineTypeSkill>,
+) -> Result<StatusCode, ApiError> {
+    let result = query!(
+        r#"
+        DELETE FROM
+            machine_type_skills
+        WHERE
+            machine_type_id = $1
+        AND
+            skill_id = $2
+        "#,
+        body.machine_type_id,
+        body.skill_id
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct MachineTypeSkill {
+    pub machine_type_id: Uuid,
+    pub skill_id: Uuid,
+}
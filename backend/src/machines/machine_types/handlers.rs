@@ -3,13 +3,15 @@ use std::sync::Arc;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Extension, Json,
+    Json,
 };
 use sqlx::{query, query_as};
 
 use crate::{
-    users::models::User,
-    utils::{check_permission, errors::ApiError},
+    auth::permissions::{
+        MachineTypeCreate, MachineTypeDelete, MachineTypeEdit, MachineTypeView, Require,
+    },
+    utils::errors::ApiError,
     AppState,
 };
 
@@ -19,12 +21,10 @@ use super::{
 };
 
 pub async fn details(
-    Extension(user): Extension<User>,
+    _: Require<MachineTypeView>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryMachineType>,
 ) -> Result<Json<MachineType>, ApiError> {
-    check_permission(user.role.machine_view)?;
-
     let machine_type = query_as!(
         MachineType,
         r#"
@@ -44,11 +44,9 @@ pub async fn details(
 }
 
 pub async fn index(
-    Extension(user): Extension<User>,
+    _: Require<MachineTypeView>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<MachineType>>, ApiError> {
-    check_permission(user.role.machine_view)?;
-
     let machine_types = query_as!(
         MachineType,
         r#"
@@ -65,12 +63,10 @@ pub async fn index(
 }
 
 pub async fn create(
-    Extension(user): Extension<User>,
+    _: Require<MachineTypeCreate>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewMachineType>,
 ) -> Result<(StatusCode, Json<MachineType>), ApiError> {
-    check_permission(user.role.machine_create)?;
-
     let machine_type = query_as!(
         MachineType,
         r#"
@@ -95,12 +91,10 @@ pub async fn create(
 }
 
 pub async fn update(
-    Extension(user): Extension<User>,
+    _: Require<MachineTypeEdit>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateMachineType>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.machine_edit)?;
-
     let result = query!(
         r#"
         UPDATE 
@@ -123,12 +117,10 @@ pub async fn update(
 }
 
 pub async fn delete(
-    Extension(user): Extension<User>,
+    _: Require<MachineTypeDelete>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryMachineType>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.machine_delete)?;
-
     let result = query!(r#"DELETE FROM machine_types WHERE id = $1"#, params.id)
         .execute(&app_state.db)
         .await?;
@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use sqlx::{query, query_as, Postgres, QueryBuilder};
+
+use crate::{
+    auth::permissions::{MachineTypeEdit, MachineTypeView, Require},
+    field_vec, update_field,
+    utils::{
+        db::{Field, IntoField, Nullable},
+        errors::{ApiError, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    DeleteMeasurementDefinition, MeasurementDefinition, NewMeasurementDefinition,
+    QueryMeasurementDefinitions, UpdateMeasurementDefinition,
+};
+
+fn check_thresholds(min_value: Option<f64>, max_value: Option<f64>) -> Result<(), ApiError> {
+    match (min_value, max_value) {
+        (Some(min_value), Some(max_value)) if min_value > max_value => Err(ApiError::InputInvalid(
+            InputInvalidReason::InvalidThresholds,
+        )),
+        _ => Ok(()),
+    }
+}
+
+// The thresholds after the update, to check them before writing
+
+fn merge<T: Copy>(value: &Nullable<T>, current: Option<T>) -> Option<T> {
+    match value {
+        Nullable::Absent => current,
+        Nullable::Null => None,
+        Nullable::Value(value) => Some(*value),
+    }
+}
+
+pub async fn index(
+    _: Require<MachineTypeView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryMeasurementDefinitions>,
+) -> Result<Json<Vec<MeasurementDefinition>>, ApiError> {
+    let definitions = query_as!(
+        MeasurementDefinition,
+        r#"
+        SELECT
+            md.id,
+            md.machine_type,
+            md.name,
+            md.unit,
+            md.min_value,
+            md.max_value,
+            md.alert_report_type,
+            md.alert_report_status,
+            md.created
+        FROM
+            measurement_definitions md
+        WHERE
+            ($1::UUID IS NULL OR md.machine_type = $1)
+        ORDER BY
+            md.name
+        "#,
+        params.machine_type_id
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(definitions))
+}
+
+pub async fn create(
+    _: Require<MachineTypeEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewMeasurementDefinition>,
+) -> Result<(StatusCode, Json<MeasurementDefinition>), ApiError> {
+    check_thresholds(body.min_value, body.max_value)?;
+
+    let definition = query_as!(
+        MeasurementDefinition,
+        r#"
+        INSERT INTO
+            measurement_definitions
+        (
+            machine_type,
+            name,
+            unit,
+            min_value,
+            max_value,
+            alert_report_type,
+            alert_report_status
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            $6,
+            $7
+        )
+        RETURNING
+            id,
+            machine_type,
+            name,
+            unit,
+            min_value,
+            max_value,
+            alert_report_type,
+            alert_report_status,
+            created
+        "#,
+        body.machine_type,
+        body.name,
+        body.unit,
+        body.min_value,
+        body.max_value,
+        body.alert_report_type,
+        body.alert_report_status
+    )
+    .fetch_one(&app_state.db)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(definition)))
+}
+
+pub async fn update(
+    _: Require<MachineTypeEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateMeasurementDefinition>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let current = query!(
+        r#"SELECT min_value, max_value FROM measurement_definitions WHERE id = $1 FOR UPDATE"#,
+        body.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    check_thresholds(
+        merge(&body.min_value, current.min_value),
+        merge(&body.max_value, current.max_value),
+    )?;
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE measurement_definitions SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        name => body.name,
+        unit => body.unit,
+        min_value => body.min_value,
+        max_value => body.max_value,
+        alert_report_type => body.alert_report_type,
+        alert_report_status => body.alert_report_status
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    let result = query_builder.build().execute(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    _: Require<MachineTypeEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteMeasurementDefinition>,
+) -> Result<StatusCode, ApiError> {
+    let result = query!(
+        r#"DELETE FROM measurement_definitions WHERE id = $1"#,
+        params.id
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::utils::db::Nullable;
+
+#[derive(Serialize)]
+pub struct MeasurementDefinition {
+    pub id: Uuid,
+    pub machine_type: Uuid,
+    pub name: String,
+    pub unit: String,
+    pub min_value: Option<f64>,
+    pub max_value: Option<f64>,
+    pub alert_report_type: Option<Uuid>,
+    pub alert_report_status: Option<Uuid>,
+    pub created: DateTime<Utc>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryMeasurementDefinitions {
+    pub machine_type_id: Option<Uuid>,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewMeasurementDefinition {
+    pub machine_type: Uuid,
+    pub name: String,
+    pub unit: String,
+    pub min_value: Option<f64>,
+    pub max_value: Option<f64>,
+    pub alert_report_type: Option<Uuid>,
+    pub alert_report_status: Option<Uuid>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateMeasurementDefinition {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub min_value: Nullable<f64>,
+    #[serde(default)]
+    pub max_value: Nullable<f64>,
+    #[serde(default)]
+    pub alert_report_type: Nullable<Uuid>,
+    #[serde(default)]
+    pub alert_report_status: Nullable<Uuid>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteMeasurementDefinition {
+    pub id: Uuid,
+}
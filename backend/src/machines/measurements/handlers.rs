@@ -0,0 +1,464 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{
+        MeasurementCreate, MeasurementDelete, MeasurementEdit, MeasurementView, Require,
+    },
+    machines::handlers::check_machine_scope,
+    tasks::handlers::task_ownership,
+    users::models::{ShortUser, User},
+    utils::{
+        db::Nullable,
+        errors::{ApiError, ForbiddenReason, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    DeleteReading, NewReading, QueryReadings, QuerySeries, Reading, Series, SeriesPoint,
+    UpdateReading,
+};
+
+struct ReadingFilter {
+    reading_id: Option<Uuid>,
+    machine_id: Option<Uuid>,
+    definition_id: Option<Uuid>,
+    task_id: Option<Uuid>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+async fn fetch_readings(
+    conn: &mut PgConnection,
+    filter: ReadingFilter,
+) -> Result<Vec<Reading>, ApiError> {
+    let readings = query_as!(
+        Reading,
+        r#"
+        SELECT
+            mr.id,
+            mr.definition_id,
+            md.name,
+            md.unit,
+            mr.machine_id,
+            mr.task_id,
+            mr.value,
+            mr.out_of_range,
+            mr.report_id,
+            mr.note,
+            (
+                u.id,
+                u.first_name,
+                u.last_name,
+                u.email,
+                u.image
+            ) AS "recorded_by?: ShortUser",
+            mr.recorded_at
+        FROM
+            measurement_readings mr
+        INNER JOIN
+            measurement_definitions md
+        ON
+            mr.definition_id = md.id
+        LEFT JOIN
+            users u
+        ON
+            mr.recorded_by = u.id
+        WHERE
+            ($1::UUID IS NULL OR mr.id = $1)
+        AND
+            ($2::UUID IS NULL OR mr.machine_id = $2)
+        AND
+            ($3::UUID IS NULL OR mr.definition_id = $3)
+        AND
+            ($4::UUID IS NULL OR mr.task_id = $4)
+        AND
+            ($5::TIMESTAMPTZ IS NULL OR mr.recorded_at >= $5)
+        AND
+            ($6::TIMESTAMPTZ IS NULL OR mr.recorded_at < $6)
+        ORDER BY
+            mr.recorded_at DESC
+        "#,
+        filter.reading_id,
+        filter.machine_id,
+        filter.definition_id,
+        filter.task_id,
+        filter.from,
+        filter.to
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(readings)
+}
+
+// The range a reading has to stay in, as written in the alert report
+
+fn describe_range(min_value: Option<f64>, max_value: Option<f64>, unit: &str) -> String {
+    match (min_value, max_value) {
+        (Some(min_value), Some(max_value)) => format!("{min_value} to {max_value} {unit}"),
+        (Some(min_value), None) => format!("at least {min_value} {unit}"),
+        (None, Some(max_value)) => format!("at most {max_value} {unit}"),
+        (None, None) => "unbounded".to_owned(),
+    }
+}
+
+pub async fn index(
+    _: Require<MeasurementView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryReadings>,
+) -> Result<Json<Vec<Reading>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_machine_scope(&mut conn, &user, params.machine_id).await?;
+
+    let readings = fetch_readings(
+        &mut conn,
+        ReadingFilter {
+            reading_id: None,
+            machine_id: Some(params.machine_id),
+            definition_id: params.definition_id,
+            task_id: params.task_id,
+            from: params.from,
+            to: params.to,
+        },
+    )
+    .await?;
+
+    Ok(Json(readings))
+}
+
+// Readings of one measurement on one machine, aggregated per interval for charts
+
+pub async fn series(
+    _: Require<MeasurementView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QuerySeries>,
+) -> Result<Json<Series>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_machine_scope(&mut conn, &user, params.machine_id).await?;
+
+    let definition = query!(
+        r#"
+        SELECT
+            name,
+            unit,
+            min_value,
+            max_value
+        FROM
+            measurement_definitions
+        WHERE
+            id = $1
+        "#,
+        params.definition_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let points = query_as!(
+        SeriesPoint,
+        r#"
+        SELECT
+            date_trunc($3, mr.recorded_at) AS "bucket!",
+            count(*) AS "count!",
+            min(mr.value) AS "min!",
+            avg(mr.value) AS "avg!",
+            max(mr.value) AS "max!",
+            count(*) FILTER (WHERE mr.out_of_range) AS "out_of_range!"
+        FROM
+            measurement_readings mr
+        WHERE
+            mr.machine_id = $1
+        AND
+            mr.definition_id = $2
+        AND
+            ($4::TIMESTAMPTZ IS NULL OR mr.recorded_at >= $4)
+        AND
+            ($5::TIMESTAMPTZ IS NULL OR mr.recorded_at < $5)
+        GROUP BY
+            1
+        ORDER BY
+            1
+        "#,
+        params.machine_id,
+        params.definition_id,
+        params.interval.as_str(),
+        params.from,
+        params.to
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(Json(Series {
+        name: definition.name,
+        unit: definition.unit,
+        min_value: definition.min_value,
+        max_value: definition.max_value,
+        points,
+    }))
+}
+
+// Executors may record readings on their own tasks without measurement.create, a reading
+// outside the thresholds opens a report when the definition names a report type and status
+
+pub async fn create(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewReading>,
+) -> Result<(StatusCode, Json<Reading>), ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    check_machine_scope(&mut tx, &user, body.machine_id).await?;
+
+    if !user.role.has::<MeasurementCreate>() {
+        let is_executor = match body.task_id {
+            Some(task_id) => task_ownership(&mut tx, &user, task_id).await?.is_executor,
+            None => false,
+        };
+
+        if !is_executor {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+    }
+
+    let definition = query!(
+        r#"
+        SELECT
+            md.name,
+            md.unit,
+            md.min_value,
+            md.max_value,
+            md.alert_report_type,
+            md.alert_report_status,
+            m.name AS machine_name
+        FROM
+            measurement_definitions md
+        INNER JOIN
+            machines m
+        ON
+            md.machine_type = m.machine_type
+        WHERE
+            md.id = $1
+        AND
+            m.id = $2
+        "#,
+        body.definition_id,
+        body.machine_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ApiError::InputInvalid(
+        InputInvalidReason::MeasurementMismatch,
+    ))?;
+
+    if let Some(task_id) = body.task_id {
+        let machine = query_scalar!(r#"SELECT machine FROM tasks WHERE id = $1"#, task_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        if machine != Some(body.machine_id) {
+            return Err(ApiError::InputInvalid(
+                InputInvalidReason::MeasurementMismatch,
+            ));
+        }
+    }
+
+    let out_of_range = definition.min_value.is_some_and(|min| body.value < min)
+        || definition.max_value.is_some_and(|max| body.value > max);
+
+    let report_id = match (
+        out_of_range,
+        definition.alert_report_type,
+        definition.alert_report_status,
+    ) {
+        (true, Some(report_type), Some(status)) => Some(
+            query_scalar!(
+                r#"
+                INSERT INTO
+                    reports
+                (
+                    title,
+                    description,
+                    report_type,
+                    status,
+                    creator,
+                    machine
+                )
+                VALUES
+                (
+                    $1,
+                    $2,
+                    $3,
+                    $4,
+                    $5,
+                    $6
+                )
+                RETURNING
+                    id
+                "#,
+                format!(
+                    "{} out of range on {}",
+                    definition.name, definition.machine_name
+                ),
+                format!(
+                    "A reading of {} {} was recorded, the allowed range is {}",
+                    body.value,
+                    definition.unit,
+                    describe_range(definition.min_value, definition.max_value, &definition.unit)
+                ),
+                report_type,
+                status,
+                user.id,
+                body.machine_id
+            )
+            .fetch_one(&mut *tx)
+            .await?,
+        ),
+        _ => None,
+    };
+
+    let reading_id = query_scalar!(
+        r#"
+        INSERT INTO
+            measurement_readings
+        (
+            definition_id,
+            machine_id,
+            task_id,
+            value,
+            out_of_range,
+            report_id,
+            note,
+            recorded_by,
+            recorded_at
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            $6,
+            $7,
+            $8,
+            COALESCE($9, NOW())
+        )
+        RETURNING
+            id
+        "#,
+        body.definition_id,
+        body.machine_id,
+        body.task_id,
+        body.value,
+        out_of_range,
+        report_id,
+        body.note,
+        user.id,
+        body.recorded_at
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let reading = fetch_readings(
+        &mut tx,
+        ReadingFilter {
+            reading_id: Some(reading_id),
+            machine_id: None,
+            definition_id: None,
+            task_id: None,
+            from: None,
+            to: None,
+        },
+    )
+    .await?
+    .pop()
+    .ok_or(ApiError::GeneralOversight(
+        "Created measurement reading wasn't found".to_owned(),
+    ))?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(reading)))
+}
+
+pub async fn update(
+    _: Require<MeasurementEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateReading>,
+) -> Result<StatusCode, ApiError> {
+    let note = match body.note {
+        Nullable::Absent => {
+            return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate))
+        }
+        Nullable::Null => None,
+        Nullable::Value(note) => Some(note),
+    };
+
+    let mut conn = app_state.db.acquire().await?;
+
+    let machine_id = query_scalar!(
+        r#"SELECT machine_id FROM measurement_readings WHERE id = $1"#,
+        body.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    check_machine_scope(&mut conn, &user, machine_id).await?;
+
+    let result = query!(
+        r#"UPDATE measurement_readings SET note = $2 WHERE id = $1"#,
+        body.id,
+        note
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    _: Require<MeasurementDelete>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteReading>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let machine_id = query_scalar!(
+        r#"SELECT machine_id FROM measurement_readings WHERE id = $1"#,
+        params.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    check_machine_scope(&mut conn, &user, machine_id).await?;
+
+    let result = query!(
+        r#"DELETE FROM measurement_readings WHERE id = $1"#,
+        params.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{users::models::ShortUser, utils::db::Nullable};
+
+#[derive(Serialize)]
+pub struct Reading {
+    pub id: Uuid,
+    pub definition_id: Uuid,
+    pub name: String,
+    pub unit: String,
+    pub machine_id: Uuid,
+    pub task_id: Option<Uuid>,
+    pub value: f64,
+    pub out_of_range: bool,
+    pub report_id: Option<Uuid>,
+    pub note: Option<String>,
+    pub recorded_by: Option<ShortUser>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryReadings {
+    pub machine_id: Uuid,
+    pub definition_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+// Time series, readings are grouped into buckets of the interval
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SeriesInterval {
+    Hour,
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl SeriesInterval {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeriesInterval::Hour => "hour",
+            SeriesInterval::Day => "day",
+            SeriesInterval::Week => "week",
+            SeriesInterval::Month => "month",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct QuerySeries {
+    pub machine_id: Uuid,
+    pub definition_id: Uuid,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub interval: SeriesInterval,
+}
+
+#[derive(Serialize)]
+pub struct SeriesPoint {
+    pub bucket: DateTime<Utc>,
+    pub count: i64,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub out_of_range: i64,
+}
+
+#[derive(Serialize)]
+pub struct Series {
+    pub name: String,
+    pub unit: String,
+    pub min_value: Option<f64>,
+    pub max_value: Option<f64>,
+    pub points: Vec<SeriesPoint>,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewReading {
+    pub definition_id: Uuid,
+    pub machine_id: Uuid,
+    pub task_id: Option<Uuid>,
+    pub value: f64,
+    pub recorded_at: Option<DateTime<Utc>>,
+    pub note: Option<String>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateReading {
+    pub id: Uuid,
+    #[serde(default)]
+    pub note: Nullable<String>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteReading {
+    pub id: Uuid,
+}
@@ -1,11 +1,21 @@
 pub mod facilities;
 pub mod handlers;
+pub mod locations;
+pub mod machine_counters;
 pub mod machine_statuses;
+pub mod machine_type_checklists;
+pub mod machine_type_parts;
+pub mod machine_type_skills;
 pub mod machine_types;
+pub mod measurement_definitions;
+pub mod measurements;
 pub mod models;
 
+pub use handlers::ancestors;
 pub use handlers::create;
 pub use handlers::delete;
 pub use handlers::details;
 pub use handlers::index;
+pub use handlers::location_history;
+pub use handlers::tree;
 pub use handlers::update;
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::prelude::{FromRow, Type};
 use uuid::Uuid;
 
-use crate::utils::db::Nullable;
+use crate::{users::models::ShortUser, utils::db::Nullable};
 
 use super::{facilities::Facility, machine_statuses::MachineStatus, machine_types::MachineType};
 
@@ -20,6 +20,8 @@ pub struct Machine {
     pub edited: DateTime<Utc>,
     pub facility: Option<Facility>,
     pub image: Option<String>,
+    pub parent: Option<Uuid>,
+    pub location: Option<Uuid>,
 }
 
 // Short variant
@@ -37,7 +39,13 @@ pub struct QueryMachine {
     pub id: Uuid,
 }
 
-// Create
+// Index, a location also lists the machines inside its sub-locations
+#[derive(Deserialize)]
+pub struct QueryMachines {
+    pub location_id: Option<Uuid>,
+}
+
+// Create, without a facility the machine takes the one of its location or parent
 #[derive(Deserialize)]
 pub struct NewMachine {
     pub name: String,
@@ -45,6 +53,8 @@ pub struct NewMachine {
     pub machine_type: Uuid,
     pub status: Uuid,
     pub facility: Option<Uuid>,
+    pub parent: Option<Uuid>,
+    pub location: Option<Uuid>,
 }
 
 #[derive(Deserialize)]
@@ -57,6 +67,10 @@ pub struct UpdateMachine {
     pub status: Option<Uuid>,
     #[serde(default)]
     pub facility: Nullable<Uuid>,
+    #[serde(default)]
+    pub parent: Nullable<Uuid>,
+    #[serde(default)]
+    pub location: Nullable<Uuid>,
 }
 
 // Delete
@@ -64,3 +78,51 @@ pub struct UpdateMachine {
 pub struct DeleteMachine {
     pub id: Uuid,
 }
+
+// Tree, the machine and everything below it, or every top level machine without an id.
+// The totals roll the open work and downtime of the components up to their parents,
+// downtime is counted from 30 days ago unless the range is given.
+// Machines outside the facilities of the user are left out, together with everything below them
+#[derive(Deserialize)]
+pub struct QueryMachineTree {
+    pub id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct MachineNode {
+    pub id: Uuid,
+    pub name: String,
+    pub parent: Option<Uuid>,
+    pub depth: i32,
+    pub status: MachineStatus,
+    pub facility: Option<Uuid>,
+    pub open_tasks: i64,
+    pub open_reports: i64,
+    pub downtime_minutes: i64,
+    pub total_open_tasks: i64,
+    pub total_open_reports: i64,
+    pub down_components: i64,
+    pub total_downtime_minutes: i64,
+}
+
+// Location history, newest move first
+#[derive(Serialize)]
+pub struct MachineMove {
+    pub id: Uuid,
+    pub from_location: Option<Uuid>,
+    pub from_name: Option<String>,
+    pub to_location: Option<Uuid>,
+    pub to_name: Option<String>,
+    pub moved_by: Option<ShortUser>,
+    pub moved_at: DateTime<Utc>,
+}
+
+// Ancestors, from the top level down to the direct parent
+#[derive(Serialize)]
+pub struct MachineAncestor {
+    pub id: Uuid,
+    pub name: String,
+    pub depth: i32,
+}
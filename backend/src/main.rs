@@ -1,13 +1,16 @@
 mod auth;
 mod channels;
 mod config;
+mod images;
 mod machines;
+mod parts;
 mod reports;
 mod router;
 mod tasks;
 mod users;
 mod utils;
 
+use auth::keys::KeyRing;
 use axum::http::{
     header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
     HeaderValue, Method,
@@ -16,10 +19,11 @@ use config::Config;
 use dotenv::dotenv;
 use router::create_router;
 use sqlx::postgres::{PgPool, PgPoolOptions};
-use std::{sync::Arc, time::Duration};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tokio::sync::{broadcast::Sender, Mutex};
 use tower_http::cors::CorsLayer;
 use tracing::info;
+use utils::storage::Storage;
 
 #[derive(Clone)]
 pub struct Channels {
@@ -31,7 +35,10 @@ pub struct Channels {
 pub struct AppState {
     db: PgPool,
     env: Config,
+    keys: KeyRing,
     channels: Channels,
+    http: reqwest::Client,
+    storage: Storage,
 }
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 6)]
@@ -49,15 +56,20 @@ async fn main() {
         .await
         .expect("Can't connect to Database");
 
+    auth::oidc::check_default_role(&pool, config.oidc.as_ref()).await;
+
     let (task_sender, report_sender) = channels::init_channels(&pool).await;
 
     let state = AppState {
         db: pool.clone(),
         env: config.clone(),
+        keys: KeyRing::init(&config),
         channels: Channels {
             tasks: Arc::new(Mutex::new(task_sender)),
             reports: Arc::new(Mutex::new(report_sender)),
         },
+        http: reqwest::Client::new(),
+        storage: Storage::init(&config.upload_path),
     };
 
     let cors = CorsLayer::new()
@@ -74,7 +86,10 @@ async fn main() {
 
     info!("Listening on 0.0.0.0:80");
 
-    axum::serve(listener, app)
-        .await
-        .expect("Can't start server");
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .expect("Can't start server");
 }
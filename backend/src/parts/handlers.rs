@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{PartCreate, PartDelete, PartEdit, PartView, Require},
+    field_vec, update_field,
+    users::models::User,
+    utils::{
+        db::{Field, IntoField, Nullable},
+        errors::{ApiError, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{NewPart, Part, QueryPart, QueryParts, UpdatePart};
+
+// Stock totals only count the facilities the user can see
+
+async fn fetch_parts(
+    conn: &mut PgConnection,
+    user: &User,
+    part_id: Option<Uuid>,
+    params: Option<&QueryParts>,
+) -> Result<Vec<Part>, ApiError> {
+    let parts = query_as!(
+        Part,
+        r#"
+        SELECT
+            p.id,
+            p.part_number,
+            p.name,
+            p.description,
+            p.unit,
+            p.unit_cost,
+            (
+                SELECT array_agg(mtp.machine_type_id)
+                FROM
+                    machine_type_parts mtp
+                WHERE
+                    mtp.part_id = p.id
+            ) AS machine_types,
+            COALESCE(stock.on_hand, 0) AS "on_hand!",
+            COALESCE(stock.on_hand - stock.reserved, 0) AS "available!",
+            p.created,
+            p.edited
+        FROM
+            parts p
+        LEFT JOIN LATERAL (
+            SELECT
+                sum(ps.on_hand) AS on_hand,
+                sum(ps.reserved) AS reserved
+            FROM
+                part_stock ps
+            WHERE
+                ps.part_id = p.id
+            AND
+                ($5::UUID[] IS NULL OR ps.facility_id = ANY($5))
+        ) stock ON TRUE
+        WHERE
+            ($1::UUID IS NULL OR p.id = $1)
+        AND
+            ($2::TEXT IS NULL OR p.name ILIKE '%' || $2 || '%' OR p.part_number ILIKE '%' || $2 || '%')
+        AND
+            ($3::UUID IS NULL OR p.id IN (
+                SELECT mtp.part_id FROM machine_type_parts mtp WHERE mtp.machine_type_id = $3
+            ))
+        AND
+            ($4::UUID IS NULL OR NOT EXISTS (
+                SELECT 1 FROM machine_type_parts mtp WHERE mtp.part_id = p.id
+            ) OR p.id IN (
+                SELECT
+                    mtp.part_id
+                FROM
+                    machines m
+                INNER JOIN
+                    machine_type_parts mtp
+                ON
+                    m.machine_type = mtp.machine_type_id
+                WHERE
+                    m.id = $4
+            ))
+        ORDER BY
+            p.name
+        "#,
+        part_id,
+        params.and_then(|params| params.search.as_deref()),
+        params.and_then(|params| params.machine_type_id),
+        params.and_then(|params| params.machine_id),
+        user.facility_scope()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(parts)
+}
+
+pub async fn details(
+    _: Require<PartView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryPart>,
+) -> Result<Json<Part>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let part = fetch_parts(&mut conn, &user, Some(params.id), None)
+        .await?
+        .pop()
+        .ok_or(ApiError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    Ok(Json(part))
+}
+
+pub async fn index(
+    _: Require<PartView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryParts>,
+) -> Result<Json<Vec<Part>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let parts = fetch_parts(&mut conn, &user, None, Some(&params)).await?;
+
+    Ok(Json(parts))
+}
+
+pub async fn create(
+    _: Require<PartCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewPart>,
+) -> Result<(StatusCode, Json<Part>), ApiError> {
+    if body.unit_cost.is_some_and(|unit_cost| unit_cost < 0) {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NegativeAmount));
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    let part_id = query_scalar!(
+        r#"
+        INSERT INTO
+            parts
+        (
+            part_number,
+            name,
+            description,
+            unit,
+            unit_cost
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            COALESCE($4, 'pcs'),
+            $5
+        )
+        RETURNING
+            id
+        "#,
+        body.part_number,
+        body.name,
+        body.description,
+        body.unit,
+        body.unit_cost
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if let Some(machine_types) = &body.machine_types {
+        query!(
+            r#"
+            INSERT INTO
+                machine_type_parts
+            (
+                machine_type_id,
+                part_id
+            )
+            SELECT unnest($2::uuid[]), $1
+            "#,
+            part_id,
+            machine_types
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let part = fetch_parts(&mut tx, &user, Some(part_id), None)
+        .await?
+        .pop()
+        .ok_or(ApiError::GeneralOversight(
+            "Created part wasn't found".to_owned(),
+        ))?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(part)))
+}
+
+pub async fn update(
+    _: Require<PartEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdatePart>,
+) -> Result<StatusCode, ApiError> {
+    if let Nullable::Value(unit_cost) = body.unit_cost {
+        if unit_cost < 0 {
+            return Err(ApiError::InputInvalid(InputInvalidReason::NegativeAmount));
+        }
+    }
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE parts SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        part_number => body.part_number,
+        name => body.name,
+        description => body.description,
+        unit => body.unit,
+        unit_cost => body.unit_cost
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    let result = query_builder.build().execute(&app_state.db).await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    _: Require<PartDelete>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryPart>,
+) -> Result<StatusCode, ApiError> {
+    let result = query!(r#"DELETE FROM parts WHERE id = $1"#, params.id)
+        .execute(&app_state.db)
+        .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
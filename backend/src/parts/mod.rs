@@ -0,0 +1,13 @@
+pub mod part_movements;
+pub mod part_stock;
+pub mod purchase_requests;
+pub mod suppliers;
+
+pub mod handlers;
+pub mod models;
+
+pub use handlers::create;
+pub use handlers::delete;
+pub use handlers::details;
+pub use handlers::index;
+pub use handlers::update;
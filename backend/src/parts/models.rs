@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::utils::db::Nullable;
+
+#[derive(Serialize)]
+pub struct Part {
+    pub id: Uuid,
+    pub part_number: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub unit: String,
+    pub unit_cost: Option<i32>,
+    pub machine_types: Option<Vec<Uuid>>,
+    pub on_hand: i64,
+    pub available: i64,
+    pub created: DateTime<Utc>,
+    pub edited: DateTime<Utc>,
+}
+
+// Details
+
+#[derive(Deserialize)]
+pub struct QueryPart {
+    pub id: Uuid,
+}
+
+// Index, machine_id lists the parts fitting that machine
+
+#[derive(Deserialize)]
+pub struct QueryParts {
+    pub search: Option<String>,
+    pub machine_type_id: Option<Uuid>,
+    pub machine_id: Option<Uuid>,
+}
+
+// Create, the unit cost is in cents
+
+#[derive(Deserialize)]
+pub struct NewPart {
+    pub part_number: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub unit: Option<String>,
+    pub unit_cost: Option<i32>,
+    pub machine_types: Option<Vec<Uuid>>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdatePart {
+    pub id: Uuid,
+    pub part_number: Option<String>,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Nullable<String>,
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub unit_cost: Nullable<i32>,
+}
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query_as, query_scalar, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{PartEdit, PartView, Require},
+    parts::part_stock::handlers::change_stock,
+    users::models::{ShortUser, User},
+    utils::errors::{ApiError, InputInvalidReason},
+    AppState,
+};
+
+use super::models::{
+    ManualMovementKind, NewPartMovement, PartMovement, QueryPartMovements, StockMovement,
+};
+
+struct MovementFilter<'a> {
+    movement_id: Option<Uuid>,
+    params: Option<&'a QueryPartMovements>,
+    facilities: Option<&'a [Uuid]>,
+}
+
+async fn fetch_movements(
+    conn: &mut PgConnection,
+    filter: MovementFilter<'_>,
+) -> Result<Vec<PartMovement>, ApiError> {
+    let params = filter.params;
+
+    let movements = query_as!(
+        PartMovement,
+        r#"
+        SELECT
+            pm.id,
+            pm.part_id,
+            p.part_number,
+            p.name,
+            pm.facility_id,
+            pm.change,
+            pm.kind,
+            pm.task_id,
+            pm.machine_id,
+            m.name AS "machine_name?",
+            pm.note,
+            (
+                u.id,
+                u.first_name,
+                u.last_name,
+                u.email,
+                u.image
+            ) AS "user?: ShortUser",
+            pm.created
+        FROM
+            part_movements pm
+        INNER JOIN
+            parts p
+        ON
+            pm.part_id = p.id
+        LEFT JOIN
+            machines m
+        ON
+            pm.machine_id = m.id
+        LEFT JOIN
+            users u
+        ON
+            pm.user_id = u.id
+        WHERE
+            ($1::UUID IS NULL OR pm.id = $1)
+        AND
+            ($2::UUID IS NULL OR pm.part_id = $2)
+        AND
+            ($3::UUID IS NULL OR pm.facility_id = $3)
+        AND
+            ($4::UUID IS NULL OR pm.machine_id = $4)
+        AND
+            ($5::UUID IS NULL OR pm.task_id = $5)
+        AND
+            ($6::TIMESTAMPTZ IS NULL OR pm.created >= $6)
+        AND
+            ($7::TIMESTAMPTZ IS NULL OR pm.created < $7)
+        AND
+            ($8::UUID[] IS NULL OR pm.facility_id = ANY($8))
+        ORDER BY
+            pm.created DESC
+        "#,
+        filter.movement_id,
+        params.and_then(|params| params.part_id),
+        params.and_then(|params| params.facility_id),
+        params.and_then(|params| params.machine_id),
+        params.and_then(|params| params.task_id),
+        params.and_then(|params| params.from),
+        params.and_then(|params| params.to),
+        filter.facilities
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(movements)
+}
+
+// Records a change of the stock on hand, the machine is taken from the task
+
+pub async fn record_movement(
+    conn: &mut PgConnection,
+    user: &User,
+    movement: &StockMovement<'_>,
+) -> Result<Uuid, ApiError> {
+    let movement_id = query_scalar!(
+        r#"
+        INSERT INTO
+            part_movements
+        (
+            part_id,
+            facility_id,
+            change,
+            kind,
+            task_id,
+            machine_id,
+            note,
+            user_id
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            (SELECT t.machine FROM tasks t WHERE t.id = $5),
+            $6,
+            $7
+        )
+        RETURNING
+            id
+        "#,
+        movement.part_id,
+        movement.facility_id,
+        movement.change,
+        movement.kind.as_str(),
+        movement.task_id,
+        movement.note,
+        user.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(movement_id)
+}
+
+pub async fn index(
+    _: Require<PartView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryPartMovements>,
+) -> Result<Json<Vec<PartMovement>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let movements = fetch_movements(
+        &mut conn,
+        MovementFilter {
+            movement_id: None,
+            params: Some(&params),
+            facilities: user.facility_scope(),
+        },
+    )
+    .await?;
+
+    Ok(Json(movements))
+}
+
+// Goods received or a stock count corrected by hand
+
+pub async fn create(
+    _: Require<PartEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewPartMovement>,
+) -> Result<(StatusCode, Json<PartMovement>), ApiError> {
+    let valid = match body.kind {
+        ManualMovementKind::Receipt => body.change > 0,
+        ManualMovementKind::Adjustment => body.change != 0,
+    };
+
+    if !valid {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidQuantity));
+    }
+
+    user.check_facility(Some(body.facility_id))?;
+
+    let mut tx = app_state.db.begin().await?;
+
+    change_stock(&mut tx, body.part_id, body.facility_id, body.change, 0).await?;
+
+    let movement_id = record_movement(
+        &mut tx,
+        &user,
+        &StockMovement {
+            part_id: body.part_id,
+            facility_id: body.facility_id,
+            change: body.change,
+            kind: body.kind.into(),
+            task_id: None,
+            note: body.note.as_deref(),
+        },
+    )
+    .await?;
+
+    let movement = fetch_movements(
+        &mut tx,
+        MovementFilter {
+            movement_id: Some(movement_id),
+            params: None,
+            facilities: None,
+        },
+    )
+    .await?
+    .pop()
+    .ok_or(ApiError::GeneralOversight(
+        "Created part movement wasn't found".to_owned(),
+    ))?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(movement)))
+}
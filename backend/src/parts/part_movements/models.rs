@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::users::models::ShortUser;
+
+#[derive(Serialize)]
+pub struct PartMovement {
+    pub id: Uuid,
+    pub part_id: Uuid,
+    pub part_number: String,
+    pub name: String,
+    pub facility_id: Uuid,
+    pub change: i32,
+    pub kind: String,
+    pub task_id: Option<Uuid>,
+    pub machine_id: Option<Uuid>,
+    pub machine_name: Option<String>,
+    pub note: Option<String>,
+    pub user: Option<ShortUser>,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy)]
+pub enum MovementKind {
+    Receipt,
+    Adjustment,
+    Consumption,
+    Return,
+}
+
+impl MovementKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MovementKind::Receipt => "receipt",
+            MovementKind::Adjustment => "adjustment",
+            MovementKind::Consumption => "consumption",
+            MovementKind::Return => "return",
+        }
+    }
+}
+
+// A change of the stock on hand as it's written to the history
+
+pub struct StockMovement<'a> {
+    pub part_id: Uuid,
+    pub facility_id: Uuid,
+    pub change: i32,
+    pub kind: MovementKind,
+    pub task_id: Option<Uuid>,
+    pub note: Option<&'a str>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryPartMovements {
+    pub part_id: Option<Uuid>,
+    pub facility_id: Option<Uuid>,
+    pub machine_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+// Create, consumption and returns only happen through the parts of a task
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ManualMovementKind {
+    Receipt,
+    Adjustment,
+}
+
+impl From<ManualMovementKind> for MovementKind {
+    fn from(kind: ManualMovementKind) -> Self {
+        match kind {
+            ManualMovementKind::Receipt => MovementKind::Receipt,
+            ManualMovementKind::Adjustment => MovementKind::Adjustment,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewPartMovement {
+    pub part_id: Uuid,
+    pub facility_id: Uuid,
+    pub change: i32,
+    pub kind: ManualMovementKind,
+    pub note: Option<String>,
+}
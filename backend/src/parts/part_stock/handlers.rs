@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{PartEdit, PartView, Require},
+    parts::purchase_requests::handlers::request_restock,
+    users::models::User,
+    utils::{
+        errors::{ApiError, ConflictReason, InputInvalidReason},
+        notifications::notify_low_stock,
+    },
+    AppState,
+};
+
+use super::models::{PartStock, QueryPartStock, UpdatePartStock};
+
+// Moves the stock on hand and the reservations of a part in a facility. Nothing may be
+// reserved that isn't on hand, and once the available stock drops to the minimum the
+// managers are alerted and a restock is requested
+
+pub async fn change_stock(
+    conn: &mut PgConnection,
+    part_id: Uuid,
+    facility_id: Uuid,
+    on_hand_change: i32,
+    reserved_change: i32,
+) -> Result<(), ApiError> {
+    query!(
+        r#"
+        INSERT INTO
+            part_stock
+        (
+            part_id,
+            facility_id
+        )
+        VALUES
+        (
+            $1,
+            $2
+        )
+        ON CONFLICT DO NOTHING
+        "#,
+        part_id,
+        facility_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let stock = query!(
+        r#"
+        SELECT
+            on_hand,
+            reserved,
+            min_quantity
+        FROM
+            part_stock
+        WHERE
+            part_id = $1
+        AND
+            facility_id = $2
+        FOR UPDATE
+        "#,
+        part_id,
+        facility_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let on_hand = stock.on_hand + on_hand_change;
+    let reserved = stock.reserved + reserved_change;
+
+    if reserved < 0 || on_hand < reserved {
+        return Err(ApiError::Conflict(ConflictReason::InsufficientStock));
+    }
+
+    query!(
+        r#"
+        UPDATE
+            part_stock
+        SET
+            on_hand = $3,
+            reserved = $4
+        WHERE
+            part_id = $1
+        AND
+            facility_id = $2
+        "#,
+        part_id,
+        facility_id,
+        on_hand,
+        reserved
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let was_low = stock.on_hand - stock.reserved <= stock.min_quantity;
+    let is_low = on_hand - reserved <= stock.min_quantity;
+
+    if is_low && !was_low {
+        notify_low_stock(conn, part_id, facility_id).await?;
+        request_restock(conn, part_id, facility_id).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn index(
+    _: Require<PartView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryPartStock>,
+) -> Result<Json<Vec<PartStock>>, ApiError> {
+    let stock = query_as!(
+        PartStock,
+        r#"
+        SELECT
+            ps.part_id,
+            p.part_number,
+            p.name,
+            p.unit,
+            ps.facility_id,
+            f.name AS facility,
+            ps.on_hand,
+            ps.reserved,
+            ps.on_hand - ps.reserved AS "available!",
+            ps.min_quantity,
+            ps.reorder_quantity,
+            ps.on_hand - ps.reserved <= ps.min_quantity AS "low!"
+        FROM
+            part_stock ps
+        INNER JOIN
+            parts p
+        ON
+            ps.part_id = p.id
+        INNER JOIN
+            facilities f
+        ON
+            ps.facility_id = f.id
+        WHERE
+            ($1::UUID IS NULL OR ps.part_id = $1)
+        AND
+            ($2::UUID IS NULL OR ps.facility_id = $2)
+        AND
+            (NOT $3 OR ps.on_hand - ps.reserved <= ps.min_quantity)
+        AND
+            ($4::UUID[] IS NULL OR ps.facility_id = ANY($4))
+        ORDER BY
+            p.name,
+            f.name
+        "#,
+        params.part_id,
+        params.facility_id,
+        params.low.unwrap_or(false),
+        user.facility_scope()
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(stock))
+}
+
+pub async fn update(
+    _: Require<PartEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdatePartStock>,
+) -> Result<StatusCode, ApiError> {
+    if body.min_quantity.is_none() && body.reorder_quantity.is_none() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    if body.min_quantity.is_some_and(|quantity| quantity < 0)
+        || body.reorder_quantity.is_some_and(|quantity| quantity < 0)
+    {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidQuantity));
+    }
+
+    user.check_facility(Some(body.facility_id))?;
+
+    query!(
+        r#"
+        INSERT INTO
+            part_stock
+        (
+            part_id,
+            facility_id,
+            min_quantity,
+            reorder_quantity
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            COALESCE($3, 0),
+            COALESCE($4, 0)
+        )
+        ON CONFLICT (part_id, facility_id) DO UPDATE SET
+            min_quantity = COALESCE($3, part_stock.min_quantity),
+            reorder_quantity = COALESCE($4, part_stock.reorder_quantity)
+        "#,
+        body.part_id,
+        body.facility_id,
+        body.min_quantity,
+        body.reorder_quantity
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct PartStock {
+    pub part_id: Uuid,
+    pub part_number: String,
+    pub name: String,
+    pub unit: String,
+    pub facility_id: Uuid,
+    pub facility: String,
+    pub on_hand: i32,
+    pub reserved: i32,
+    pub available: i32,
+    pub min_quantity: i32,
+    pub reorder_quantity: i32,
+    pub low: bool,
+}
+
+// Index, low only lists the stock at or below its minimum
+
+#[derive(Deserialize)]
+pub struct QueryPartStock {
+    pub part_id: Option<Uuid>,
+    pub facility_id: Option<Uuid>,
+    pub low: Option<bool>,
+}
+
+// Update, the minimum under which the facility gets alerted and a restock is requested,
+// and the least amount such a request asks for
+
+#[derive(Deserialize)]
+pub struct UpdatePartStock {
+    pub part_id: Uuid,
+    pub facility_id: Uuid,
+    pub min_quantity: Option<i32>,
+    pub reorder_quantity: Option<i32>,
+}
@@ -0,0 +1,547 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{
+        PurchaseRequestApprove, PurchaseRequestCreate, PurchaseRequestDelete, PurchaseRequestEdit,
+        PurchaseRequestView, Require,
+    },
+    field_vec,
+    parts::{
+        part_movements::{
+            handlers::record_movement,
+            models::{MovementKind, StockMovement},
+        },
+        part_stock::handlers::change_stock,
+    },
+    update_field,
+    users::models::{ShortUser, User},
+    utils::{
+        db::{Field, IntoField},
+        errors::{ApiError, ConflictReason, ForbiddenReason, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    ChangePurchaseStatus, DeletePurchaseRequest, NewPurchaseRequest, PurchaseRequest,
+    PurchaseSource, PurchaseStatus, QueryPurchaseRequest, QueryPurchaseRequests,
+    UpdatePurchaseRequest,
+};
+
+async fn fetch_requests(
+    conn: &mut PgConnection,
+    request_id: Option<Uuid>,
+    params: Option<&QueryPurchaseRequests>,
+    facilities: Option<&[Uuid]>,
+) -> Result<Vec<PurchaseRequest>, ApiError> {
+    let requests = query_as!(
+        PurchaseRequest,
+        r#"
+        SELECT
+            pr.id,
+            pr.part_id,
+            p.part_number,
+            p.name,
+            p.unit,
+            pr.facility_id,
+            f.name AS facility,
+            pr.quantity,
+            pr.supplier_id,
+            s.name AS "supplier?",
+            pr.status,
+            pr.source,
+            pr.task_id,
+            pr.note,
+            (
+                ru.id,
+                ru.first_name,
+                ru.last_name,
+                ru.email,
+                ru.image
+            ) AS "requested_by?: ShortUser",
+            (
+                du.id,
+                du.first_name,
+                du.last_name,
+                du.email,
+                du.image
+            ) AS "decided_by?: ShortUser",
+            pr.decided_at,
+            pr.ordered_at,
+            pr.received_at,
+            pr.created,
+            pr.edited
+        FROM
+            purchase_requests pr
+        INNER JOIN
+            parts p
+        ON
+            pr.part_id = p.id
+        INNER JOIN
+            facilities f
+        ON
+            pr.facility_id = f.id
+        LEFT JOIN
+            suppliers s
+        ON
+            pr.supplier_id = s.id
+        LEFT JOIN
+            users ru
+        ON
+            pr.requested_by = ru.id
+        LEFT JOIN
+            users du
+        ON
+            pr.decided_by = du.id
+        WHERE
+            ($1::UUID IS NULL OR pr.id = $1)
+        AND
+            ($2::TEXT IS NULL OR pr.status = $2)
+        AND
+            ($3::UUID IS NULL OR pr.part_id = $3)
+        AND
+            ($4::UUID IS NULL OR pr.facility_id = $4)
+        AND
+            ($5::UUID IS NULL OR pr.supplier_id = $5)
+        AND
+            ($6::UUID IS NULL OR pr.task_id = $6)
+        AND
+            ($7::UUID[] IS NULL OR pr.facility_id = ANY($7))
+        ORDER BY
+            pr.created DESC
+        "#,
+        request_id,
+        params
+            .and_then(|params| params.status)
+            .map(|status| status.as_str()),
+        params.and_then(|params| params.part_id),
+        params.and_then(|params| params.facility_id),
+        params.and_then(|params| params.supplier_id),
+        params.and_then(|params| params.task_id),
+        facilities
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(requests)
+}
+
+async fn fetch_request(
+    conn: &mut PgConnection,
+    request_id: Uuid,
+) -> Result<PurchaseRequest, ApiError> {
+    fetch_requests(conn, Some(request_id), None, None)
+        .await?
+        .pop()
+        .ok_or(ApiError::DatabaseError(sqlx::Error::RowNotFound))
+}
+
+// Raised when the available stock drops to the minimum, enough to get back above it and
+// at least the reorder quantity. Nothing is raised while a request is still open
+
+pub async fn request_restock(
+    conn: &mut PgConnection,
+    part_id: Uuid,
+    facility_id: Uuid,
+) -> Result<(), ApiError> {
+    query!(
+        r#"
+        INSERT INTO
+            purchase_requests
+        (
+            part_id,
+            facility_id,
+            quantity,
+            source
+        )
+        SELECT
+            ps.part_id,
+            ps.facility_id,
+            GREATEST(ps.reorder_quantity, ps.min_quantity - (ps.on_hand - ps.reserved) + 1),
+            $3
+        FROM
+            part_stock ps
+        WHERE
+            ps.part_id = $1
+        AND
+            ps.facility_id = $2
+        AND NOT EXISTS (
+            SELECT
+                1
+            FROM
+                purchase_requests pr
+            WHERE
+                pr.part_id = ps.part_id
+            AND
+                pr.facility_id = ps.facility_id
+            AND
+                pr.status IN ('requested', 'approved', 'ordered')
+        )
+        "#,
+        part_id,
+        facility_id,
+        PurchaseSource::LowStock.as_str()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// Raised for the parts a task is missing, once per task and part while it's open.
+// Returns the open request, so asking again hands back the same one
+
+pub async fn request_for_task(
+    conn: &mut PgConnection,
+    user: &User,
+    task_id: Uuid,
+    part_id: Uuid,
+    facility_id: Uuid,
+    quantity: i32,
+) -> Result<PurchaseRequest, ApiError> {
+    let request_id = query_scalar!(
+        r#"
+        WITH open_request AS (
+            SELECT
+                pr.id
+            FROM
+                purchase_requests pr
+            WHERE
+                pr.part_id = $1
+            AND
+                pr.task_id = $5
+            AND
+                pr.status IN ('requested', 'approved', 'ordered')
+            LIMIT 1
+        ), new_request AS (
+            INSERT INTO
+                purchase_requests
+            (
+                part_id,
+                facility_id,
+                quantity,
+                source,
+                task_id,
+                requested_by
+            )
+            SELECT
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6
+            WHERE NOT EXISTS (SELECT 1 FROM open_request)
+            RETURNING
+                id
+        )
+        SELECT id AS "id!" FROM open_request
+        UNION ALL
+        SELECT id FROM new_request
+        "#,
+        part_id,
+        facility_id,
+        quantity,
+        PurchaseSource::Task.as_str(),
+        task_id,
+        user.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    fetch_requests(conn, Some(request_id), None, None)
+        .await?
+        .pop()
+        .ok_or(ApiError::GeneralOversight(
+            "Requested purchase wasn't found".to_owned(),
+        ))
+}
+
+pub async fn details(
+    _: Require<PurchaseRequestView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryPurchaseRequest>,
+) -> Result<Json<PurchaseRequest>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let request = fetch_requests(&mut conn, Some(params.id), None, user.facility_scope())
+        .await?
+        .pop()
+        .ok_or(ApiError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    Ok(Json(request))
+}
+
+pub async fn index(
+    _: Require<PurchaseRequestView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryPurchaseRequests>,
+) -> Result<Json<Vec<PurchaseRequest>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let requests = fetch_requests(&mut conn, None, Some(&params), user.facility_scope()).await?;
+
+    Ok(Json(requests))
+}
+
+pub async fn create(
+    _: Require<PurchaseRequestCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewPurchaseRequest>,
+) -> Result<(StatusCode, Json<PurchaseRequest>), ApiError> {
+    if body.quantity <= 0 {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidQuantity));
+    }
+
+    user.check_facility(Some(body.facility_id))?;
+
+    let source = match body.task_id {
+        Some(_) => PurchaseSource::Task,
+        None => PurchaseSource::Manual,
+    };
+
+    let mut tx = app_state.db.begin().await?;
+
+    let request_id = query_scalar!(
+        r#"
+        INSERT INTO
+            purchase_requests
+        (
+            part_id,
+            facility_id,
+            quantity,
+            supplier_id,
+            source,
+            task_id,
+            note,
+            requested_by
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            $6,
+            $7,
+            $8
+        )
+        RETURNING
+            id
+        "#,
+        body.part_id,
+        body.facility_id,
+        body.quantity,
+        body.supplier_id,
+        source.as_str(),
+        body.task_id,
+        body.note,
+        user.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let request = fetch_request(&mut tx, request_id).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(request)))
+}
+
+pub async fn update(
+    _: Require<PurchaseRequestEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdatePurchaseRequest>,
+) -> Result<StatusCode, ApiError> {
+    if body.quantity.is_some_and(|quantity| quantity <= 0) {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidQuantity));
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    let current = query!(
+        r#"SELECT facility_id, status FROM purchase_requests WHERE id = $1 FOR UPDATE"#,
+        body.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    user.check_facility(Some(current.facility_id))?;
+
+    if current.status != PurchaseStatus::Requested.as_str()
+        && current.status != PurchaseStatus::Approved.as_str()
+    {
+        return Err(ApiError::Conflict(ConflictReason::InvalidStatusTransition));
+    }
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE purchase_requests SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        quantity => body.quantity,
+        supplier_id => body.supplier_id,
+        note => body.note
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    query_builder.build().execute(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Approving and rejecting take purchase_request.approve, ordering, receiving and
+// cancelling purchase_request.edit
+
+pub async fn status(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<ChangePurchaseStatus>,
+) -> Result<Json<PurchaseRequest>, ApiError> {
+    let allowed = match body.status {
+        PurchaseStatus::Approved | PurchaseStatus::Rejected => {
+            user.role.has::<PurchaseRequestApprove>()
+        }
+        _ => user.role.has::<PurchaseRequestEdit>(),
+    };
+
+    if !allowed {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    let current = query!(
+        r#"
+        SELECT
+            part_id,
+            facility_id,
+            quantity,
+            status
+        FROM
+            purchase_requests
+        WHERE
+            id = $1
+        FOR UPDATE
+        "#,
+        body.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    user.check_facility(Some(current.facility_id))?;
+
+    if !PurchaseStatus::next(&current.status).contains(&body.status) {
+        return Err(ApiError::Conflict(ConflictReason::InvalidStatusTransition));
+    }
+
+    let quantity = match body.status {
+        PurchaseStatus::Received => {
+            let received = body.received_quantity.unwrap_or(current.quantity);
+
+            if received <= 0 {
+                return Err(ApiError::InputInvalid(InputInvalidReason::InvalidQuantity));
+            }
+
+            change_stock(&mut tx, current.part_id, current.facility_id, received, 0).await?;
+
+            let note = format!("Purchase request {}", body.id);
+
+            record_movement(
+                &mut tx,
+                &user,
+                &StockMovement {
+                    part_id: current.part_id,
+                    facility_id: current.facility_id,
+                    change: received,
+                    kind: MovementKind::Receipt,
+                    task_id: None,
+                    note: Some(&note),
+                },
+            )
+            .await?;
+
+            received
+        }
+        _ => current.quantity,
+    };
+
+    query!(
+        r#"
+        UPDATE
+            purchase_requests
+        SET
+            status = $2::TEXT,
+            quantity = $3,
+            decided_by = CASE WHEN $2::TEXT IN ('approved', 'rejected') THEN $4 ELSE decided_by END,
+            decided_at = CASE WHEN $2::TEXT IN ('approved', 'rejected') THEN NOW() ELSE decided_at END,
+            ordered_at = CASE WHEN $2::TEXT = 'ordered' THEN NOW() ELSE ordered_at END,
+            received_at = CASE WHEN $2::TEXT = 'received' THEN NOW() ELSE received_at END
+        WHERE
+            id = $1
+        "#,
+        body.id,
+        body.status.as_str(),
+        quantity,
+        user.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let request = fetch_request(&mut tx, body.id).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(request))
+}
+
+pub async fn delete(
+    _: Require<PurchaseRequestDelete>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeletePurchaseRequest>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let facility_id = query_scalar!(
+        r#"SELECT facility_id FROM purchase_requests WHERE id = $1"#,
+        params.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    user.check_facility(Some(facility_id))?;
+
+    let result = query!(r#"DELETE FROM purchase_requests WHERE id = $1"#, params.id)
+        .execute(&mut *conn)
+        .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{users::models::ShortUser, utils::db::Nullable};
+
+#[derive(Serialize)]
+pub struct PurchaseRequest {
+    pub id: Uuid,
+    pub part_id: Uuid,
+    pub part_number: String,
+    pub name: String,
+    pub unit: String,
+    pub facility_id: Uuid,
+    pub facility: String,
+    pub quantity: i32,
+    pub supplier_id: Option<Uuid>,
+    pub supplier: Option<String>,
+    pub status: String,
+    pub source: String,
+    pub task_id: Option<Uuid>,
+    pub note: Option<String>,
+    pub requested_by: Option<ShortUser>,
+    pub decided_by: Option<ShortUser>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub ordered_at: Option<DateTime<Utc>>,
+    pub received_at: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+    pub edited: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PurchaseStatus {
+    Requested,
+    Approved,
+    Rejected,
+    Ordered,
+    Received,
+    Cancelled,
+}
+
+impl PurchaseStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PurchaseStatus::Requested => "requested",
+            PurchaseStatus::Approved => "approved",
+            PurchaseStatus::Rejected => "rejected",
+            PurchaseStatus::Ordered => "ordered",
+            PurchaseStatus::Received => "received",
+            PurchaseStatus::Cancelled => "cancelled",
+        }
+    }
+
+    // The statuses a request may move on to from this one
+
+    pub fn next(status: &str) -> &'static [PurchaseStatus] {
+        match status {
+            "requested" => &[
+                PurchaseStatus::Approved,
+                PurchaseStatus::Rejected,
+                PurchaseStatus::Cancelled,
+            ],
+            "approved" => &[PurchaseStatus::Ordered, PurchaseStatus::Cancelled],
+            "ordered" => &[PurchaseStatus::Received, PurchaseStatus::Cancelled],
+            _ => &[],
+        }
+    }
+}
+
+pub enum PurchaseSource {
+    Manual,
+    LowStock,
+    Task,
+}
+
+impl PurchaseSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PurchaseSource::Manual => "manual",
+            PurchaseSource::LowStock => "low_stock",
+            PurchaseSource::Task => "task",
+        }
+    }
+}
+
+// Details
+
+#[derive(Deserialize)]
+pub struct QueryPurchaseRequest {
+    pub id: Uuid,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryPurchaseRequests {
+    pub status: Option<PurchaseStatus>,
+    pub part_id: Option<Uuid>,
+    pub facility_id: Option<Uuid>,
+    pub supplier_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewPurchaseRequest {
+    pub part_id: Uuid,
+    pub facility_id: Uuid,
+    pub quantity: i32,
+    pub supplier_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+    pub note: Option<String>,
+}
+
+// Update, only while the request hasn't been ordered
+
+#[derive(Deserialize)]
+pub struct UpdatePurchaseRequest {
+    pub id: Uuid,
+    pub quantity: Option<i32>,
+    #[serde(default)]
+    pub supplier_id: Nullable<Uuid>,
+    #[serde(default)]
+    pub note: Nullable<String>,
+}
+
+// Status, receiving books the parts into the stock, fewer than requested if the
+// delivery was short
+
+#[derive(Deserialize)]
+pub struct ChangePurchaseStatus {
+    pub id: Uuid,
+    pub status: PurchaseStatus,
+    pub received_quantity: Option<i32>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeletePurchaseRequest {
+    pub id: Uuid,
+}
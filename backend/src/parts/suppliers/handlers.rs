@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use sqlx::{query, query_as, Postgres, QueryBuilder};
+
+use crate::{
+    auth::permissions::{Require, SupplierCreate, SupplierDelete, SupplierEdit, SupplierView},
+    field_vec, update_field,
+    utils::{
+        db::{Field, IntoField},
+        errors::{ApiError, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{NewSupplier, QuerySupplier, QuerySuppliers, Supplier, UpdateSupplier};
+
+pub async fn details(
+    _: Require<SupplierView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QuerySupplier>,
+) -> Result<Json<Supplier>, ApiError> {
+    let supplier = query_as!(
+        Supplier,
+        r#"
+        SELECT
+            s.id,
+            s.name,
+            s.contact_name,
+            s.email,
+            s.phone,
+            s.address,
+            s.created
+        FROM
+            suppliers s
+        WHERE
+            s.id = $1
+        "#,
+        params.id
+    )
+    .fetch_one(&app_state.db)
+    .await?;
+
+    Ok(Json(supplier))
+}
+
+pub async fn index(
+    _: Require<SupplierView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QuerySuppliers>,
+) -> Result<Json<Vec<Supplier>>, ApiError> {
+    let suppliers = query_as!(
+        Supplier,
+        r#"
+        SELECT
+            s.id,
+            s.name,
+            s.contact_name,
+            s.email,
+            s.phone,
+            s.address,
+            s.created
+        FROM
+            suppliers s
+        WHERE
+            ($1::TEXT IS NULL OR s.name ILIKE '%' || $1 || '%')
+        ORDER BY
+            s.name
+        "#,
+        params.search
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(suppliers))
+}
+
+pub async fn create(
+    _: Require<SupplierCreate>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewSupplier>,
+) -> Result<(StatusCode, Json<Supplier>), ApiError> {
+    let supplier = query_as!(
+        Supplier,
+        r#"
+        INSERT INTO
+            suppliers
+        (
+            name,
+            contact_name,
+            email,
+            phone,
+            address
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5
+        )
+        RETURNING
+            id,
+            name,
+            contact_name,
+            email,
+            phone,
+            address,
+            created
+        "#,
+        body.name,
+        body.contact_name,
+        body.email,
+        body.phone,
+        body.address
+    )
+    .fetch_one(&app_state.db)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(supplier)))
+}
+
+pub async fn update(
+    _: Require<SupplierEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateSupplier>,
+) -> Result<StatusCode, ApiError> {
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE suppliers SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        name => body.name,
+        contact_name => body.contact_name,
+        email => body.email,
+        phone => body.phone,
+        address => body.address
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    let result = query_builder.build().execute(&app_state.db).await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    _: Require<SupplierDelete>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QuerySupplier>,
+) -> Result<StatusCode, ApiError> {
+    let result = query!(r#"DELETE FROM suppliers WHERE id = $1"#, params.id)
+        .execute(&app_state.db)
+        .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
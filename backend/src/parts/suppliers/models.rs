@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::utils::db::Nullable;
+
+#[derive(Serialize)]
+pub struct Supplier {
+    pub id: Uuid,
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+// Details
+
+#[derive(Deserialize)]
+pub struct QuerySupplier {
+    pub id: Uuid,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QuerySuppliers {
+    pub search: Option<String>,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewSupplier {
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateSupplier {
+    pub id: Uuid,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub contact_name: Nullable<String>,
+    #[serde(default)]
+    pub email: Nullable<String>,
+    #[serde(default)]
+    pub phone: Nullable<String>,
+    #[serde(default)]
+    pub address: Nullable<String>,
+}
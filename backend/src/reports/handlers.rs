@@ -5,28 +5,75 @@ use axum::{
     http::StatusCode,
     Extension, Json,
 };
-use sqlx::{query, query_as, query_scalar, Postgres, QueryBuilder};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
 
 use crate::{
+    auth::permissions::{ReportCreate, ReportDelete, ReportView, Require},
     field_vec,
-    machines::models::ShortMachine,
+    machines::{handlers::check_machine_scope, models::ShortMachine},
     update_field,
     users::models::{ShortUser, User},
     utils::{
-        check_permission,
-        db::{Field, IntoField},
+        db::{Field, IntoField, Nullable},
         errors::{ApiError, ForbiddenReason, InputInvalidReason},
     },
     AppState,
 };
 
 use super::{
-    models::{DeleteReport, NewReport, QueryReport, Report, UpdateReport},
+    models::{
+        DeleteReport, NewReport, QueryReport, QueryReports, Report, ReportOwnership, UpdateReport,
+    },
     report_documents::ReportDocument,
     report_statuses::ReportStatus,
     report_types::ReportType,
 };
 
+// Same facility rule as the delete handler, for handlers on a single report
+
+pub async fn check_report_scope(
+    conn: &mut PgConnection,
+    user: &User,
+    report_id: Uuid,
+) -> Result<(), ApiError> {
+    if user.facility_scope().is_none() {
+        return Ok(());
+    }
+
+    let facility = query_scalar!(r#"SELECT report_facility($1)"#, report_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    user.check_facility(facility)
+}
+
+// Whether the user created the report, for the ownership rules
+
+pub async fn report_ownership(
+    conn: &mut PgConnection,
+    user: &User,
+    report_id: Uuid,
+) -> Result<ReportOwnership, ApiError> {
+    let ownership = query_as!(
+        ReportOwnership,
+        r#"
+        SELECT
+            r.creator = $2 AS "is_creator!"
+        FROM
+            reports r
+        WHERE
+            r.id = $1
+        "#,
+        report_id,
+        user.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(ownership)
+}
+
 pub async fn details(
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
@@ -34,13 +81,19 @@ pub async fn details(
 ) -> Result<Json<Vec<Report>>, ApiError> {
     let user_id = user.id;
 
-    let permissions_ok =
-        user.role.report_view || params.creator_id.map_or(false, |id| id == user_id);
+    // Asking for your own reports is allowed without report.view and isn't limited to your facilities
+
+    let is_own = params.creator_id == Some(user_id);
 
-    if !permissions_ok {
+    if !user.role.has::<ReportView>() && !is_own {
         return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
     }
 
+    let scope = match is_own {
+        true => None,
+        false => user.facility_scope(),
+    };
+
     let reports = query_as!(
         Report,
         r#"
@@ -73,6 +126,7 @@ pub async fn details(
             (
                 SELECT array_agg(
                     (
+                        rd.report_id,
                         rd.uri,
                         rd.name,
                         rd.description
@@ -105,9 +159,12 @@ pub async fn details(
             ($1::UUID IS NULL OR r.id = $1)
         AND
             ($2::UUID IS NULL OR r.creator = $2)
+        AND
+            ($3::UUID[] IS NULL OR COALESCE(m.facility, u.facility) = ANY($3))
         "#,
         params.report_id,
-        params.creator_id
+        params.creator_id,
+        scope
     )
     .fetch_all(&app_state.db)
     .await?;
@@ -116,11 +173,11 @@ pub async fn details(
 }
 
 pub async fn index(
+    _: Require<ReportView>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryReports>,
 ) -> Result<Json<Vec<Report>>, ApiError> {
-    check_permission(user.role.report_view)?;
-
     let reports = query_as!(
         Report,
         r#"
@@ -153,6 +210,7 @@ pub async fn index(
             (
                 SELECT array_agg(
                     (
+                        rd.report_id,
                         rd.uri,
                         rd.name,
                         rd.description
@@ -181,7 +239,13 @@ pub async fn index(
             machines m
         ON
             r.machine = m.id
-        "#
+        WHERE
+            ($1::UUID[] IS NULL OR COALESCE(m.facility, u.facility) = ANY($1))
+        AND
+            ($2::UUID IS NULL OR m.location IN (SELECT location_subtree($2)))
+        "#,
+        user.facility_scope(),
+        params.location_id
     )
     .fetch_all(&app_state.db)
     .await?;
@@ -190,14 +254,17 @@ pub async fn index(
 }
 
 pub async fn create(
+    _: Require<ReportCreate>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewReport>,
 ) -> Result<(StatusCode, Json<Report>), ApiError> {
-    check_permission(user.role.report_create)?;
-
     let mut tx = app_state.db.begin().await?;
 
+    if let Some(machine_id) = body.machine {
+        check_machine_scope(&mut tx, &user, machine_id).await?;
+    }
+
     let report_id = query_scalar!(
         r#"
         INSERT INTO
@@ -208,7 +275,8 @@ pub async fn create(
             report_type,
             status,
             archived,
-            creator
+            creator,
+            machine
         )
         VALUES
         (
@@ -217,7 +285,8 @@ pub async fn create(
             $3,
             $4,
             $5,
-            $6
+            $6,
+            $7
         )
         RETURNING
             id
@@ -228,6 +297,7 @@ pub async fn create(
         body.status,
         body.archived.unwrap_or(false),
         user.id,
+        body.machine
     )
     .fetch_one(&mut *tx)
     .await?;
@@ -264,6 +334,7 @@ pub async fn create(
             (
                 SELECT array_agg(
                     (
+                        rd.report_id,
                         rd.uri,
                         rd.name,
                         rd.description
@@ -310,10 +381,21 @@ pub async fn update(
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateReport>,
 ) -> Result<Json<Report>, ApiError> {
-    check_permission(user.role.report_edit)?;
-
     let mut tx = app_state.db.begin().await?;
 
+    check_report_scope(&mut tx, &user, body.id).await?;
+
+    if !report_ownership(&mut tx, &user, body.id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    if let Nullable::Value(machine_id) = body.machine {
+        check_machine_scope(&mut tx, &user, machine_id).await?;
+    }
+
     let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE reports SET");
     let mut separated_list = query_builder.separated(",");
 
@@ -377,6 +459,7 @@ pub async fn update(
             (
                 SELECT array_agg(
                     (
+                        rd.report_id,
                         rd.uri,
                         rd.name,
                         rd.description
@@ -423,7 +506,15 @@ pub async fn delete(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<DeleteReport>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.report_delete)?;
+    let mut conn = app_state.db.acquire().await?;
+
+    if !user.role.has::<ReportDelete>() {
+        let ownership = report_ownership(&mut conn, &user, params.id).await?;
+
+        if !ownership.can_delete(&user) {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+    }
 
     let result = query!(
         r#"
@@ -431,10 +522,13 @@ pub async fn delete(
             reports r
         WHERE
             r.id = $1
+        AND
+            ($2::UUID[] IS NULL OR report_facility(r.id) = ANY($2))
         "#,
-        params.id
+        params.id,
+        user.facility_scope()
     )
-    .execute(&app_state.db)
+    .execute(&mut *conn)
     .await?;
 
     match result.rows_affected() {
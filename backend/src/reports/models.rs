@@ -2,7 +2,12 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{machines::models::ShortMachine, users::models::ShortUser, utils::db::Nullable};
+use crate::{
+    auth::permissions::{ReportDelete, ReportDeleteOwn, ReportEdit, ReportEditOwn},
+    machines::models::ShortMachine,
+    users::models::{ShortUser, User},
+    utils::db::Nullable,
+};
 
 use super::{
     report_documents::ReportDocument, report_statuses::ReportStatus, report_types::ReportType,
@@ -23,6 +28,22 @@ pub struct Report {
     pub edited: DateTime<Utc>,
 }
 
+// Ownership
+
+pub struct ReportOwnership {
+    pub is_creator: bool,
+}
+
+impl ReportOwnership {
+    pub fn can_edit(&self, user: &User) -> bool {
+        user.role.has::<ReportEdit>() || (self.is_creator && user.role.has::<ReportEditOwn>())
+    }
+
+    pub fn can_delete(&self, user: &User) -> bool {
+        user.role.has::<ReportDelete>() || (self.is_creator && user.role.has::<ReportDeleteOwn>())
+    }
+}
+
 // Details
 
 #[derive(Deserialize)]
@@ -31,6 +52,13 @@ pub struct QueryReport {
     pub creator_id: Option<Uuid>,
 }
 
+// Index, a location lists the reports on machines anywhere inside it
+
+#[derive(Deserialize)]
+pub struct QueryReports {
+    pub location_id: Option<Uuid>,
+}
+
 // Create
 
 #[derive(Deserialize)]
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use sqlx::query;
+
+use crate::{
+    reports::handlers::{check_report_scope, report_ownership},
+    users::models::User,
+    utils::errors::{ApiError, ForbiddenReason},
+    AppState,
+};
+
+use super::models::{DeleteReportDocument, NewReportDocument};
+
+pub async fn create(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewReportDocument>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_report_scope(&mut conn, &user, body.report_id).await?;
+
+    if !report_ownership(&mut conn, &user, body.report_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let result = query!(
+        r#"
+        INSERT INTO
+            report_documents
+        (
+            report_id,
+            uri,
+            name,
+            description
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4
+        )
+        "#,
+        body.report_id,
+        body.uri,
+        body.name,
+        body.description
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::CREATED),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<DeleteReportDocument>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_report_scope(&mut conn, &user, body.report_id).await?;
+
+    if !report_ownership(&mut conn, &user, body.report_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let result = query!(
+        r#"
+        DELETE FROM
+            report_documents
+        WHERE
+            report_id = $1
+        AND
+            uri = $2
+        "#,
+        body.report_id,
+        body.uri
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -1,11 +1,29 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use sqlx::prelude::Type;
 use uuid::Uuid;
 
 #[derive(Type, Debug, Serialize)]
 pub struct ReportDocument {
-    pub task_id: Option<Uuid>,
+    pub report_id: Option<Uuid>,
     pub uri: Option<String>,
     pub name: Option<String>,
     pub description: Option<String>,
 }
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewReportDocument {
+    pub report_id: Uuid,
+    pub uri: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteReportDocument {
+    pub report_id: Uuid,
+    pub uri: String,
+}
@@ -3,13 +3,15 @@ use std::sync::Arc;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Extension, Json,
+    Json,
 };
 use sqlx::{query, query_as};
 
 use crate::{
-    users::models::User,
-    utils::{check_permission, errors::ApiError},
+    auth::permissions::{
+        ReportStatusCreate, ReportStatusDelete, ReportStatusEdit, ReportStatusView, Require,
+    },
+    utils::errors::ApiError,
     AppState,
 };
 
@@ -19,12 +21,10 @@ use super::{
 };
 
 pub async fn details(
-    Extension(user): Extension<User>,
+    _: Require<ReportStatusView>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryReportStatus>,
 ) -> Result<Json<ReportStatus>, ApiError> {
-    check_permission(user.role.report_view)?;
-
     let report_status = query_as!(
         ReportStatus,
         r#"
@@ -44,11 +44,9 @@ pub async fn details(
 }
 
 pub async fn index(
-    Extension(user): Extension<User>,
+    _: Require<ReportStatusView>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<ReportStatus>>, ApiError> {
-    check_permission(user.role.report_view)?;
-
     let report_statuses = query_as!(
         ReportStatus,
         r#"
@@ -65,12 +63,10 @@ pub async fn index(
 }
 
 pub async fn create(
-    Extension(user): Extension<User>,
+    _: Require<ReportStatusCreate>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewReportStatus>,
 ) -> Result<(StatusCode, Json<ReportStatus>), ApiError> {
-    check_permission(user.role.report_create)?;
-
     let report_status = query_as!(
         ReportStatus,
         r#"
@@ -95,12 +91,10 @@ pub async fn create(
 }
 
 pub async fn update(
-    Extension(user): Extension<User>,
+    _: Require<ReportStatusEdit>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateReportStatus>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.report_edit)?;
-
     let result = query!(
         r#"
         UPDATE 
@@ -123,12 +117,10 @@ pub async fn update(
 }
 
 pub async fn delete(
-    Extension(user): Extension<User>,
+    _: Require<ReportStatusDelete>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryReportStatus>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.report_delete)?;
-
     let result = query!(r#"DELETE FROM report_statuses WHERE id = $1"#, params.id)
         .execute(&app_state.db)
         .await?;
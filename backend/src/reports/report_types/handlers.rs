@@ -3,13 +3,15 @@ use std::sync::Arc;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Extension, Json,
+    Json,
 };
 use sqlx::{query, query_as};
 
 use crate::{
-    users::models::User,
-    utils::{check_permission, errors::ApiError},
+    auth::permissions::{
+        ReportTypeCreate, ReportTypeDelete, ReportTypeEdit, ReportTypeView, Require,
+    },
+    utils::errors::ApiError,
     AppState,
 };
 
@@ -19,12 +21,10 @@ use super::{
 };
 
 pub async fn details(
-    Extension(user): Extension<User>,
+    _: Require<ReportTypeView>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryReportType>,
 ) -> Result<Json<ReportType>, ApiError> {
-    check_permission(user.role.report_view)?;
-
     let report_type = query_as!(
         ReportType,
         r#"
@@ -44,11 +44,9 @@ pub async fn details(
 }
 
 pub async fn index(
-    Extension(user): Extension<User>,
+    _: Require<ReportTypeView>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<ReportType>>, ApiError> {
-    check_permission(user.role.report_view)?;
-
     let report_types = query_as!(
         ReportType,
         r#"
@@ -65,12 +63,10 @@ pub async fn index(
 }
 
 pub async fn create(
-    Extension(user): Extension<User>,
+    _: Require<ReportTypeCreate>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewReportType>,
 ) -> Result<(StatusCode, Json<ReportType>), ApiError> {
-    check_permission(user.role.report_create)?;
-
     let report_type = query_as!(
         ReportType,
         r#"
@@ -95,12 +91,10 @@ pub async fn create(
 }
 
 pub async fn update(
-    Extension(user): Extension<User>,
+    _: Require<ReportTypeEdit>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateReportType>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.report_edit)?;
-
     let result = query!(
         r#"
         UPDATE 
@@ -123,12 +117,10 @@ pub async fn update(
 }
 
 pub async fn delete(
-    Extension(user): Extension<User>,
+    _: Require<ReportTypeDelete>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryReportType>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.report_delete)?;
-
     let result = query!(r#"DELETE FROM report_types WHERE id = $1"#, params.id)
         .execute(&app_state.db)
         .await?;
@@ -1,13 +1,27 @@
 use crate::{
-    auth::{self, auth},
+    auth::{self, auth, impersonation, login_history, oidc},
     channels,
-    machines::{self, facilities, machine_statuses, machine_types},
-    reports::{self, report_statuses, report_types},
-    tasks::{self, task_executors, task_statuses, task_types},
-    users::{self, roles},
+    images::{self, models::MAX_UPLOAD_BYTES},
+    machines::{
+        self, facilities, locations, machine_counters, machine_statuses, machine_type_checklists,
+        machine_type_parts, machine_type_skills, machine_types, measurement_definitions,
+        measurements,
+    },
+    parts::{self, part_movements, part_stock, purchase_requests, suppliers},
+    reports::{self, report_documents, report_statuses, report_types},
+    tasks::{
+        self, checklists, dispatch, maintenance_rules, task_checklists, task_costs, task_documents,
+        task_executors, task_parts, task_statuses, task_teams, task_time_entries,
+        task_type_checklists, task_type_skills, task_types,
+    },
+    users::{
+        self, api_keys, availabilities, bulk, invitations, roles, skills, team_members, teams,
+        user_rates, user_skills,
+    },
     AppState,
 };
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
     routing::{delete, get, get_service, post, put},
     Router,
@@ -35,18 +49,83 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
         // Auth
         .route("/logout", get(auth::logout))
         .route("/me", get(auth::me))
+        .route("/me", put(auth::update_me))
+        .route(
+            "/me/image",
+            post(images::upload_my_image).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route("/me/image", delete(images::delete_my_image))
+        .route("/login_history", get(login_history::index))
+        .route("/impersonations", get(impersonation::index))
+        .route("/impersonation", post(impersonation::create))
+        .route("/impersonation", delete(impersonation::delete))
         // Users
         .route("/user", get(users::details))
         .route("/users", get(users::index))
         .route("/user", post(users::create))
         .route("/user", put(users::update))
         .route("/user", delete(users::delete))
+        .route("/users/import", post(bulk::import))
+        .route("/users/export", get(bulk::export))
+        // Images
+        .route("/images/:kind/:file", get(images::serve))
+        .route(
+            "/user/image",
+            post(images::upload_user_image).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route("/user/image", delete(images::delete_user_image))
+        .route(
+            "/machine/image",
+            post(images::upload_machine_image).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route("/machine/image", delete(images::delete_machine_image))
+        // Invitations
+        .route("/invitations", get(invitations::index))
+        .route("/invitation", post(invitations::create))
+        .route("/invitation", delete(invitations::delete))
+        // Teams
+        .route("/team", get(teams::details))
+        .route("/teams", get(teams::index))
+        .route("/team", post(teams::create))
+        .route("/team", put(teams::update))
+        .route("/team", delete(teams::delete))
+        // TeamMembers
+        .route("/team_member", post(team_members::create))
+        .route("/team_member", delete(team_members::delete))
+        // Availabilities
+        .route("/availabilities", get(availabilities::index))
+        .route("/availabilities/available", get(availabilities::available))
+        .route("/availability", post(availabilities::create))
+        .route("/availability", put(availabilities::update))
+        .route("/availability", delete(availabilities::delete))
+        // Skills
+        .route("/skill", get(skills::details))
+        .route("/skills", get(skills::index))
+        .route("/skill", post(skills::create))
+        .route("/skill", put(skills::update))
+        .route("/skill", delete(skills::delete))
+        // UserSkills
+        .route("/user_skills", get(user_skills::index))
+        .route("/user_skills/expiring", get(user_skills::expiring))
+        .route("/user_skill", post(user_skills::create))
+        .route("/user_skill", delete(user_skills::delete))
+        // UserRates
+        .route("/user_rates", get(user_rates::index))
+        .route("/user_rate", put(user_rates::update))
+        .route("/user_rate", delete(user_rates::delete))
         // Roles
         .route("/role", get(roles::details))
         .route("/roles", get(roles::index))
         .route("/role", post(roles::create))
         .route("/role", put(roles::update))
         .route("/role", delete(roles::delete))
+        .route("/permissions", get(roles::permissions))
+        // ApiKeys
+        .route("/api_key", get(api_keys::details))
+        .route("/api_keys", get(api_keys::index))
+        .route("/api_key", post(api_keys::create))
+        .route("/api_key", put(api_keys::update))
+        .route("/api_key", delete(api_keys::delete))
         // Reports
         .route("/report", get(reports::details))
         .route("/reports", get(reports::index))
@@ -65,18 +144,45 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
         .route("/report_status", post(report_statuses::create))
         .route("/report_status", put(report_statuses::update))
         .route("/report_status", delete(report_statuses::delete))
+        // ReportDocuments
+        .route("/report_document", post(report_documents::create))
+        .route("/report_document", delete(report_documents::delete))
         // Tasks
         .route("/task", get(tasks::details))
         .route("/tasks", get(tasks::index))
         .route("/task", post(tasks::create))
         .route("/task", put(tasks::update))
         .route("/task", delete(tasks::delete))
+        .route("/task/dispatch", post(dispatch::dispatch))
         // TaskTypes
         .route("/task_type", get(task_types::details))
         .route("/task_types", get(task_types::index))
         .route("/task_type", post(task_types::create))
         .route("/task_type", put(task_types::update))
         .route("/task_type", delete(task_types::delete))
+        // TaskTypeSkills
+        .route("/task_type_skill", post(task_type_skills::create))
+        .route("/task_type_skill", delete(task_type_skills::delete))
+        // TaskTypeChecklists
+        .route("/task_type_checklist", post(task_type_checklists::create))
+        .route("/task_type_checklist", delete(task_type_checklists::delete))
+        // Checklists
+        .route("/checklist", get(checklists::details))
+        .route("/checklists", get(checklists::index))
+        .route("/checklist", post(checklists::create))
+        .route("/checklist", put(checklists::update))
+        .route("/checklist", delete(checklists::delete))
+        // TaskChecklists
+        .route("/task_checklist", get(task_checklists::index))
+        .route("/task_checklist", post(task_checklists::create))
+        .route("/task_checklist_step", put(task_checklists::update))
+        .route("/task_checklist_step", delete(task_checklists::delete))
+        // MaintenanceRules
+        .route("/maintenance_rule", get(maintenance_rules::details))
+        .route("/maintenance_rules", get(maintenance_rules::index))
+        .route("/maintenance_rule", post(maintenance_rules::create))
+        .route("/maintenance_rule", put(maintenance_rules::update))
+        .route("/maintenance_rule", delete(maintenance_rules::delete))
         // TaskStatuses
         .route("/task_status", get(task_statuses::details))
         .route("/task_statuses", get(task_statuses::index))
@@ -86,24 +192,126 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
         // TaskExecutors
         .route("/task_executor", post(task_executors::create))
         .route("/task_executor", delete(task_executors::delete))
+        // TaskTeams
+        .route("/task_team", post(task_teams::create))
+        .route("/task_team", delete(task_teams::delete))
+        // TaskTimeEntries
+        .route("/task_time_entries", get(task_time_entries::index))
+        .route("/task_time_entries/labour", get(task_time_entries::labour))
+        .route("/task_time_entry", post(task_time_entries::create))
+        .route("/task_time_entry", put(task_time_entries::update))
+        .route("/task_time_entry", delete(task_time_entries::delete))
+        .route("/task_timer/start", post(task_time_entries::start))
+        .route("/task_timer/stop", post(task_time_entries::stop))
+        // TaskCosts
+        .route("/task_costs", get(task_costs::index))
+        .route("/task_costs/report", get(task_costs::report))
+        .route("/task_costs/export", get(task_costs::export))
+        .route("/task_cost", post(task_costs::create))
+        .route("/task_cost", put(task_costs::update))
+        .route("/task_cost", delete(task_costs::delete))
+        // TaskParts
+        .route("/task_parts", get(task_parts::index))
+        .route("/task_part", post(task_parts::create))
+        .route("/task_part", put(task_parts::update))
+        .route("/task_part", delete(task_parts::delete))
+        // TaskDocuments
+        .route("/task_document", post(task_documents::create))
+        .route("/task_document", delete(task_documents::delete))
         // Facilities
         .route("/facility", get(facilities::details))
         .route("/facilities", get(facilities::index))
         .route("/facility", post(facilities::create))
         .route("/facility", put(facilities::update))
         .route("/facility", delete(facilities::delete))
+        .route("/location", get(locations::details))
+        .route("/locations", get(locations::index))
+        .route("/location", post(locations::create))
+        .route("/location", put(locations::update))
+        .route("/location", delete(locations::delete))
         // Machines
         .route("/machine", get(machines::details))
         .route("/machines", get(machines::index))
+        .route("/machine/tree", get(machines::tree))
+        .route("/machine/ancestors", get(machines::ancestors))
+        .route("/machine/location_history", get(machines::location_history))
         .route("/machine", post(machines::create))
         .route("/machine", put(machines::update))
         .route("/machine", delete(machines::delete))
+        // MachineCounters
+        .route("/machine_counters", get(machine_counters::index))
+        .route("/machine_counter", post(machine_counters::create))
+        .route("/machine_counter", put(machine_counters::update))
+        .route("/machine_counter", delete(machine_counters::delete))
         // MachineTypes
         .route("/machine_type", get(machine_types::details))
         .route("/machine_types", get(machine_types::index))
         .route("/machine_type", post(machine_types::create))
         .route("/machine_type", put(machine_types::update))
         .route("/machine_type", delete(machine_types::delete))
+        // MachineTypeSkills
+        .route("/machine_type_skill", post(machine_type_skills::create))
+        .route("/machine_type_skill", delete(machine_type_skills::delete))
+        // MachineTypeChecklists
+        .route(
+            "/machine_type_checklist",
+            post(machine_type_checklists::create),
+        )
+        .route(
+            "/machine_type_checklist",
+            delete(machine_type_checklists::delete),
+        )
+        // MeasurementDefinitions
+        .route(
+            "/measurement_definitions",
+            get(measurement_definitions::index),
+        )
+        .route(
+            "/measurement_definition",
+            post(measurement_definitions::create),
+        )
+        .route(
+            "/measurement_definition",
+            put(measurement_definitions::update),
+        )
+        .route(
+            "/measurement_definition",
+            delete(measurement_definitions::delete),
+        )
+        // Measurements
+        .route("/measurements", get(measurements::index))
+        .route("/measurements/series", get(measurements::series))
+        .route("/measurement", post(measurements::create))
+        .route("/measurement", put(measurements::update))
+        .route("/measurement", delete(measurements::delete))
+        // MachineTypeParts
+        .route("/machine_type_part", post(machine_type_parts::create))
+        .route("/machine_type_part", delete(machine_type_parts::delete))
+        // Parts
+        .route("/part", get(parts::details))
+        .route("/parts", get(parts::index))
+        .route("/part", post(parts::create))
+        .route("/part", put(parts::update))
+        .route("/part", delete(parts::delete))
+        // PartStock
+        .route("/part_stock", get(part_stock::index))
+        .route("/part_stock", put(part_stock::update))
+        // PartMovements
+        .route("/part_movements", get(part_movements::index))
+        .route("/part_movement", post(part_movements::create))
+        // Suppliers
+        .route("/supplier", get(suppliers::details))
+        .route("/suppliers", get(suppliers::index))
+        .route("/supplier", post(suppliers::create))
+        .route("/supplier", put(suppliers::update))
+        .route("/supplier", delete(suppliers::delete))
+        // PurchaseRequests
+        .route("/purchase_request", get(purchase_requests::details))
+        .route("/purchase_requests", get(purchase_requests::index))
+        .route("/purchase_request", post(purchase_requests::create))
+        .route("/purchase_request", put(purchase_requests::update))
+        .route("/purchase_request", delete(purchase_requests::delete))
+        .route("/purchase_request/status", put(purchase_requests::status))
         // MachineStauses
         .route("/machine_status", get(machine_statuses::details))
         .route("/machine_statuses", get(machine_statuses::index))
@@ -114,9 +322,14 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
 
     let api = Router::new()
         .nest("/auth", auth)
+        .route("/.well-known/jwks.json", get(auth::jwks))
         .route("/login", post(auth::login_initiate))
         .route("/login/password", post(auth::login_password))
-        .route("/login/otp", post(auth::login_otp));
+        .route("/login/otp", post(auth::login_otp))
+        .route("/login/oidc", get(oidc::login_oidc))
+        .route("/login/oidc/callback", get(oidc::login_oidc_callback))
+        .route("/invitation", get(invitations::details))
+        .route("/invitation/accept", post(invitations::accept));
 
     let app = Router::new()
         .nest("/api", api)
@@ -0,0 +1,304 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{ChecklistCreate, ChecklistDelete, ChecklistEdit, ChecklistView, Require},
+    field_vec, update_field,
+    utils::{
+        db::{Field, IntoField},
+        errors::{ApiError, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    ChecklistTemplate, ChecklistTemplateStep, NewChecklistStep, NewChecklistTemplate,
+    QueryChecklistTemplate, QueryChecklistTemplates, UpdateChecklistTemplate,
+};
+
+async fn fetch_templates(
+    conn: &mut PgConnection,
+    template_id: Option<Uuid>,
+    task_type_id: Option<Uuid>,
+    machine_type_id: Option<Uuid>,
+) -> Result<Vec<ChecklistTemplate>, ApiError> {
+    let templates = query_as!(
+        ChecklistTemplate,
+        r#"
+        SELECT
+            ct.id,
+            ct.name,
+            ct.description,
+            (
+                SELECT array_agg(
+                    (
+                        cts.id,
+                        cts.position,
+                        cts.title,
+                        cts.instructions,
+                        cts.mandatory,
+                        cts.requires_photo,
+                        cts.requires_measurement,
+                        cts.measurement_unit
+                    )
+                    ORDER BY cts.position
+                )
+                FROM
+                    checklist_template_steps cts
+                WHERE
+                    cts.template_id = ct.id
+            ) AS "steps: Vec<ChecklistTemplateStep>",
+            ct.created
+        FROM
+            checklist_templates ct
+        WHERE
+            ($1::UUID IS NULL OR ct.id = $1)
+        AND
+            ($2::UUID IS NULL OR ct.id IN (
+                SELECT ttc.template_id FROM task_type_checklists ttc WHERE ttc.task_type_id = $2
+            ))
+        AND
+            ($3::UUID IS NULL OR ct.id IN (
+                SELECT mtc.template_id FROM machine_type_checklists mtc WHERE mtc.machine_type_id = $3
+            ))
+        ORDER BY
+            ct.name
+        "#,
+        template_id,
+        task_type_id,
+        machine_type_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(templates)
+}
+
+async fn insert_steps(
+    conn: &mut PgConnection,
+    template_id: Uuid,
+    steps: &[NewChecklistStep],
+) -> Result<(), ApiError> {
+    let titles: Vec<&str> = steps.iter().map(|step| step.title.as_str()).collect();
+    let instructions: Vec<Option<&str>> = steps
+        .iter()
+        .map(|step| step.instructions.as_deref())
+        .collect();
+    let mandatory: Vec<bool> = steps
+        .iter()
+        .map(|step| step.mandatory.unwrap_or(true))
+        .collect();
+    let requires_photo: Vec<bool> = steps
+        .iter()
+        .map(|step| step.requires_photo.unwrap_or(false))
+        .collect();
+    let requires_measurement: Vec<bool> = steps
+        .iter()
+        .map(|step| step.requires_measurement.unwrap_or(false))
+        .collect();
+    let measurement_units: Vec<Option<&str>> = steps
+        .iter()
+        .map(|step| step.measurement_unit.as_deref())
+        .collect();
+
+    query!(
+        r#"
+        INSERT INTO
+            checklist_template_steps
+        (
+            template_id,
+            position,
+            title,
+            instructions,
+            mandatory,
+            requires_photo,
+            requires_measurement,
+            measurement_unit
+        )
+        SELECT
+            $1,
+            s.position::INT,
+            s.title,
+            s.instructions,
+            s.mandatory,
+            s.requires_photo,
+            s.requires_measurement,
+            s.measurement_unit
+        FROM
+            unnest($2::TEXT[], $3::TEXT[], $4::BOOL[], $5::BOOL[], $6::BOOL[], $7::TEXT[])
+            WITH ORDINALITY AS s(
+                title,
+                instructions,
+                mandatory,
+                requires_photo,
+                requires_measurement,
+                measurement_unit,
+                position
+            )
+        "#,
+        template_id,
+        &titles as &[&str],
+        &instructions as &[Option<&str>],
+        &mandatory,
+        &requires_photo,
+        &requires_measurement,
+        &measurement_units as &[Option<&str>]
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn details(
+    _: Require<ChecklistView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryChecklistTemplate>,
+) -> Result<Json<ChecklistTemplate>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let template = fetch_templates(&mut conn, Some(params.id), None, None)
+        .await?
+        .pop()
+        .ok_or(ApiError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    Ok(Json(template))
+}
+
+pub async fn index(
+    _: Require<ChecklistView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryChecklistTemplates>,
+) -> Result<Json<Vec<ChecklistTemplate>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let templates =
+        fetch_templates(&mut conn, None, params.task_type_id, params.machine_type_id).await?;
+
+    Ok(Json(templates))
+}
+
+pub async fn create(
+    _: Require<ChecklistCreate>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewChecklistTemplate>,
+) -> Result<(StatusCode, Json<ChecklistTemplate>), ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let template_id = query_scalar!(
+        r#"
+        INSERT INTO
+            checklist_templates
+        (
+            name,
+            description
+        )
+        VALUES
+        (
+            $1,
+            $2
+        )
+        RETURNING
+            id
+        "#,
+        body.name,
+        body.description
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    insert_steps(&mut tx, template_id, &body.steps).await?;
+
+    let template = fetch_templates(&mut tx, Some(template_id), None, None)
+        .await?
+        .pop()
+        .ok_or(ApiError::GeneralOversight(
+            "Created checklist template wasn't found".to_owned(),
+        ))?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(template)))
+}
+
+pub async fn update(
+    _: Require<ChecklistEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateChecklistTemplate>,
+) -> Result<StatusCode, ApiError> {
+    let fields = field_vec![
+        name => body.name,
+        description => body.description
+    ];
+
+    if fields.is_empty() && body.steps.is_none() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    // When only the steps change the update is a no-op, it still tells whether the template exists
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE checklist_templates SET");
+    let mut separated_list = query_builder.separated(",");
+
+    match fields.is_empty() {
+        true => {
+            separated_list.push(" name = name");
+        }
+        false => {
+            for (field, value) in fields {
+                update_field!(separated_list, field, value);
+            }
+        }
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    let result = query_builder.build().execute(&mut *tx).await?;
+
+    if result.rows_affected() != 1 {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+
+    if let Some(steps) = &body.steps {
+        query!(
+            r#"DELETE FROM checklist_template_steps WHERE template_id = $1"#,
+            body.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        insert_steps(&mut tx, body.id, steps).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete(
+    _: Require<ChecklistDelete>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryChecklistTemplate>,
+) -> Result<StatusCode, ApiError> {
+    let result = query!(
+        r#"DELETE FROM checklist_templates WHERE id = $1"#,
+        params.id
+    )
+    .execute(&app_state.db)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
+use uuid::Uuid;
+
+use crate::utils::db::Nullable;
+
+#[derive(Serialize)]
+pub struct ChecklistTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub steps: Option<Vec<ChecklistTemplateStep>>,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Type, Serialize, Debug)]
+pub struct ChecklistTemplateStep {
+    pub id: Option<Uuid>,
+    pub position: Option<i32>,
+    pub title: Option<String>,
+    pub instructions: Option<String>,
+    pub mandatory: Option<bool>,
+    pub requires_photo: Option<bool>,
+    pub requires_measurement: Option<bool>,
+    pub measurement_unit: Option<String>,
+}
+
+// Details
+
+#[derive(Deserialize)]
+pub struct QueryChecklistTemplate {
+    pub id: Uuid,
+}
+
+// Index, the filters list the templates attached to a task type or a machine type
+
+#[derive(Deserialize)]
+pub struct QueryChecklistTemplates {
+    pub task_type_id: Option<Uuid>,
+    pub machine_type_id: Option<Uuid>,
+}
+
+// Create, steps are numbered in the order they're sent
+
+#[derive(Deserialize)]
+pub struct NewChecklistStep {
+    pub title: String,
+    pub instructions: Option<String>,
+    pub mandatory: Option<bool>,
+    pub requires_photo: Option<bool>,
+    pub requires_measurement: Option<bool>,
+    pub measurement_unit: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NewChecklistTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub steps: Vec<NewChecklistStep>,
+}
+
+// Update, sending steps replaces all of them
+
+#[derive(Deserialize)]
+pub struct UpdateChecklistTemplate {
+    pub id: Uuid,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Nullable<String>,
+    pub steps: Option<Vec<NewChecklistStep>>,
+}
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Extension, Json};
+use sqlx::{query, query_as, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    tasks::handlers::{check_task_scope, task_ownership},
+    users::models::{ShortUser, User},
+    utils::{
+        errors::{ApiError, ForbiddenReason},
+        notifications::notify_task_assigned,
+    },
+    AppState,
+};
+
+use super::models::{
+    Assignment, AssignmentStrategy, AutoAssign, Candidate, Dispatch, DispatchResult,
+};
+
+// Active users of the task's facility who aren't assigned yet and hold the required skills,
+// optionally with a matching
+// occupation and available at the due date, or right now when the task has none
+
+async fn fetch_candidates(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    options: &AutoAssign,
+) -> Result<Vec<Candidate>, ApiError> {
+    let candidates = query_as!(
+        Candidate,
+        r#"
+        SELECT
+            u.id,
+            u.first_name,
+            u.last_name,
+            u.email,
+            u.image,
+            u.occupation,
+            f.name AS "facility?",
+            t.due_at,
+            is_user_available(u.id, COALESCE(t.due_at, NOW()), COALESCE(t.due_at, NOW())) AS "available!",
+            (
+                SELECT
+                    count(*)
+                FROM
+                    tasks ot
+                WHERE
+                    NOT ot.archived
+                AND
+                (
+                    ot.id IN (SELECT te.task_id FROM task_executors te WHERE te.user_id = u.id)
+                    OR
+                    ot.id IN (
+                        SELECT
+                            ttm.task_id
+                        FROM
+                            task_teams ttm
+                        INNER JOIN
+                            team_members tm
+                        ON
+                            ttm.team_id = tm.team_id
+                        WHERE
+                            tm.user_id = u.id
+                    )
+                )
+            ) AS "open_tasks!",
+            (
+                SELECT max(te.assigned) FROM task_executors te WHERE te.user_id = u.id
+            ) AS last_assigned
+        FROM
+            tasks t
+        CROSS JOIN
+            users u
+        LEFT JOIN
+            facilities f
+        ON
+            f.id = task_facility(t.id)
+        WHERE
+            t.id = $1
+        AND
+            u.active
+        AND
+            NOT u.service_account
+        AND
+            (f.id IS NULL OR f.id = ANY(user_facility_ids(u.id)))
+        AND
+            NOT is_task_executor(t.id, u.id)
+        AND
+            cardinality(task_missing_skills(t.id, u.id)) = 0
+        AND
+            ($2::TEXT IS NULL OR u.occupation ILIKE $2)
+        AND
+            (NOT $3 OR is_user_available(u.id, COALESCE(t.due_at, NOW()), COALESCE(t.due_at, NOW())))
+        ORDER BY
+            u.last_name,
+            u.first_name
+        "#,
+        task_id,
+        options.occupation,
+        options.require_available.unwrap_or(true)
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(candidates)
+}
+
+fn explain(
+    candidate: &Candidate,
+    strategy: AssignmentStrategy,
+    rank: usize,
+    total: usize,
+) -> Vec<String> {
+    let mut reasons = Vec::new();
+
+    reasons.push(match &candidate.facility {
+        Some(facility) => format!("Works at {facility}"),
+        None => "The task has no facility".to_owned(),
+    });
+
+    if let Some(occupation) = &candidate.occupation {
+        reasons.push(format!("Occupation is {occupation}"));
+    }
+
+    reasons.push(match (candidate.available, candidate.due_at) {
+        (true, Some(due_at)) => format!("Available at the due date {due_at}"),
+        (true, None) => "Available right now".to_owned(),
+        (false, _) => "Not available, but availability wasn't required".to_owned(),
+    });
+
+    reasons.push(match strategy {
+        AssignmentStrategy::RoundRobin => match candidate.last_assigned {
+            Some(last_assigned) => format!(
+                "Last assigned {last_assigned}, number {} of {total} candidates in the rotation",
+                rank + 1
+            ),
+            None => format!(
+                "Never assigned a task, number {} of {total} candidates in the rotation",
+                rank + 1
+            ),
+        },
+        AssignmentStrategy::LeastLoaded => format!(
+            "Open tasks: {}, number {} of {total} candidates by workload",
+            candidate.open_tasks,
+            rank + 1
+        ),
+    });
+
+    reasons
+}
+
+// Picks executors for the task and, unless it's a dry run, assigns them. Notifying them is
+// left to the caller, so tasks::create can send a single notification for everyone
+
+pub async fn assign_executors(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    options: &AutoAssign,
+    dry_run: bool,
+) -> Result<Vec<Assignment>, ApiError> {
+    let mut candidates = fetch_candidates(conn, task_id, options).await?;
+
+    options.strategy.rank(&mut candidates);
+
+    let total = candidates.len();
+    let count = options.count.map_or(1, |count| count.get() as usize);
+
+    let assignments: Vec<Assignment> = candidates
+        .iter()
+        .take(count)
+        .enumerate()
+        .map(|(rank, candidate)| Assignment {
+            user: ShortUser {
+                id: Some(candidate.id),
+                first_name: Some(candidate.first_name.clone()),
+                last_name: Some(candidate.last_name.clone()),
+                email: Some(candidate.email.clone()),
+                image: candidate.image.clone(),
+            },
+            open_tasks: candidate.open_tasks,
+            last_assigned: candidate.last_assigned,
+            reasons: explain(candidate, options.strategy, rank, total),
+        })
+        .collect();
+
+    if dry_run || assignments.is_empty() {
+        return Ok(assignments);
+    }
+
+    let user_ids: Vec<Uuid> = assignments
+        .iter()
+        .filter_map(|assignment| assignment.user.id)
+        .collect();
+
+    query!(
+        r#"
+        INSERT INTO
+            task_executors
+        (
+            task_id,
+            user_id
+        )
+        SELECT $1, unnest($2::uuid[])
+        ON CONFLICT DO NOTHING
+        "#,
+        task_id,
+        &user_ids
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(assignments)
+}
+
+// Same rules as adding an executor by hand
+
+pub async fn dispatch(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<Dispatch>,
+) -> Result<Json<DispatchResult>, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    check_task_scope(&mut tx, &user, body.task_id).await?;
+
+    if !task_ownership(&mut tx, &user, body.task_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let dry_run = body.dry_run.unwrap_or(false);
+
+    let assignments = assign_executors(&mut tx, body.task_id, &body.options, dry_run).await?;
+
+    if !dry_run {
+        let user_ids: Vec<Uuid> = assignments
+            .iter()
+            .filter_map(|assignment| assignment.user.id)
+            .collect();
+
+        notify_task_assigned(&mut tx, body.task_id, &user_ids).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(DispatchResult {
+        task_id: body.task_id,
+        assignments,
+    }))
+}
@@ -0,0 +1,6 @@
+pub mod handlers;
+pub mod models;
+
+pub use handlers::assign_executors;
+pub use handlers::dispatch;
+pub use models::AutoAssign;
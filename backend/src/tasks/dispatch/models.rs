@@ -0,0 +1,81 @@
+use std::num::NonZeroU16;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::users::models::ShortUser;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentStrategy {
+    // Whoever went the longest without getting a task
+    RoundRobin,
+    // Whoever has the fewest open tasks
+    #[default]
+    LeastLoaded,
+}
+
+impl AssignmentStrategy {
+    // Sorting is stable, so ties keep the alphabetical order of the candidate query
+
+    pub fn rank(&self, candidates: &mut [Candidate]) {
+        match self {
+            AssignmentStrategy::RoundRobin => {
+                candidates.sort_by_key(|candidate| candidate.last_assigned)
+            }
+            AssignmentStrategy::LeastLoaded => {
+                candidates.sort_by_key(|candidate| (candidate.open_tasks, candidate.last_assigned))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct AutoAssign {
+    #[serde(default)]
+    pub strategy: AssignmentStrategy,
+    pub count: Option<NonZeroU16>,
+    pub occupation: Option<String>,
+    pub require_available: Option<bool>,
+}
+
+// Candidates
+
+pub struct Candidate {
+    pub id: Uuid,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub image: Option<String>,
+    pub occupation: Option<String>,
+    pub facility: Option<String>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub available: bool,
+    pub open_tasks: i64,
+    pub last_assigned: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Assignment {
+    pub user: ShortUser,
+    pub open_tasks: i64,
+    pub last_assigned: Option<DateTime<Utc>>,
+    pub reasons: Vec<String>,
+}
+
+// Dispatch
+
+#[derive(Deserialize)]
+pub struct Dispatch {
+    pub task_id: Uuid,
+    pub dry_run: Option<bool>,
+    #[serde(flatten)]
+    pub options: AutoAssign,
+}
+
+#[derive(Serialize)]
+pub struct DispatchResult {
+    pub task_id: Uuid,
+    pub assignments: Vec<Assignment>,
+}
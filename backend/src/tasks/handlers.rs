@@ -5,29 +5,82 @@ use axum::{
     http::StatusCode,
     Extension, Json,
 };
-use sqlx::{query, Postgres, QueryBuilder};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
 
 use crate::{
+    auth::permissions::{Require, TaskCreate, TaskDelete, TaskEditStatusAssigned, TaskView},
     field_vec,
-    machines::models::ShortMachine,
+    machines::{handlers::check_machine_scope, models::ShortMachine},
     tasks::models::Task,
     update_field,
-    users::models::{ShortUser, User},
+    users::{
+        models::{ShortUser, User},
+        teams::{handlers::check_team_scope, ShortTeam},
+    },
     utils::{
-        check_permission,
-        db::{Field, IntoField},
+        db::{Field, IntoField, Nullable},
         errors::{ApiError, ForbiddenReason, InputInvalidReason},
+        notifications::notify_task_assigned,
     },
     AppState,
 };
 
 use super::{
-    models::{DeleteTask, NewTask, QueryTask, UpdateTask},
+    dispatch::assign_executors,
+    models::{CreatedTask, DeleteTask, NewTask, QueryTask, QueryTasks, TaskOwnership, UpdateTask},
+    task_checklists::handlers::{add_default_checklists, check_checklist_complete},
     task_documents::TaskDocument,
+    task_executors::handlers::{check_executor_skills, check_team_skills},
     task_statuses::TaskStatus,
     task_types::TaskType,
 };
 
+// Same facility rule as the delete handler, for handlers on a single task
+
+pub async fn check_task_scope(
+    conn: &mut PgConnection,
+    user: &User,
+    task_id: Uuid,
+) -> Result<(), ApiError> {
+    if user.facility_scope().is_none() {
+        return Ok(());
+    }
+
+    let facility = query_scalar!(r#"SELECT task_facility($1)"#, task_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    user.check_facility(facility)
+}
+
+// Whether the user created or is assigned to the task, for the ownership rules
+
+pub async fn task_ownership(
+    conn: &mut PgConnection,
+    user: &User,
+    task_id: Uuid,
+) -> Result<TaskOwnership, ApiError> {
+    let ownership = query_as!(
+        TaskOwnership,
+        r#"
+        SELECT
+            t.creator = $2 AS "is_creator!",
+            is_task_executor(t.id, $2) AS "is_executor!"
+        FROM
+            tasks t
+        WHERE
+            t.id = $1
+        "#,
+        task_id,
+        user.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(ownership)
+}
+
 pub async fn details(
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
@@ -35,14 +88,19 @@ pub async fn details(
 ) -> Result<Json<Vec<Task>>, ApiError> {
     let user_id = user.id;
 
-    let permissions_ok = user.role.task_view
-        || params.creator_id.map_or(false, |id| id == user_id)
-        || params.executor_id.map_or(false, |id| id == user_id);
+    // Asking for your own tasks is allowed without task.view and isn't limited to your facilities
 
-    if !permissions_ok {
+    let is_own = params.creator_id == Some(user_id) || params.executor_id == Some(user_id);
+
+    if !user.role.has::<TaskView>() && !is_own {
         return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
     }
 
+    let scope = match is_own {
+        true => None,
+        false => user.facility_scope(),
+    };
+
     let tasks = sqlx::query_as!(
         Task,
         r#"
@@ -56,7 +114,8 @@ pub async fn details(
             ) AS "task_type!: TaskType",
             (
                 ts.id,
-                ts.name
+                ts.name,
+                ts.terminal
             ) AS "status!: TaskStatus",
             t.archived,
             (
@@ -88,6 +147,23 @@ pub async fn details(
             (
                 SELECT array_agg(
                     (
+                        tm.id,
+                        tm.name
+                    )
+                )
+                FROM
+                    task_teams ttm
+                INNER JOIN
+                    teams tm
+                ON
+                    ttm.team_id = tm.id
+                WHERE
+                    ttm.task_id = t.id
+            ) AS "teams: Vec<ShortTeam>",
+            (
+                SELECT array_agg(
+                    (
+                        td.task_id,
                         td.uri,
                         td.name,
                         td.description
@@ -106,7 +182,9 @@ pub async fn details(
             ) AS "machine?: ShortMachine",
             t.created,
             t.edited,
-            t.due_at
+            t.due_at,
+            is_task_outside_availability(t.id) AS "due_outside_availability!",
+            task_logged_minutes(t.id) AS "logged_minutes!"
         FROM
             tasks t
         INNER JOIN
@@ -130,13 +208,14 @@ pub async fn details(
         AND
             ($2::UUID IS NULL OR t.creator = $2)
         AND
-            ($3::UUID IS NULL OR t.id IN (
-                SELECT te.task_id FROM task_executors te WHERE te.user_id = $3
-            ))
+            ($3::UUID IS NULL OR is_task_executor(t.id, $3))
+        AND
+            ($4::UUID[] IS NULL OR COALESCE(m.facility, u.facility) = ANY($4))
         "#,
         params.task_id,
         params.creator_id,
-        params.executor_id
+        params.executor_id,
+        scope
     )
     .fetch_all(&app_state.db)
     .await?;
@@ -145,11 +224,11 @@ pub async fn details(
 }
 
 pub async fn index(
+    _: Require<TaskView>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryTasks>,
 ) -> Result<Json<Vec<Task>>, ApiError> {
-    check_permission(user.role.task_view)?;
-
     let tasks = sqlx::query_as!(
         Task,
         r#"
@@ -163,7 +242,8 @@ pub async fn index(
             ) AS "task_type!: TaskType",
             (
                 ts.id,
-                ts.name
+                ts.name,
+                ts.terminal
             ) AS "status!: TaskStatus",
             t.archived,
             (
@@ -190,6 +270,23 @@ pub async fn index(
             (
                 SELECT array_agg(
                     (
+                        tm.id,
+                        tm.name
+                    )
+                )
+                FROM
+                    task_teams ttm
+                INNER JOIN
+                    teams tm
+                ON
+                    ttm.team_id = tm.id
+                WHERE
+                    ttm.task_id = t.id
+            ) AS "teams: Vec<ShortTeam>",
+            (
+                SELECT array_agg(
+                    (
+                        td.task_id,
                         td.uri,
                         td.name,
                         td.description
@@ -206,7 +303,9 @@ pub async fn index(
             ) AS "machine?: ShortMachine",
             t.created,
             t.edited,
-            t.due_at
+            t.due_at,
+            is_task_outside_availability(t.id) AS "due_outside_availability!",
+            task_logged_minutes(t.id) AS "logged_minutes!"
         FROM
             tasks t
         INNER JOIN
@@ -225,7 +324,13 @@ pub async fn index(
             machines m
         ON
             t.machine = m.id
-        "#
+        WHERE
+            ($1::UUID[] IS NULL OR COALESCE(m.facility, u.facility) = ANY($1))
+        AND
+            ($2::UUID IS NULL OR m.location IN (SELECT location_subtree($2)))
+        "#,
+        user.facility_scope(),
+        params.location_id
     )
     .fetch_all(&app_state.db)
     .await?;
@@ -233,15 +338,14 @@ pub async fn index(
     Ok(Json(tasks))
 }
 
-pub async fn create(
-    Extension(user): Extension<User>,
-    State(app_state): State<Arc<AppState>>,
-    Json(body): Json<NewTask>,
-) -> Result<(StatusCode, Json<Task>), ApiError> {
-    check_permission(user.role.task_create)?;
-
-    let mut tx = app_state.db.begin().await?;
+// Creates the task with its executors, teams and checklists, maintenance rules open
+// their tasks through here as well
 
+pub async fn insert_task(
+    conn: &mut PgConnection,
+    user: &User,
+    body: &NewTask,
+) -> Result<CreatedTask, ApiError> {
     let task_id = sqlx::query_scalar!(
         r#"
         INSERT INTO
@@ -279,10 +383,12 @@ pub async fn create(
         body.machine,
         body.due_at
     )
-    .fetch_one(&mut *tx)
+    .fetch_one(&mut *conn)
     .await?;
 
-    if let Some(executors) = body.executors {
+    if let Some(executors) = &body.executors {
+        check_executor_skills(conn, task_id, executors).await?;
+
         query!(
             r#"
             INSERT INTO
@@ -294,12 +400,73 @@ pub async fn create(
             SELECT $1, unnest($2::uuid[])
             "#,
             task_id,
-            &executors
+            executors
         )
-        .execute(&mut *tx)
+        .execute(&mut *conn)
         .await?;
     }
 
+    if let Some(teams) = &body.teams {
+        for team_id in teams {
+            check_team_scope(conn, user, *team_id).await?;
+        }
+
+        check_team_skills(conn, task_id, teams).await?;
+
+        query!(
+            r#"
+            INSERT INTO
+                task_teams
+            (
+                task_id,
+                team_id
+            )
+            SELECT $1, unnest($2::uuid[])
+            "#,
+            task_id,
+            teams
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    add_default_checklists(conn, task_id).await?;
+    check_checklist_complete(conn, task_id).await?;
+
+    let assignments = match &body.auto_assign {
+        Some(options) => Some(assign_executors(conn, task_id, options, false).await?),
+        None => None,
+    };
+
+    if body.executors.is_some() || body.teams.is_some() || assignments.is_some() {
+        let assigned = query_scalar!(
+            r#"
+            SELECT
+                te.user_id AS "user_id!"
+            FROM
+                task_executors te
+            WHERE
+                te.task_id = $1
+            UNION
+            SELECT
+                tm.user_id
+            FROM
+                task_teams ttm
+            INNER JOIN
+                team_members tm
+            ON
+                ttm.team_id = tm.team_id
+            WHERE
+                ttm.task_id = $1
+            "#,
+            task_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        notify_task_assigned(conn, task_id, &assigned).await?;
+    }
+
     let task = sqlx::query_as!(
         Task,
         r#"
@@ -313,7 +480,8 @@ pub async fn create(
             ) AS "task_type!: TaskType",
             (
                 ts.id,
-                ts.name
+                ts.name,
+                ts.terminal
             ) AS "status!: TaskStatus",
             t.archived,
             (
@@ -345,6 +513,23 @@ pub async fn create(
             (
                 SELECT array_agg(
                     (
+                        tm.id,
+                        tm.name
+                    )
+                )
+                FROM
+                    task_teams ttm
+                INNER JOIN
+                    teams tm
+                ON
+                    ttm.team_id = tm.id
+                WHERE
+                    ttm.task_id = t.id
+            ) AS "teams: Vec<ShortTeam>",
+            (
+                SELECT array_agg(
+                    (
+                        td.task_id,
                         td.uri,
                         td.name,
                         td.description
@@ -363,7 +548,9 @@ pub async fn create(
             ) AS "machine?: ShortMachine",
             t.created,
             t.edited,
-            t.due_at
+            t.due_at,
+            is_task_outside_availability(t.id) AS "due_outside_availability!",
+            task_logged_minutes(t.id) AS "logged_minutes!"
         FROM
             tasks t
         INNER JOIN
@@ -387,9 +574,26 @@ pub async fn create(
         "#,
         task_id
     )
-    .fetch_one(&mut *tx)
+    .fetch_one(&mut *conn)
     .await?;
 
+    Ok(CreatedTask { task, assignments })
+}
+
+pub async fn create(
+    _: Require<TaskCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewTask>,
+) -> Result<(StatusCode, Json<CreatedTask>), ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    if let Some(machine_id) = body.machine {
+        check_machine_scope(&mut tx, &user, machine_id).await?;
+    }
+
+    let task = insert_task(&mut tx, &user, &body).await?;
+
     tx.commit().await?;
 
     Ok((StatusCode::CREATED, Json(task)))
@@ -400,13 +604,21 @@ pub async fn update(
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateTask>,
 ) -> Result<Json<Task>, ApiError> {
-    check_permission(user.role.task_edit)?;
-
     let mut tx = app_state.db.begin().await?;
 
+    check_task_scope(&mut tx, &user, body.id).await?;
+
+    let ownership = task_ownership(&mut tx, &user, body.id).await?;
+
+    if let Nullable::Value(machine_id) = body.machine {
+        check_machine_scope(&mut tx, &user, machine_id).await?;
+    }
+
     let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE tasks SET");
     let mut separated_list = query_builder.separated(",");
 
+    let status_changed = body.status.is_some();
+
     let fields = field_vec!(
         title => body.title,
         description => body.description,
@@ -421,6 +633,16 @@ pub async fn update(
         return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
     }
 
+    // Executors may move their own tasks along without being allowed to edit them
+
+    let status_only = fields.iter().all(|(field, _)| *field == "status");
+
+    if !(ownership.can_edit(&user)
+        || (status_only && ownership.is_executor && user.role.has::<TaskEditStatusAssigned>()))
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
     for (field, value) in fields {
         update_field!(separated_list, field, value);
     }
@@ -436,6 +658,10 @@ pub async fn update(
         ));
     }
 
+    if status_changed {
+        check_checklist_complete(&mut tx, body.id).await?;
+    }
+
     let task = sqlx::query_as!(
         Task,
         r#"
@@ -449,7 +675,8 @@ pub async fn update(
             ) AS "task_type!: TaskType",
             (
                 ts.id,
-                ts.name
+                ts.name,
+                ts.terminal
             ) AS "status!: TaskStatus",
             t.archived,
             (
@@ -481,6 +708,23 @@ pub async fn update(
             (
                 SELECT array_agg(
                     (
+                        tm.id,
+                        tm.name
+                    )
+                )
+                FROM
+                    task_teams ttm
+                INNER JOIN
+                    teams tm
+                ON
+                    ttm.team_id = tm.id
+                WHERE
+                    ttm.task_id = t.id
+            ) AS "teams: Vec<ShortTeam>",
+            (
+                SELECT array_agg(
+                    (
+                        td.task_id,
                         td.uri,
                         td.name,
                         td.description
@@ -499,7 +743,9 @@ pub async fn update(
             ) AS "machine?: ShortMachine",
             t.created,
             t.edited,
-            t.due_at
+            t.due_at,
+            is_task_outside_availability(t.id) AS "due_outside_availability!",
+            task_logged_minutes(t.id) AS "logged_minutes!"
         FROM
             tasks t
         INNER JOIN
@@ -536,7 +782,15 @@ pub async fn delete(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<DeleteTask>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.task_delete)?;
+    let mut conn = app_state.db.acquire().await?;
+
+    if !user.role.has::<TaskDelete>() {
+        let ownership = task_ownership(&mut conn, &user, params.id).await?;
+
+        if !ownership.can_delete(&user) {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+    }
 
     let result = query!(
         r#"
@@ -544,10 +798,13 @@ pub async fn delete(
             tasks t
         WHERE
             t.id = $1
+        AND
+            ($2::UUID[] IS NULL OR task_facility(t.id) = ANY($2))
         "#,
-        params.id
+        params.id,
+        user.facility_scope()
     )
-    .execute(&app_state.db)
+    .execute(&mut *conn)
     .await?;
 
     match result.rows_affected() {
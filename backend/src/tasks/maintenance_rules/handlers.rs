@@ -0,0 +1,381 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{Duration, Utc};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{
+        MaintenanceCreate, MaintenanceDelete, MaintenanceEdit, MaintenanceView, Require,
+    },
+    field_vec,
+    machines::handlers::check_machine_scope,
+    tasks::{
+        dispatch::models::AutoAssign,
+        handlers::insert_task,
+        models::{CreatedTask, NewTask},
+    },
+    update_field,
+    users::models::User,
+    utils::{
+        db::{Field, IntoField},
+        errors::{ApiError, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    MaintenanceRule, NewMaintenanceRule, QueryMaintenanceRule, QueryMaintenanceRules,
+    UpdateMaintenanceRule,
+};
+
+async fn fetch_rules(
+    conn: &mut PgConnection,
+    user: &User,
+    rule_id: Option<Uuid>,
+    machine_id: Option<Uuid>,
+    counter_id: Option<Uuid>,
+) -> Result<Vec<MaintenanceRule>, ApiError> {
+    let rules = query_as!(
+        MaintenanceRule,
+        r#"
+        SELECT
+            mr.id,
+            mr.counter_id,
+            mc.name AS counter_name,
+            mc.value AS counter_value,
+            mc.unit,
+            mc.machine_id,
+            mr.usage_interval,
+            mr.next_due,
+            mr.title,
+            mr.description,
+            mr.task_type,
+            mr.status,
+            mr.due_in_days,
+            mr.auto_assign,
+            mr.active,
+            mr.last_task,
+            mr.last_triggered,
+            mr.created
+        FROM
+            maintenance_rules mr
+        INNER JOIN
+            machine_counters mc
+        ON
+            mr.counter_id = mc.id
+        INNER JOIN
+            machines m
+        ON
+            mc.machine_id = m.id
+        WHERE
+            ($1::UUID IS NULL OR mr.id = $1)
+        AND
+            ($2::UUID IS NULL OR mc.machine_id = $2)
+        AND
+            ($3::UUID IS NULL OR mr.counter_id = $3)
+        AND
+            ($4::UUID[] IS NULL OR m.facility = ANY($4))
+        ORDER BY
+            mr.next_due - mc.value,
+            mr.title
+        "#,
+        rule_id,
+        machine_id,
+        counter_id,
+        user.facility_scope()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rules)
+}
+
+async fn check_counter_scope(
+    conn: &mut PgConnection,
+    user: &User,
+    counter_id: Uuid,
+) -> Result<(), ApiError> {
+    let machine_id = query_scalar!(
+        r#"SELECT machine_id FROM machine_counters WHERE id = $1"#,
+        counter_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    check_machine_scope(conn, user, machine_id).await
+}
+
+// Opens a task for every active rule of the counter whose threshold has been reached. A
+// counter that jumped past several intervals at once only opens one task, the next
+// threshold is the first one above the current value
+
+pub async fn trigger_maintenance(
+    conn: &mut PgConnection,
+    user: &User,
+    counter_id: Uuid,
+) -> Result<Vec<CreatedTask>, ApiError> {
+    let rules = query!(
+        r#"
+        SELECT
+            mr.id,
+            mr.title,
+            mr.description,
+            mr.task_type,
+            mr.status,
+            mr.due_in_days,
+            mr.auto_assign,
+            mc.machine_id
+        FROM
+            maintenance_rules mr
+        INNER JOIN
+            machine_counters mc
+        ON
+            mr.counter_id = mc.id
+        WHERE
+            mr.counter_id = $1
+        AND
+            mr.active
+        AND
+            mc.value >= mr.next_due
+        ORDER BY
+            mr.next_due
+        FOR UPDATE OF mr
+        "#,
+        counter_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut tasks = Vec::with_capacity(rules.len());
+
+    for rule in rules {
+        let task = NewTask {
+            title: rule.title,
+            description: rule.description,
+            task_type: rule.task_type,
+            status: rule.status,
+            archived: None,
+            executors: None,
+            teams: None,
+            machine: Some(rule.machine_id),
+            due_at: rule
+                .due_in_days
+                .map(|days| Utc::now() + Duration::days(days.into())),
+            auto_assign: rule.auto_assign.then(AutoAssign::default),
+        };
+
+        let created = insert_task(conn, user, &task).await?;
+
+        query!(
+            r#"
+            UPDATE
+                maintenance_rules mr
+            SET
+                next_due = mr.next_due + mr.usage_interval * (
+                    floor((mc.value - mr.next_due) / mr.usage_interval) + 1
+                ),
+                last_task = $2,
+                last_triggered = NOW()
+            FROM
+                machine_counters mc
+            WHERE
+                mr.counter_id = mc.id
+            AND
+                mr.id = $1
+            "#,
+            rule.id,
+            created.task.id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        tasks.push(created);
+    }
+
+    Ok(tasks)
+}
+
+pub async fn details(
+    _: Require<MaintenanceView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryMaintenanceRule>,
+) -> Result<Json<MaintenanceRule>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let rule = fetch_rules(&mut conn, &user, Some(params.id), None, None)
+        .await?
+        .pop()
+        .ok_or(ApiError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    Ok(Json(rule))
+}
+
+pub async fn index(
+    _: Require<MaintenanceView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryMaintenanceRules>,
+) -> Result<Json<Vec<MaintenanceRule>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let rules = fetch_rules(&mut conn, &user, None, params.machine_id, params.counter_id).await?;
+
+    Ok(Json(rules))
+}
+
+// Without next_due the first task is due one interval after the current counter value
+
+pub async fn create(
+    _: Require<MaintenanceCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewMaintenanceRule>,
+) -> Result<(StatusCode, Json<MaintenanceRule>), ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    check_counter_scope(&mut tx, &user, body.counter_id).await?;
+
+    let rule_id = query_scalar!(
+        r#"
+        INSERT INTO
+            maintenance_rules
+        (
+            counter_id,
+            usage_interval,
+            next_due,
+            title,
+            description,
+            task_type,
+            status,
+            due_in_days,
+            auto_assign,
+            active
+        )
+        SELECT
+            $1,
+            $2,
+            COALESCE($3, mc.value + $2),
+            $4,
+            $5,
+            $6,
+            $7,
+            $8,
+            $9,
+            $10
+        FROM
+            machine_counters mc
+        WHERE
+            mc.id = $1
+        RETURNING
+            id
+        "#,
+        body.counter_id,
+        body.usage_interval,
+        body.next_due,
+        body.title,
+        body.description,
+        body.task_type,
+        body.status,
+        body.due_in_days,
+        body.auto_assign.unwrap_or(false),
+        body.active.unwrap_or(true)
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let rule = fetch_rules(&mut tx, &user, Some(rule_id), None, None)
+        .await?
+        .pop()
+        .ok_or(ApiError::GeneralOversight(
+            "Created maintenance rule wasn't found".to_owned(),
+        ))?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+pub async fn update(
+    _: Require<MaintenanceEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateMaintenanceRule>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let counter_id = query_scalar!(
+        r#"SELECT counter_id FROM maintenance_rules WHERE id = $1"#,
+        body.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    check_counter_scope(&mut conn, &user, counter_id).await?;
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE maintenance_rules SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        usage_interval => body.usage_interval,
+        next_due => body.next_due,
+        title => body.title,
+        description => body.description,
+        task_type => body.task_type,
+        status => body.status,
+        due_in_days => body.due_in_days,
+        auto_assign => body.auto_assign,
+        active => body.active
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    let result = query_builder.build().execute(&mut *conn).await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    _: Require<MaintenanceDelete>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryMaintenanceRule>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let counter_id = query_scalar!(
+        r#"SELECT counter_id FROM maintenance_rules WHERE id = $1"#,
+        params.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    check_counter_scope(&mut conn, &user, counter_id).await?;
+
+    let result = query!(r#"DELETE FROM maintenance_rules WHERE id = $1"#, params.id)
+        .execute(&mut *conn)
+        .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::utils::db::Nullable;
+
+#[derive(Serialize)]
+pub struct MaintenanceRule {
+    pub id: Uuid,
+    pub counter_id: Uuid,
+    pub counter_name: String,
+    pub counter_value: f64,
+    pub unit: String,
+    pub machine_id: Uuid,
+    pub usage_interval: f64,
+    pub next_due: f64,
+    pub title: String,
+    pub description: String,
+    pub task_type: Uuid,
+    pub status: Uuid,
+    pub due_in_days: Option<i32>,
+    pub auto_assign: bool,
+    pub active: bool,
+    pub last_task: Option<Uuid>,
+    pub last_triggered: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+}
+
+// Details
+
+#[derive(Deserialize)]
+pub struct QueryMaintenanceRule {
+    pub id: Uuid,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryMaintenanceRules {
+    pub machine_id: Option<Uuid>,
+    pub counter_id: Option<Uuid>,
+}
+
+// Create, the task fields follow NewTask, the machine is the one of the counter
+
+#[derive(Deserialize)]
+pub struct NewMaintenanceRule {
+    pub counter_id: Uuid,
+    pub usage_interval: f64,
+    pub next_due: Option<f64>,
+    pub title: String,
+    pub description: String,
+    pub task_type: Uuid,
+    pub status: Uuid,
+    pub due_in_days: Option<i32>,
+    pub auto_assign: Option<bool>,
+    pub active: Option<bool>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateMaintenanceRule {
+    pub id: Uuid,
+    pub usage_interval: Option<f64>,
+    pub next_due: Option<f64>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub task_type: Option<Uuid>,
+    pub status: Option<Uuid>,
+    #[serde(default)]
+    pub due_in_days: Nullable<i32>,
+    pub auto_assign: Option<bool>,
+    pub active: Option<bool>,
+}
@@ -1,9 +1,19 @@
 pub mod handlers;
 pub mod models;
 
+pub mod checklists;
+pub mod dispatch;
+pub mod maintenance_rules;
+pub mod task_checklists;
+pub mod task_costs;
 pub mod task_documents;
 pub mod task_executors;
+pub mod task_parts;
 pub mod task_statuses;
+pub mod task_teams;
+pub mod task_time_entries;
+pub mod task_type_checklists;
+pub mod task_type_skills;
 pub mod task_types;
 
 pub use handlers::create;
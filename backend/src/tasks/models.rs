@@ -3,10 +3,21 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    machines::models::ShortMachine, users::models::ShortUser, utils::db::nullable::Nullable,
+    auth::permissions::{TaskDelete, TaskDeleteOwn, TaskEdit, TaskEditOwn},
+    machines::models::ShortMachine,
+    users::{
+        models::{ShortUser, User},
+        teams::ShortTeam,
+    },
+    utils::db::nullable::Nullable,
 };
 
-use super::{task_documents::TaskDocument, task_statuses::TaskStatus, task_types::TaskType};
+use super::{
+    dispatch::{models::Assignment, AutoAssign},
+    task_documents::TaskDocument,
+    task_statuses::TaskStatus,
+    task_types::TaskType,
+};
 
 #[derive(Debug, Serialize)]
 pub struct Task {
@@ -18,11 +29,31 @@ pub struct Task {
     pub archived: bool,
     pub creator: ShortUser,
     pub executors: Option<Vec<ShortUser>>,
+    pub teams: Option<Vec<ShortTeam>>,
     pub documents: Option<Vec<TaskDocument>>,
     pub machine: Option<ShortMachine>,
     pub created: DateTime<Utc>,
     pub edited: DateTime<Utc>,
     pub due_at: Option<DateTime<Utc>>,
+    pub due_outside_availability: bool,
+    pub logged_minutes: i64,
+}
+
+// Ownership
+
+pub struct TaskOwnership {
+    pub is_creator: bool,
+    pub is_executor: bool,
+}
+
+impl TaskOwnership {
+    pub fn can_edit(&self, user: &User) -> bool {
+        user.role.has::<TaskEdit>() || (self.is_creator && user.role.has::<TaskEditOwn>())
+    }
+
+    pub fn can_delete(&self, user: &User) -> bool {
+        user.role.has::<TaskDelete>() || (self.is_creator && user.role.has::<TaskDeleteOwn>())
+    }
 }
 
 // Details
@@ -34,6 +65,13 @@ pub struct QueryTask {
     pub executor_id: Option<Uuid>,
 }
 
+// Index, a location lists the tasks on machines anywhere inside it
+
+#[derive(Deserialize)]
+pub struct QueryTasks {
+    pub location_id: Option<Uuid>,
+}
+
 // Create
 
 #[derive(Deserialize)]
@@ -44,8 +82,21 @@ pub struct NewTask {
     pub status: Uuid,
     pub archived: Option<bool>,
     pub executors: Option<Vec<Uuid>>,
+    pub teams: Option<Vec<Uuid>>,
     pub machine: Option<Uuid>,
     pub due_at: Option<DateTime<Utc>>,
+    pub auto_assign: Option<AutoAssign>,
+}
+
+// The automatically picked executors are already part of the task, the assignments
+// only add why they were picked
+
+#[derive(Serialize)]
+pub struct CreatedTask {
+    #[serde(flatten)]
+    pub task: Task,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignments: Option<Vec<Assignment>>,
 }
 
 // Update
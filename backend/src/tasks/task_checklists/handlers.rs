@@ -0,0 +1,366 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, query_scalar, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::TaskView,
+    tasks::handlers::{check_task_scope, task_ownership},
+    users::models::{ShortUser, User},
+    utils::errors::{ApiError, ConflictReason, ForbiddenReason, InputInvalidReason},
+    AppState,
+};
+
+use super::models::{
+    DeleteTaskChecklistStep, NewTaskChecklist, QueryTaskChecklist, TaskChecklistStep,
+    UpdateTaskChecklistStep,
+};
+
+async fn fetch_steps(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<Vec<TaskChecklistStep>, ApiError> {
+    let steps = query_as!(
+        TaskChecklistStep,
+        r#"
+        SELECT
+            tcs.id,
+            tcs.task_id,
+            tcs.template_id,
+            tcs.position,
+            tcs.title,
+            tcs.instructions,
+            tcs.mandatory,
+            tcs.requires_photo,
+            tcs.requires_measurement,
+            tcs.measurement_unit,
+            tcs.photo,
+            tcs.measurement,
+            tcs.note,
+            (
+                u.id,
+                u.first_name,
+                u.last_name,
+                u.email,
+                u.image
+            ) AS "completed_by?: ShortUser",
+            tcs.completed_at
+        FROM
+            task_checklist_steps tcs
+        LEFT JOIN
+            users u
+        ON
+            tcs.completed_by = u.id
+        WHERE
+            tcs.task_id = $1
+        ORDER BY
+            tcs.position
+        "#,
+        task_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(steps)
+}
+
+// Copies the steps of the templates onto the task, after the steps it already has
+
+pub async fn add_checklists(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    template_ids: &[Uuid],
+) -> Result<(), ApiError> {
+    query!(
+        r#"
+        INSERT INTO
+            task_checklist_steps
+        (
+            task_id,
+            template_id,
+            position,
+            title,
+            instructions,
+            mandatory,
+            requires_photo,
+            requires_measurement,
+            measurement_unit
+        )
+        SELECT
+            $1,
+            cts.template_id,
+            (
+                SELECT COALESCE(max(tcs.position), 0) FROM task_checklist_steps tcs WHERE tcs.task_id = $1
+            ) + row_number() OVER (
+                ORDER BY array_position($2::UUID[], cts.template_id), cts.position
+            )::INT,
+            cts.title,
+            cts.instructions,
+            cts.mandatory,
+            cts.requires_photo,
+            cts.requires_measurement,
+            cts.measurement_unit
+        FROM
+            checklist_template_steps cts
+        WHERE
+            cts.template_id = ANY($2)
+        "#,
+        task_id,
+        template_ids
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// New tasks get the checklists of their task type and of their machine's type
+
+pub async fn add_default_checklists(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<(), ApiError> {
+    let template_ids = query_scalar!(
+        r#"
+        SELECT
+            ct.id
+        FROM
+            checklist_templates ct
+        WHERE
+            ct.id IN (
+                SELECT
+                    ttc.template_id
+                FROM
+                    tasks t
+                INNER JOIN
+                    task_type_checklists ttc
+                ON
+                    t.task_type = ttc.task_type_id
+                WHERE
+                    t.id = $1
+                UNION
+                SELECT
+                    mtc.template_id
+                FROM
+                    tasks t
+                INNER JOIN
+                    machines m
+                ON
+                    t.machine = m.id
+                INNER JOIN
+                    machine_type_checklists mtc
+                ON
+                    m.machine_type = mtc.machine_type_id
+                WHERE
+                    t.id = $1
+            )
+        ORDER BY
+            ct.name
+        "#,
+        task_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    if template_ids.is_empty() {
+        return Ok(());
+    }
+
+    add_checklists(conn, task_id, &template_ids).await
+}
+
+// A task can't be moved to a terminal status while mandatory steps are still open
+
+pub async fn check_checklist_complete(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<(), ApiError> {
+    let blocked = query_scalar!(
+        r#"
+        SELECT
+            ts.terminal AND is_task_checklist_incomplete(t.id) AS "blocked!"
+        FROM
+            tasks t
+        INNER JOIN
+            task_statuses ts
+        ON
+            t.status = ts.id
+        WHERE
+            t.id = $1
+        "#,
+        task_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    match blocked {
+        true => Err(ApiError::Conflict(ConflictReason::ChecklistIncomplete)),
+        false => Ok(()),
+    }
+}
+
+// Anyone who may see the task may see its checklist
+
+pub async fn index(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryTaskChecklist>,
+) -> Result<Json<Vec<TaskChecklistStep>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let ownership = task_ownership(&mut conn, &user, params.task_id).await?;
+
+    if !ownership.is_creator && !ownership.is_executor {
+        if !user.role.has::<TaskView>() {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+
+        check_task_scope(&mut conn, &user, params.task_id).await?;
+    }
+
+    let steps = fetch_steps(&mut conn, params.task_id).await?;
+
+    Ok(Json(steps))
+}
+
+pub async fn create(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewTaskChecklist>,
+) -> Result<(StatusCode, Json<Vec<TaskChecklistStep>>), ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    check_task_scope(&mut tx, &user, body.task_id).await?;
+
+    if !task_ownership(&mut tx, &user, body.task_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    add_checklists(&mut tx, body.task_id, &[body.template_id]).await?;
+
+    let steps = fetch_steps(&mut tx, body.task_id).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(steps)))
+}
+
+// Executors tick off the steps of their tasks, a step that asks for a photo or a
+// measurement can only be completed with it
+
+pub async fn update(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateTaskChecklistStep>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let step = query!(
+        r#"
+        SELECT
+            task_id,
+            requires_photo,
+            requires_measurement
+        FROM
+            task_checklist_steps
+        WHERE
+            id = $1
+        FOR UPDATE
+        "#,
+        body.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    check_task_scope(&mut tx, &user, step.task_id).await?;
+
+    let ownership = task_ownership(&mut tx, &user, step.task_id).await?;
+
+    if !ownership.is_executor && !ownership.can_edit(&user) {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    if body.completed
+        && ((step.requires_photo && body.photo.is_none())
+            || (step.requires_measurement && body.measurement.is_none()))
+    {
+        return Err(ApiError::InputInvalid(
+            InputInvalidReason::ChecklistStepIncomplete,
+        ));
+    }
+
+    query!(
+        r#"
+        UPDATE
+            task_checklist_steps
+        SET
+            photo = $2,
+            measurement = $3,
+            note = $4,
+            completed_by = CASE WHEN $5 THEN $6::UUID END,
+            completed_at = CASE WHEN $5 THEN NOW() END
+        WHERE
+            id = $1
+        "#,
+        body.id,
+        body.photo,
+        body.measurement,
+        body.note,
+        body.completed,
+        user.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Reopening a mandatory step of a closed task would sneak past the status check
+
+    check_checklist_complete(&mut tx, step.task_id).await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteTaskChecklistStep>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let task_id = query_scalar!(
+        r#"SELECT task_id FROM task_checklist_steps WHERE id = $1"#,
+        params.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    check_task_scope(&mut conn, &user, task_id).await?;
+
+    if !task_ownership(&mut conn, &user, task_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let result = query!(
+        r#"DELETE FROM task_checklist_steps WHERE id = $1"#,
+        params.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::users::models::ShortUser;
+
+#[derive(Serialize)]
+pub struct TaskChecklistStep {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub template_id: Option<Uuid>,
+    pub position: i32,
+    pub title: String,
+    pub instructions: Option<String>,
+    pub mandatory: bool,
+    pub requires_photo: bool,
+    pub requires_measurement: bool,
+    pub measurement_unit: Option<String>,
+    pub photo: Option<String>,
+    pub measurement: Option<f64>,
+    pub note: Option<String>,
+    pub completed_by: Option<ShortUser>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryTaskChecklist {
+    pub task_id: Uuid,
+}
+
+// Create, copies the steps of a template onto the task
+
+#[derive(Deserialize)]
+pub struct NewTaskChecklist {
+    pub task_id: Uuid,
+    pub template_id: Uuid,
+}
+
+// Update, ticking off a step sends its whole state
+
+#[derive(Deserialize)]
+pub struct UpdateTaskChecklistStep {
+    pub id: Uuid,
+    pub completed: bool,
+    pub photo: Option<String>,
+    pub measurement: Option<f64>,
+    pub note: Option<String>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteTaskChecklistStep {
+    pub id: Uuid,
+}
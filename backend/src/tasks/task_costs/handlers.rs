@@ -0,0 +1,432 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use csv::Writer;
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{CostCreate, CostDelete, CostEdit, CostView, Require},
+    field_vec,
+    tasks::handlers::check_task_scope,
+    update_field,
+    users::models::{ShortUser, User},
+    utils::{
+        db::{Field, IntoField},
+        errors::{ApiError, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    CostRow, DeleteTaskCost, NewTaskCost, QueryCosts, QueryTaskCosts, TaskCost, UpdateTaskCost,
+};
+
+struct TaskCostFilter<'a> {
+    cost_id: Option<Uuid>,
+    task_id: Option<Uuid>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    facilities: Option<&'a [Uuid]>,
+}
+
+async fn fetch_costs(
+    conn: &mut PgConnection,
+    filter: TaskCostFilter<'_>,
+) -> Result<Vec<TaskCost>, ApiError> {
+    let costs = query_as!(
+        TaskCost,
+        r#"
+        SELECT
+            tc.id,
+            tc.task_id,
+            t.title AS task_title,
+            tc.kind,
+            tc.description,
+            tc.amount,
+            tc.supplier_id,
+            s.name AS "supplier?",
+            tc.incurred_at,
+            (
+                u.id,
+                u.first_name,
+                u.last_name,
+                u.email,
+                u.image
+            ) AS "created_by?: ShortUser",
+            tc.created
+        FROM
+            task_costs tc
+        INNER JOIN
+            tasks t
+        ON
+            tc.task_id = t.id
+        LEFT JOIN
+            suppliers s
+        ON
+            tc.supplier_id = s.id
+        LEFT JOIN
+            users u
+        ON
+            tc.created_by = u.id
+        WHERE
+            ($1::UUID IS NULL OR tc.id = $1)
+        AND
+            ($2::UUID IS NULL OR tc.task_id = $2)
+        AND
+            ($3::TIMESTAMPTZ IS NULL OR tc.incurred_at >= $3)
+        AND
+            ($4::TIMESTAMPTZ IS NULL OR tc.incurred_at < $4)
+        AND
+            ($5::UUID[] IS NULL OR task_facility(t.id) = ANY($5))
+        ORDER BY
+            tc.incurred_at DESC
+        "#,
+        filter.cost_id,
+        filter.task_id,
+        filter.from,
+        filter.to,
+        filter.facilities
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(costs)
+}
+
+async fn check_cost_scope(
+    conn: &mut PgConnection,
+    user: &User,
+    cost_id: Uuid,
+) -> Result<(), ApiError> {
+    let task_id = query_scalar!(r#"SELECT task_id FROM task_costs WHERE id = $1"#, cost_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    check_task_scope(conn, user, task_id).await
+}
+
+pub async fn index(
+    _: Require<CostView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryTaskCosts>,
+) -> Result<Json<Vec<TaskCost>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let costs = fetch_costs(
+        &mut conn,
+        TaskCostFilter {
+            cost_id: None,
+            task_id: params.task_id,
+            from: params.from,
+            to: params.to,
+            facilities: user.facility_scope(),
+        },
+    )
+    .await?;
+
+    Ok(Json(costs))
+}
+
+pub async fn create(
+    _: Require<CostCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewTaskCost>,
+) -> Result<(StatusCode, Json<TaskCost>), ApiError> {
+    if body.amount < 0 {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NegativeAmount));
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    check_task_scope(&mut tx, &user, body.task_id).await?;
+
+    let cost_id = query_scalar!(
+        r#"
+        INSERT INTO
+            task_costs
+        (
+            task_id,
+            kind,
+            description,
+            amount,
+            supplier_id,
+            incurred_at,
+            created_by
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            COALESCE($6, NOW()),
+            $7
+        )
+        RETURNING
+            id
+        "#,
+        body.task_id,
+        body.kind.as_str(),
+        body.description,
+        body.amount,
+        body.supplier_id,
+        body.incurred_at,
+        user.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let cost = fetch_costs(
+        &mut tx,
+        TaskCostFilter {
+            cost_id: Some(cost_id),
+            task_id: None,
+            from: None,
+            to: None,
+            facilities: None,
+        },
+    )
+    .await?
+    .pop()
+    .ok_or(ApiError::GeneralOversight(
+        "Created task cost wasn't found".to_owned(),
+    ))?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(cost)))
+}
+
+pub async fn update(
+    _: Require<CostEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateTaskCost>,
+) -> Result<StatusCode, ApiError> {
+    if body.amount.is_some_and(|amount| amount < 0) {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NegativeAmount));
+    }
+
+    let mut conn = app_state.db.acquire().await?;
+
+    check_cost_scope(&mut conn, &user, body.id).await?;
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE task_costs SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        kind => body.kind.map(|kind| kind.as_str().to_owned()),
+        description => body.description,
+        amount => body.amount,
+        supplier_id => body.supplier_id,
+        incurred_at => body.incurred_at
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    query_builder.build().execute(&mut *conn).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete(
+    _: Require<CostDelete>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteTaskCost>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_cost_scope(&mut conn, &user, params.id).await?;
+
+    query!(r#"DELETE FROM task_costs WHERE id = $1"#, params.id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Time entries are cut to the range like in the labour report and count towards the
+// period they start in
+
+async fn fetch_report(
+    conn: &mut PgConnection,
+    user: &User,
+    params: &QueryCosts,
+) -> Result<Vec<CostRow>, ApiError> {
+    if params.to <= params.from {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidTimeRange));
+    }
+
+    let rows = query_as!(
+        CostRow,
+        r#"
+        WITH costs AS (
+            SELECT
+                tte.task_id,
+                GREATEST(tte.started_at, $1) AS at,
+                round(
+                    EXTRACT(
+                        EPOCH FROM
+                            LEAST(COALESCE(tte.ended_at, NOW()), $2)
+                            - GREATEST(tte.started_at, $1)
+                    ) / 3600 * COALESCE(tte.hourly_rate, 0)
+                )::BIGINT AS labour,
+                0::BIGINT AS parts,
+                0::BIGINT AS services
+            FROM
+                task_time_entries tte
+            WHERE
+                tte.started_at < $2
+            AND
+                COALESCE(tte.ended_at, NOW()) > $1
+            UNION ALL
+            SELECT
+                tp.task_id,
+                tp.consumed_at,
+                0,
+                tp.quantity::BIGINT * COALESCE(tp.unit_cost, 0),
+                0
+            FROM
+                task_parts tp
+            WHERE
+                tp.consumed_at >= $1
+            AND
+                tp.consumed_at < $2
+            UNION ALL
+            SELECT
+                tc.task_id,
+                tc.incurred_at,
+                0,
+                0,
+                tc.amount
+            FROM
+                task_costs tc
+            WHERE
+                tc.incurred_at >= $1
+            AND
+                tc.incurred_at < $2
+        )
+        SELECT
+            date_trunc($4::TEXT, c.at) AS period,
+            CASE $3
+                WHEN 'task' THEN t.id
+                WHEN 'machine' THEN m.id
+                WHEN 'machine_type' THEN mt.id
+                ELSE f.id
+            END AS id,
+            CASE $3
+                WHEN 'task' THEN t.title
+                WHEN 'machine' THEN m.name
+                WHEN 'machine_type' THEN mt.name
+                ELSE f.name
+            END AS name,
+            sum(c.labour)::BIGINT AS "labour!",
+            sum(c.parts)::BIGINT AS "parts!",
+            sum(c.services)::BIGINT AS "services!",
+            sum(c.labour + c.parts + c.services)::BIGINT AS "total!"
+        FROM
+            costs c
+        INNER JOIN
+            tasks t
+        ON
+            c.task_id = t.id
+        LEFT JOIN
+            machines m
+        ON
+            t.machine = m.id
+        LEFT JOIN
+            machine_types mt
+        ON
+            m.machine_type = mt.id
+        LEFT JOIN
+            facilities f
+        ON
+            task_facility(t.id) = f.id
+        WHERE
+            ($5::UUID[] IS NULL OR task_facility(t.id) = ANY($5))
+        GROUP BY
+            1,
+            2,
+            3
+        ORDER BY
+            1,
+            7 DESC
+        "#,
+        params.from,
+        params.to,
+        params.group_by.as_str(),
+        params.period.map(|period| period.as_str()),
+        user.facility_scope()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn report(
+    _: Require<CostView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryCosts>,
+) -> Result<Json<Vec<CostRow>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let rows = fetch_report(&mut conn, &user, &params).await?;
+
+    Ok(Json(rows))
+}
+
+// Same rows as report, as csv
+
+pub async fn export(
+    _: Require<CostView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryCosts>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let rows = fetch_report(&mut conn, &user, &params).await?;
+
+    let mut writer = Writer::from_writer(Vec::new());
+
+    for row in &rows {
+        writer.serialize(row)?;
+    }
+
+    let csv = writer
+        .into_inner()
+        .map_err(|error| ApiError::GeneralOversight(error.to_string()))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"costs.csv\"",
+            ),
+        ],
+        csv,
+    ))
+}
@@ -0,0 +1,9 @@
+pub mod handlers;
+pub mod models;
+
+pub use handlers::create;
+pub use handlers::delete;
+pub use handlers::export;
+pub use handlers::index;
+pub use handlers::report;
+pub use handlers::update;
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{users::models::ShortUser, utils::db::Nullable};
+
+// Amounts are in cents
+
+#[derive(Serialize)]
+pub struct TaskCost {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub task_title: String,
+    pub kind: String,
+    pub description: String,
+    pub amount: i32,
+    pub supplier_id: Option<Uuid>,
+    pub supplier: Option<String>,
+    pub incurred_at: DateTime<Utc>,
+    pub created_by: Option<ShortUser>,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CostKind {
+    Service,
+    Other,
+}
+
+impl CostKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CostKind::Service => "service",
+            CostKind::Other => "other",
+        }
+    }
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryTaskCosts {
+    pub task_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewTaskCost {
+    pub task_id: Uuid,
+    pub kind: CostKind,
+    pub description: String,
+    pub amount: i32,
+    pub supplier_id: Option<Uuid>,
+    pub incurred_at: Option<DateTime<Utc>>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateTaskCost {
+    pub id: Uuid,
+    pub kind: Option<CostKind>,
+    pub description: Option<String>,
+    pub amount: Option<i32>,
+    #[serde(default)]
+    pub supplier_id: Nullable<Uuid>,
+    pub incurred_at: Option<DateTime<Utc>>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteTaskCost {
+    pub id: Uuid,
+}
+
+// Cost report, labour from the time entries at the rate they were logged at, consumed
+// parts at their cost when used and the other task costs. Without a period the range is
+// totalled, with one every row is a day, week, month or year of it
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CostGrouping {
+    Task,
+    #[default]
+    Machine,
+    MachineType,
+    Facility,
+}
+
+impl CostGrouping {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CostGrouping::Task => "task",
+            CostGrouping::Machine => "machine",
+            CostGrouping::MachineType => "machine_type",
+            CostGrouping::Facility => "facility",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CostPeriod {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl CostPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CostPeriod::Day => "day",
+            CostPeriod::Week => "week",
+            CostPeriod::Month => "month",
+            CostPeriod::Year => "year",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct QueryCosts {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    #[serde(default)]
+    pub group_by: CostGrouping,
+    pub period: Option<CostPeriod>,
+}
+
+#[derive(Serialize)]
+pub struct CostRow {
+    pub period: Option<DateTime<Utc>>,
+    pub id: Option<Uuid>,
+    pub name: Option<String>,
+    pub labour: i64,
+    pub parts: i64,
+    pub services: i64,
+    pub total: i64,
+}
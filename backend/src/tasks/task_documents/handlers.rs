@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use sqlx::query;
+
+use crate::{
+    tasks::handlers::{check_task_scope, task_ownership},
+    users::models::User,
+    utils::errors::{ApiError, ForbiddenReason},
+    AppState,
+};
+
+use super::models::{DeleteTaskDocument, NewTaskDocument};
+
+pub async fn create(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewTaskDocument>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_task_scope(&mut conn, &user, body.task_id).await?;
+
+    if !task_ownership(&mut conn, &user, body.task_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let result = query!(
+        r#"
+        INSERT INTO
+            task_documents
+        (
+            task_id,
+            uri,
+            name,
+            description
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4
+        )
+        "#,
+        body.task_id,
+        body.uri,
+        body.name,
+        body.description
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::CREATED),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<DeleteTaskDocument>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_task_scope(&mut conn, &user, body.task_id).await?;
+
+    if !task_ownership(&mut conn, &user, body.task_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let result = query!(
+        r#"
+        DELETE FROM
+            task_documents
+        WHERE
+            task_id = $1
+        AND
+            uri = $2
+        "#,
+        body.task_id,
+        body.uri
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
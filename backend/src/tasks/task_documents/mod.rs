@@ -1,4 +1,6 @@
 pub mod handlers;
 pub mod models;
 
+pub use handlers::create;
+pub use handlers::delete;
 pub use models::TaskDocument;
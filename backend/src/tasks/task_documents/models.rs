@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use sqlx::prelude::Type;
 use uuid::Uuid;
 
@@ -9,3 +9,21 @@ pub struct TaskDocument {
     pub name: Option<String>,
     pub description: Option<String>,
 }
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewTaskDocument {
+    pub task_id: Uuid,
+    pub uri: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteTaskDocument {
+    pub task_id: Uuid,
+    pub uri: String,
+}
@@ -1,22 +1,85 @@
 use std::sync::Arc;
 
 use axum::{extract::State, http::StatusCode, Extension, Json};
-use sqlx::query;
+use sqlx::{query, query_scalar, PgConnection};
+use uuid::Uuid;
 
 use crate::{
+    tasks::handlers::{check_task_scope, task_ownership},
     users::models::User,
-    utils::{check_permission, errors::ApiError},
+    utils::{
+        errors::{ApiError, ForbiddenReason, InputInvalidReason},
+        notifications::notify_task_assigned,
+    },
     AppState,
 };
 
 use super::models::TaskExecutor;
 
+// Executors have to hold every skill the task requires, expired certifications don't count
+
+pub async fn check_executor_skills(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    user_ids: &[Uuid],
+) -> Result<(), ApiError> {
+    let lacking = query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT
+                1
+            FROM
+                unnest($2::UUID[]) e(user_id)
+            WHERE
+                cardinality(task_missing_skills($1, e.user_id)) > 0
+        ) AS "lacking!"
+        "#,
+        task_id,
+        user_ids
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    match lacking {
+        true => Err(ApiError::InputInvalid(InputInvalidReason::MissingSkills)),
+        false => Ok(()),
+    }
+}
+
+// Team members count as executors, so every member of the teams needs the skills as well
+
+pub async fn check_team_skills(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    team_ids: &[Uuid],
+) -> Result<(), ApiError> {
+    let members = query_scalar!(
+        r#"SELECT DISTINCT tm.user_id FROM team_members tm WHERE tm.team_id = ANY($1)"#,
+        team_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    check_executor_skills(conn, task_id, &members).await
+}
+
 pub async fn create(
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<TaskExecutor>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.task_edit)?;
+    let mut conn = app_state.db.acquire().await?;
+
+    check_task_scope(&mut conn, &user, body.task_id).await?;
+
+    if !task_ownership(&mut conn, &user, body.task_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    check_executor_skills(&mut conn, body.task_id, &[body.user_id]).await?;
 
     let result = query!(
         r#"
@@ -35,9 +98,11 @@ pub async fn create(
         body.task_id,
         body.user_id
     )
-    .execute(&app_state.db)
+    .execute(&mut *conn)
     .await?;
 
+    notify_task_assigned(&mut conn, body.task_id, &[body.user_id]).await?;
+
     match result.rows_affected() {
         1 => Ok(StatusCode::CREATED),
         _ => Ok(StatusCode::NOT_FOUND),
@@ -49,7 +114,16 @@ pub async fn delete(
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<TaskExecutor>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.task_edit)?;
+    let mut conn = app_state.db.acquire().await?;
+
+    check_task_scope(&mut conn, &user, body.task_id).await?;
+
+    if !task_ownership(&mut conn, &user, body.task_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
 
     let result = query!(
         r#"
@@ -63,7 +137,7 @@ pub async fn delete(
         body.task_id,
         body.user_id
     )
-    .execute(&app_state.db)
+    .execute(&mut *conn)
     .await?;
 
     match result.rows_affected() {
@@ -0,0 +1,433 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, query_scalar, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::TaskView,
+    parts::{
+        part_movements::{
+            handlers::record_movement,
+            models::{MovementKind, StockMovement},
+        },
+        part_stock::handlers::change_stock,
+        purchase_requests::handlers::request_for_task,
+    },
+    tasks::handlers::{check_task_scope, task_ownership},
+    users::models::{ShortUser, User},
+    utils::errors::{ApiError, ForbiddenReason, InputInvalidReason},
+    AppState,
+};
+
+use super::models::{
+    CreatedTaskPart, DeleteTaskPart, NewTaskPart, QueryTaskParts, TaskPart, UpdateTaskPart,
+};
+
+async fn fetch_parts(
+    conn: &mut PgConnection,
+    task_id: Option<Uuid>,
+    task_part_id: Option<Uuid>,
+) -> Result<Vec<TaskPart>, ApiError> {
+    let parts = query_as!(
+        TaskPart,
+        r#"
+        SELECT
+            tp.id,
+            tp.task_id,
+            tp.part_id,
+            p.part_number,
+            p.name,
+            p.unit,
+            tp.facility_id,
+            tp.quantity,
+            (
+                u.id,
+                u.first_name,
+                u.last_name,
+                u.email,
+                u.image
+            ) AS "reserved_by?: ShortUser",
+            tp.consumed_at,
+            tp.unit_cost,
+            tp.created
+        FROM
+            task_parts tp
+        INNER JOIN
+            parts p
+        ON
+            tp.part_id = p.id
+        LEFT JOIN
+            users u
+        ON
+            tp.reserved_by = u.id
+        WHERE
+            ($1::UUID IS NULL OR tp.task_id = $1)
+        AND
+            ($2::UUID IS NULL OR tp.id = $2)
+        ORDER BY
+            tp.created
+        "#,
+        task_id,
+        task_part_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(parts)
+}
+
+async fn fetch_part(conn: &mut PgConnection, task_part_id: Uuid) -> Result<TaskPart, ApiError> {
+    fetch_parts(conn, None, Some(task_part_id))
+        .await?
+        .pop()
+        .ok_or(ApiError::DatabaseError(sqlx::Error::RowNotFound))
+}
+
+// Executors and whoever may edit the task handle its parts
+
+async fn check_task_part_access(
+    conn: &mut PgConnection,
+    user: &User,
+    task_id: Uuid,
+) -> Result<(), ApiError> {
+    check_task_scope(conn, user, task_id).await?;
+
+    let ownership = task_ownership(conn, user, task_id).await?;
+
+    if !ownership.is_executor && !ownership.can_edit(user) {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    Ok(())
+}
+
+// Anyone who may see the task may see its parts
+
+pub async fn index(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryTaskParts>,
+) -> Result<Json<Vec<TaskPart>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let ownership = task_ownership(&mut conn, &user, params.task_id).await?;
+
+    if !ownership.is_creator && !ownership.is_executor {
+        if !user.role.has::<TaskView>() {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+
+        check_task_scope(&mut conn, &user, params.task_id).await?;
+    }
+
+    let parts = fetch_parts(&mut conn, Some(params.task_id), None).await?;
+
+    Ok(Json(parts))
+}
+
+// Reserves the parts, they have to fit the machine of the task
+
+pub async fn create(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewTaskPart>,
+) -> Result<(StatusCode, Json<CreatedTaskPart>), ApiError> {
+    if body.quantity <= 0 {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidQuantity));
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    check_task_part_access(&mut tx, &user, body.task_id).await?;
+
+    let task = query!(
+        r#"
+        SELECT
+            task_facility(t.id) AS facility,
+            t.machine IS NULL OR NOT EXISTS (
+                SELECT 1 FROM machine_type_parts mtp WHERE mtp.part_id = $2
+            ) OR EXISTS (
+                SELECT
+                    1
+                FROM
+                    machines m
+                INNER JOIN
+                    machine_type_parts mtp
+                ON
+                    m.machine_type = mtp.machine_type_id
+                WHERE
+                    m.id = t.machine
+                AND
+                    mtp.part_id = $2
+            ) AS "compatible!"
+        FROM
+            tasks t
+        WHERE
+            t.id = $1
+        "#,
+        body.task_id,
+        body.part_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if !task.compatible {
+        return Err(ApiError::InputInvalid(InputInvalidReason::IncompatiblePart));
+    }
+
+    let facility_id = body
+        .facility_id
+        .or(task.facility)
+        .ok_or(ApiError::InputInvalid(InputInvalidReason::MissingFacility))?;
+
+    user.check_facility(Some(facility_id))?;
+
+    if body.order_missing.unwrap_or(false) {
+        let available = query_scalar!(
+            r#"
+            SELECT
+                on_hand - reserved AS "available!"
+            FROM
+                part_stock
+            WHERE
+                part_id = $1
+            AND
+                facility_id = $2
+            "#,
+            body.part_id,
+            facility_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .unwrap_or(0);
+
+        // Nothing is reserved yet, the caller gets the purchase request to wait for instead
+
+        if available < body.quantity {
+            let request = request_for_task(
+                &mut tx,
+                &user,
+                body.task_id,
+                body.part_id,
+                facility_id,
+                body.quantity - available,
+            )
+            .await?;
+
+            tx.commit().await?;
+
+            return Ok((
+                StatusCode::ACCEPTED,
+                Json(CreatedTaskPart {
+                    task_part: None,
+                    purchase_request: Some(request),
+                }),
+            ));
+        }
+    }
+
+    change_stock(&mut tx, body.part_id, facility_id, 0, body.quantity).await?;
+
+    let task_part_id = query_scalar!(
+        r#"
+        INSERT INTO
+            task_parts
+        (
+            task_id,
+            part_id,
+            facility_id,
+            quantity,
+            reserved_by
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5
+        )
+        RETURNING
+            id
+        "#,
+        body.task_id,
+        body.part_id,
+        facility_id,
+        body.quantity,
+        user.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let part = fetch_part(&mut tx, task_part_id).await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedTaskPart {
+            task_part: Some(part),
+            purchase_request: None,
+        }),
+    ))
+}
+
+pub async fn update(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateTaskPart>,
+) -> Result<Json<TaskPart>, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let task_part = query!(
+        r#"
+        SELECT
+            task_id,
+            part_id,
+            facility_id,
+            quantity,
+            consumed_at IS NOT NULL AS "consumed!"
+        FROM
+            task_parts
+        WHERE
+            id = $1
+        FOR UPDATE
+        "#,
+        body.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    check_task_part_access(&mut tx, &user, task_part.task_id).await?;
+
+    let quantity = body.quantity.unwrap_or(task_part.quantity);
+
+    if task_part.consumed || quantity <= 0 || quantity > task_part.quantity {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidQuantity));
+    }
+
+    change_stock(
+        &mut tx,
+        task_part.part_id,
+        task_part.facility_id,
+        -quantity,
+        -task_part.quantity,
+    )
+    .await?;
+
+    record_movement(
+        &mut tx,
+        &user,
+        &StockMovement {
+            part_id: task_part.part_id,
+            facility_id: task_part.facility_id,
+            change: -quantity,
+            kind: MovementKind::Consumption,
+            task_id: Some(task_part.task_id),
+            note: None,
+        },
+    )
+    .await?;
+
+    query!(
+        r#"
+        UPDATE
+            task_parts
+        SET
+            quantity = $2,
+            consumed_at = NOW(),
+            unit_cost = (SELECT unit_cost FROM parts WHERE id = task_parts.part_id)
+        WHERE
+            id = $1
+        "#,
+        body.id,
+        quantity
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let part = fetch_part(&mut tx, body.id).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(part))
+}
+
+pub async fn delete(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteTaskPart>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let task_part = query!(
+        r#"
+        SELECT
+            task_id,
+            part_id,
+            facility_id,
+            quantity,
+            consumed_at IS NOT NULL AS "consumed!"
+        FROM
+            task_parts
+        WHERE
+            id = $1
+        FOR UPDATE
+        "#,
+        params.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    check_task_part_access(&mut tx, &user, task_part.task_id).await?;
+
+    match task_part.consumed {
+        true => {
+            change_stock(
+                &mut tx,
+                task_part.part_id,
+                task_part.facility_id,
+                task_part.quantity,
+                0,
+            )
+            .await?;
+
+            record_movement(
+                &mut tx,
+                &user,
+                &StockMovement {
+                    part_id: task_part.part_id,
+                    facility_id: task_part.facility_id,
+                    change: task_part.quantity,
+                    kind: MovementKind::Return,
+                    task_id: Some(task_part.task_id),
+                    note: None,
+                },
+            )
+            .await?;
+        }
+        false => {
+            change_stock(
+                &mut tx,
+                task_part.part_id,
+                task_part.facility_id,
+                0,
+                -task_part.quantity,
+            )
+            .await?;
+        }
+    }
+
+    query!(r#"DELETE FROM task_parts WHERE id = $1"#, params.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,7 @@
+pub mod handlers;
+pub mod models;
+
+pub use handlers::create;
+pub use handlers::delete;
+pub use handlers::index;
+pub use handlers::update;
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{parts::purchase_requests::models::PurchaseRequest, users::models::ShortUser};
+
+#[derive(Serialize)]
+pub struct TaskPart {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub part_id: Uuid,
+    pub part_number: String,
+    pub name: String,
+    pub unit: String,
+    pub facility_id: Uuid,
+    pub quantity: i32,
+    pub reserved_by: Option<ShortUser>,
+    pub consumed_at: Option<DateTime<Utc>>,
+    pub unit_cost: Option<i32>,
+    pub created: DateTime<Utc>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryTaskParts {
+    pub task_id: Uuid,
+}
+
+// Create, the parts are reserved in the facility of the task unless another one is given.
+// With order_missing a shortage requests the missing parts instead of reserving any
+
+#[derive(Deserialize)]
+pub struct NewTaskPart {
+    pub task_id: Uuid,
+    pub part_id: Uuid,
+    pub quantity: i32,
+    pub facility_id: Option<Uuid>,
+    pub order_missing: Option<bool>,
+}
+
+// Either the reservation or, while the parts are on order, the purchase request to wait for
+
+#[derive(Serialize)]
+pub struct CreatedTaskPart {
+    pub task_part: Option<TaskPart>,
+    pub purchase_request: Option<PurchaseRequest>,
+}
+
+// Update, consumes the reservation, a lower quantity releases the rest
+
+#[derive(Deserialize)]
+pub struct UpdateTaskPart {
+    pub id: Uuid,
+    pub quantity: Option<i32>,
+}
+
+// Delete, releases the reservation or returns consumed parts to the stock
+
+#[derive(Deserialize)]
+pub struct DeleteTaskPart {
+    pub id: Uuid,
+}
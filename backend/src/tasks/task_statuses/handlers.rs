@@ -3,13 +3,15 @@ use std::sync::Arc;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Extension, Json,
+    Json,
 };
 use sqlx::{query, query_as};
 
 use crate::{
-    users::models::User,
-    utils::{check_permission, errors::ApiError},
+    auth::permissions::{
+        Require, TaskStatusCreate, TaskStatusDelete, TaskStatusEdit, TaskStatusView,
+    },
+    utils::errors::ApiError,
     AppState,
 };
 
@@ -19,12 +21,10 @@ use super::{
 };
 
 pub async fn details(
-    Extension(user): Extension<User>,
+    _: Require<TaskStatusView>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryTaskStatus>,
 ) -> Result<Json<TaskStatus>, ApiError> {
-    check_permission(user.role.task_view)?;
-
     let task_status = query_as!(
         TaskStatus,
         r#"
@@ -44,11 +44,9 @@ pub async fn details(
 }
 
 pub async fn index(
-    Extension(user): Extension<User>,
+    _: Require<TaskStatusView>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<TaskStatus>>, ApiError> {
-    check_permission(user.role.task_view)?;
-
     let task_statuses = query_as!(
         TaskStatus,
         r#"
@@ -65,28 +63,29 @@ pub async fn index(
 }
 
 pub async fn create(
-    Extension(user): Extension<User>,
+    _: Require<TaskStatusCreate>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewTaskStatus>,
 ) -> Result<(StatusCode, Json<TaskStatus>), ApiError> {
-    check_permission(user.role.task_create)?;
-
     let task_status = query_as!(
         TaskStatus,
         r#"
         INSERT INTO
             task_statuses
         (
-            name
+            name,
+            terminal
         )
         VALUES
         (
-            $1
+            $1,
+            $2
         )
         RETURNING
             *
         "#,
-        body.name
+        body.name,
+        body.terminal.unwrap_or(false)
     )
     .fetch_one(&app_state.db)
     .await?;
@@ -95,22 +94,22 @@ pub async fn create(
 }
 
 pub async fn update(
-    Extension(user): Extension<User>,
+    _: Require<TaskStatusEdit>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateTaskStatus>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.task_edit)?;
-
     let result = query!(
         r#"
         UPDATE 
             task_statuses ts
         SET
-            name = $1
+            name = COALESCE($1, name),
+            terminal = COALESCE($2, terminal)
         WHERE
-            ts.id = $2
+            ts.id = $3
         "#,
         body.name,
+        body.terminal,
         body.id
     )
     .execute(&app_state.db)
@@ -123,12 +122,10 @@ pub async fn update(
 }
 
 pub async fn delete(
-    Extension(user): Extension<User>,
+    _: Require<TaskStatusDelete>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryTaskStatus>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.task_delete)?;
-
     let result = query!(r#"DELETE FROM task_statuses WHERE id = $1"#, params.id)
         .execute(&app_state.db)
         .await?;
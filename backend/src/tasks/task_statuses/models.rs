@@ -6,6 +6,7 @@ use uuid::Uuid;
 pub struct TaskStatus {
     pub id: Uuid,
     pub name: String,
+    pub terminal: bool,
 }
 
 // Details
@@ -20,6 +21,7 @@ pub struct QueryTaskStatus {
 #[derive(Deserialize)]
 pub struct NewTaskStatus {
     pub name: String,
+    pub terminal: Option<bool>,
 }
 
 // Update
@@ -27,5 +29,6 @@ pub struct NewTaskStatus {
 #[derive(Deserialize)]
 pub struct UpdateTaskStatus {
     pub id: Uuid,
-    pub name: String,
+    pub name: Option<String>,
+    pub terminal: Option<bool>,
 }
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use sqlx::{query, query_scalar};
+
+use crate::{
+    tasks::{
+        handlers::{check_task_scope, task_ownership},
+        task_executors::handlers::check_team_skills,
+    },
+    users::{models::User, teams::handlers::check_team_scope},
+    utils::{
+        errors::{ApiError, ForbiddenReason},
+        notifications::notify_task_assigned,
+    },
+    AppState,
+};
+
+use super::models::TaskTeam;
+
+// Every current member of the team is told about the assignment
+
+pub async fn create(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<TaskTeam>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    check_task_scope(&mut tx, &user, body.task_id).await?;
+    check_team_scope(&mut tx, &user, body.team_id).await?;
+
+    if !task_ownership(&mut tx, &user, body.task_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    check_team_skills(&mut tx, body.task_id, &[body.team_id]).await?;
+
+    let result = query!(
+        r#"
+        INSERT INTO
+            task_teams
+        (
+            task_id,
+            team_id
+        )
+        VALUES
+        (
+            $1,
+            $2
+        )
+        "#,
+        body.task_id,
+        body.team_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let members = query_scalar!(
+        r#"SELECT tm.user_id FROM team_members tm WHERE tm.team_id = $1"#,
+        body.team_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    notify_task_assigned(&mut tx, body.task_id, &members).await?;
+
+    tx.commit().await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::CREATED),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<TaskTeam>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_task_scope(&mut conn, &user, body.task_id).await?;
+
+    if !task_ownership(&mut conn, &user, body.task_id)
+        .await?
+        .can_edit(&user)
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let result = query!(
+        r#"
+        DELETE FROM
+            task_teams
+        WHERE
+            task_id = $1
+        AND
+            team_id = $2
+        "#,
+        body.task_id,
+        body.team_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
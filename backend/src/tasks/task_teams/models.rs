@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct TaskTeam {
+    pub task_id: Uuid,
+    pub team_id: Uuid,
+}
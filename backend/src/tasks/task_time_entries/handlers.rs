@@ -0,0 +1,526 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{TimeEntryCreate, TimeEntryDelete, TimeEntryEdit, TimeEntryView},
+    tasks::handlers::check_task_scope,
+    users::models::{ShortUser, User},
+    utils::{
+        db::Nullable,
+        errors::{ApiError, ConflictReason, ForbiddenReason, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    DeleteTimeEntry, LabourRow, NewTimeEntry, QueryLabour, QueryTimeEntries, StartTimer, TimeEntry,
+    UpdateTimeEntry,
+};
+
+struct TimeEntryFilter<'a> {
+    entry_id: Option<Uuid>,
+    task_id: Option<Uuid>,
+    user_id: Option<Uuid>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    facilities: Option<&'a [Uuid]>,
+}
+
+impl TimeEntryFilter<'_> {
+    fn entry(entry_id: Uuid) -> Self {
+        Self {
+            entry_id: Some(entry_id),
+            task_id: None,
+            user_id: None,
+            from: None,
+            to: None,
+            facilities: None,
+        }
+    }
+}
+
+async fn fetch_entries(
+    conn: &mut PgConnection,
+    filter: TimeEntryFilter<'_>,
+) -> Result<Vec<TimeEntry>, ApiError> {
+    let entries = query_as!(
+        TimeEntry,
+        r#"
+        SELECT
+            tte.id,
+            tte.task_id,
+            t.title AS task_title,
+            (
+                u.id,
+                u.first_name,
+                u.last_name,
+                u.email,
+                u.image
+            ) AS "user!: ShortUser",
+            tte.started_at,
+            tte.ended_at,
+            floor(
+                EXTRACT(EPOCH FROM COALESCE(tte.ended_at, NOW()) - tte.started_at) / 60
+            )::BIGINT AS "minutes!",
+            tte.note,
+            tte.created
+        FROM
+            task_time_entries tte
+        INNER JOIN
+            tasks t
+        ON
+            tte.task_id = t.id
+        INNER JOIN
+            users u
+        ON
+            tte.user_id = u.id
+        WHERE
+            ($1::UUID IS NULL OR tte.id = $1)
+        AND
+            ($2::UUID IS NULL OR tte.task_id = $2)
+        AND
+            ($3::UUID IS NULL OR tte.user_id = $3)
+        AND
+            ($4::TIMESTAMPTZ IS NULL OR COALESCE(tte.ended_at, NOW()) > $4)
+        AND
+            ($5::TIMESTAMPTZ IS NULL OR tte.started_at < $5)
+        AND
+            ($6::UUID[] IS NULL OR task_facility(t.id) = ANY($6))
+        ORDER BY
+            tte.started_at DESC
+        "#,
+        filter.entry_id,
+        filter.task_id,
+        filter.user_id,
+        filter.from,
+        filter.to,
+        filter.facilities
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(entries)
+}
+
+async fn fetch_entry(conn: &mut PgConnection, entry_id: Uuid) -> Result<TimeEntry, ApiError> {
+    fetch_entries(conn, TimeEntryFilter::entry(entry_id))
+        .await?
+        .pop()
+        .ok_or(ApiError::DatabaseError(sqlx::Error::RowNotFound))
+}
+
+// Time can only be logged by, or for, someone assigned to the task
+
+async fn check_executor(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), ApiError> {
+    let is_executor = query_scalar!(
+        r#"SELECT is_task_executor($1, $2) AS "is_executor!""#,
+        task_id,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    match is_executor {
+        true => Ok(()),
+        false => Err(ApiError::InputInvalid(InputInvalidReason::NotTaskExecutor)),
+    }
+}
+
+// Entries of other users need the permission and have to be in the user's facilities
+
+async fn check_entry_access(
+    conn: &mut PgConnection,
+    user: &User,
+    entry_id: Uuid,
+    has_permission: bool,
+) -> Result<(), ApiError> {
+    let entry = query!(
+        r#"SELECT task_id, user_id FROM task_time_entries WHERE id = $1"#,
+        entry_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if entry.user_id == user.id {
+        return Ok(());
+    }
+
+    if !has_permission {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    check_task_scope(conn, user, entry.task_id).await
+}
+
+// Without time_entry.view only your own entries are listed
+
+pub async fn index(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryTimeEntries>,
+) -> Result<Json<Vec<TimeEntry>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let filter = match user.role.has::<TimeEntryView>() {
+        true => TimeEntryFilter {
+            entry_id: None,
+            task_id: params.task_id,
+            user_id: params.user_id,
+            from: params.from,
+            to: params.to,
+            facilities: user.facility_scope(),
+        },
+        false => TimeEntryFilter {
+            entry_id: None,
+            task_id: params.task_id,
+            user_id: Some(user.id),
+            from: params.from,
+            to: params.to,
+            facilities: None,
+        },
+    };
+
+    let entries = fetch_entries(&mut conn, filter).await?;
+
+    Ok(Json(entries))
+}
+
+// Logging time for someone else takes time_entry.create
+
+pub async fn create(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewTimeEntry>,
+) -> Result<(StatusCode, Json<TimeEntry>), ApiError> {
+    if body.ended_at <= body.started_at {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidTimeRange));
+    }
+
+    let user_id = body.user_id.unwrap_or(user.id);
+
+    let mut tx = app_state.db.begin().await?;
+
+    if user_id != user.id {
+        if !user.role.has::<TimeEntryCreate>() {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+
+        check_task_scope(&mut tx, &user, body.task_id).await?;
+    }
+
+    check_executor(&mut tx, body.task_id, user_id).await?;
+
+    let entry_id = query_scalar!(
+        r#"
+        INSERT INTO
+            task_time_entries
+        (
+            task_id,
+            user_id,
+            started_at,
+            ended_at,
+            note
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5
+        )
+        RETURNING
+            id
+        "#,
+        body.task_id,
+        user_id,
+        body.started_at,
+        body.ended_at,
+        body.note
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let entry = fetch_entry(&mut tx, entry_id).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+pub async fn start(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<StartTimer>,
+) -> Result<(StatusCode, Json<TimeEntry>), ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    check_executor(&mut tx, body.task_id, user.id).await?;
+
+    let running = query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM task_time_entries WHERE user_id = $1 AND ended_at IS NULL
+        ) AS "running!"
+        "#,
+        user.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if running {
+        return Err(ApiError::Conflict(ConflictReason::TimerRunning));
+    }
+
+    let entry_id = query_scalar!(
+        r#"
+        INSERT INTO
+            task_time_entries
+        (
+            task_id,
+            user_id,
+            started_at,
+            note
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            NOW(),
+            $3
+        )
+        RETURNING
+            id
+        "#,
+        body.task_id,
+        user.id,
+        body.note
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let entry = fetch_entry(&mut tx, entry_id).await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+// Stops the running timer of the logged in user, whichever task it is on
+
+pub async fn stop(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<TimeEntry>, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let entry_id = query_scalar!(
+        r#"
+        UPDATE
+            task_time_entries
+        SET
+            ended_at = GREATEST(NOW(), started_at + INTERVAL '1 second')
+        WHERE
+            user_id = $1
+        AND
+            ended_at IS NULL
+        RETURNING
+            id
+        "#,
+        user.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let entry = fetch_entry(&mut tx, entry_id).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(entry))
+}
+
+pub async fn update(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateTimeEntry>,
+) -> Result<Json<TimeEntry>, ApiError> {
+    if body.started_at.is_none() && body.ended_at.is_none() && matches!(body.note, Nullable::Absent)
+    {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    check_entry_access(&mut tx, &user, body.id, user.role.has::<TimeEntryEdit>()).await?;
+
+    let current = query!(
+        r#"SELECT started_at, ended_at FROM task_time_entries WHERE id = $1 FOR UPDATE"#,
+        body.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let started_at = body.started_at.unwrap_or(current.started_at);
+
+    if let Some(ended_at) = body.ended_at.or(current.ended_at) {
+        if ended_at <= started_at {
+            return Err(ApiError::InputInvalid(InputInvalidReason::InvalidTimeRange));
+        }
+    }
+
+    let (note_changed, note) = match body.note {
+        Nullable::Absent => (false, None),
+        Nullable::Null => (true, None),
+        Nullable::Value(note) => (true, Some(note)),
+    };
+
+    query!(
+        r#"
+        UPDATE
+            task_time_entries
+        SET
+            started_at = $2,
+            ended_at = COALESCE($3, ended_at),
+            note = CASE WHEN $4 THEN $5 ELSE note END
+        WHERE
+            id = $1
+        "#,
+        body.id,
+        started_at,
+        body.ended_at,
+        note_changed,
+        note
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let entry = fetch_entry(&mut tx, body.id).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(entry))
+}
+
+pub async fn delete(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteTimeEntry>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_entry_access(
+        &mut conn,
+        &user,
+        params.id,
+        user.role.has::<TimeEntryDelete>(),
+    )
+    .await?;
+
+    let result = query!(r#"DELETE FROM task_time_entries WHERE id = $1"#, params.id)
+        .execute(&mut *conn)
+        .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+// Logged minutes within the range per user, task type, machine or facility
+
+pub async fn labour(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryLabour>,
+) -> Result<Json<Vec<LabourRow>>, ApiError> {
+    if !user.role.has::<TimeEntryView>() {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    if params.to <= params.from {
+        return Err(ApiError::InputInvalid(InputInvalidReason::InvalidTimeRange));
+    }
+
+    let rows = query_as!(
+        LabourRow,
+        r#"
+        SELECT
+            CASE $3
+                WHEN 'user' THEN u.id
+                WHEN 'task_type' THEN tt.id
+                WHEN 'machine' THEN m.id
+                ELSE f.id
+            END AS id,
+            CASE $3
+                WHEN 'user' THEN u.first_name || ' ' || u.last_name
+                WHEN 'task_type' THEN tt.name
+                WHEN 'machine' THEN m.name
+                ELSE f.name
+            END AS name,
+            floor(
+                sum(
+                    EXTRACT(
+                        EPOCH FROM
+                            LEAST(COALESCE(tte.ended_at, NOW()), $2)
+                            - GREATEST(tte.started_at, $1)
+                    )
+                ) / 60
+            )::BIGINT AS "minutes!",
+            count(*) AS "entries!",
+            count(DISTINCT tte.task_id) AS "tasks!"
+        FROM
+            task_time_entries tte
+        INNER JOIN
+            tasks t
+        ON
+            tte.task_id = t.id
+        INNER JOIN
+            task_types tt
+        ON
+            t.task_type = tt.id
+        INNER JOIN
+            users u
+        ON
+            tte.user_id = u.id
+        LEFT JOIN
+            machines m
+        ON
+            t.machine = m.id
+        LEFT JOIN
+            facilities f
+        ON
+            task_facility(t.id) = f.id
+        WHERE
+            tte.started_at < $2
+        AND
+            COALESCE(tte.ended_at, NOW()) > $1
+        AND
+            ($4::UUID[] IS NULL OR task_facility(t.id) = ANY($4))
+        GROUP BY
+            1,
+            2
+        ORDER BY
+            3 DESC
+        "#,
+        params.from,
+        params.to,
+        params.group_by.as_str(),
+        user.facility_scope()
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(rows))
+}
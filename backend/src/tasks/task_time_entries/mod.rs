@@ -0,0 +1,10 @@
+pub mod handlers;
+pub mod models;
+
+pub use handlers::create;
+pub use handlers::delete;
+pub use handlers::index;
+pub use handlers::labour;
+pub use handlers::start;
+pub use handlers::stop;
+pub use handlers::update;
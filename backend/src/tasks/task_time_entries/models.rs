@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{users::models::ShortUser, utils::db::Nullable};
+
+#[derive(Serialize)]
+pub struct TimeEntry {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub task_title: String,
+    pub user: ShortUser,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub minutes: i64,
+    pub note: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryTimeEntries {
+    pub task_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+// Create, a finished entry logged after the fact
+
+#[derive(Deserialize)]
+pub struct NewTimeEntry {
+    pub task_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub note: Option<String>,
+}
+
+// Timers
+
+#[derive(Deserialize)]
+pub struct StartTimer {
+    pub task_id: Uuid,
+    pub note: Option<String>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateTimeEntry {
+    pub id: Uuid,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub note: Nullable<String>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteTimeEntry {
+    pub id: Uuid,
+}
+
+// Labour report, the entries are cut to the range so long timers aren't counted twice
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LabourGrouping {
+    #[default]
+    User,
+    TaskType,
+    Machine,
+    Facility,
+}
+
+impl LabourGrouping {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabourGrouping::User => "user",
+            LabourGrouping::TaskType => "task_type",
+            LabourGrouping::Machine => "machine",
+            LabourGrouping::Facility => "facility",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct QueryLabour {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    #[serde(default)]
+    pub group_by: LabourGrouping,
+}
+
+#[derive(Serialize)]
+pub struct LabourRow {
+    pub id: Option<Uuid>,
+    pub name: Option<String>,
+    pub minutes: i64,
+    pub entries: i64,
+    pub tasks: i64,
+}
@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct TaskTypeChecklist {
+    pub task_type_id: Uuid,
+    pub template_id: Uuid,
+}
@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct TaskTypeSkill {
+    pub task_type_id: Uuid,
+    pub skill_id: Uuid,
+}
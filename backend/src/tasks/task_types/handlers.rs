@@ -3,13 +3,13 @@ use std::sync::Arc;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Extension, Json,
+    Json,
 };
 use sqlx::{query, query_as};
 
 use crate::{
-    users::models::User,
-    utils::{check_permission, errors::ApiError},
+    auth::permissions::{Require, TaskTypeCreate, TaskTypeDelete, TaskTypeEdit, TaskTypeView},
+    utils::errors::ApiError,
     AppState,
 };
 
@@ -19,12 +19,10 @@ use super::{
 };
 
 pub async fn details(
-    Extension(user): Extension<User>,
+    _: Require<TaskTypeView>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryTaskType>,
 ) -> Result<Json<TaskType>, ApiError> {
-    check_permission(user.role.task_view)?;
-
     let task_type = query_as!(
         TaskType,
         r#"
@@ -44,11 +42,9 @@ pub async fn details(
 }
 
 pub async fn index(
-    Extension(user): Extension<User>,
+    _: Require<TaskTypeView>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<TaskType>>, ApiError> {
-    check_permission(user.role.task_view)?;
-
     let task_types = query_as!(
         TaskType,
         r#"
@@ -65,12 +61,10 @@ pub async fn index(
 }
 
 pub async fn create(
-    Extension(user): Extension<User>,
+    _: Require<TaskTypeCreate>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewTaskType>,
 ) -> Result<(StatusCode, Json<TaskType>), ApiError> {
-    check_permission(user.role.task_create)?;
-
     let task_type = query_as!(
         TaskType,
         r#"
@@ -95,12 +89,10 @@ pub async fn create(
 }
 
 pub async fn update(
-    Extension(user): Extension<User>,
+    _: Require<TaskTypeEdit>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateTaskType>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.task_edit)?;
-
     let result = query!(
         r#"
         UPDATE 
@@ -123,12 +115,10 @@ pub async fn update(
 }
 
 pub async fn delete(
-    Extension(user): Extension<User>,
+    _: Require<TaskTypeDelete>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryTaskType>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.task_delete)?;
-
     let result = query!(r#"DELETE FROM task_types WHERE id = $1"#, params.id)
         .execute(&app_state.db)
         .await?;
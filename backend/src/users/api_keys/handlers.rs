@@ -0,0 +1,331 @@
+use std::sync::Arc;
+
+use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use rand_core::{OsRng, RngCore};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{ApiKeyCreate, ApiKeyDelete, ApiKeyEdit, ApiKeyView, Require},
+    field_vec,
+    machines::facilities::Facility,
+    update_field, user_from_id,
+    users::{
+        models::User,
+        roles::{handlers::check_grantable, models::Role},
+    },
+    utils::{
+        db::{Field, IntoField},
+        errors::{ApiError, ForbiddenReason, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    ApiKey, CreatedApiKey, NewApiKey, QueryApiKey, QueryApiKeys, UpdateApiKey, API_KEY_PREFIX,
+};
+
+fn random_hex(length: usize) -> String {
+    let mut buffer = vec![0u8; length];
+
+    OsRng.fill_bytes(&mut buffer);
+
+    buffer.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn check_owner(user: &User, owner: &User) -> Result<(), ApiError> {
+    if owner.role.level <= user.role.level {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    if !owner.service_account {
+        return Err(ApiError::InputInvalid(
+            InputInvalidReason::NotServiceAccount,
+        ));
+    }
+
+    Ok(())
+}
+
+async fn set_permissions(
+    conn: &mut PgConnection,
+    api_key_id: Uuid,
+    permissions: &[String],
+) -> Result<(), ApiError> {
+    query!(
+        r#"DELETE FROM api_key_permissions WHERE api_key_id = $1"#,
+        api_key_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let result = query!(
+        r#"
+        INSERT INTO
+            api_key_permissions
+        (
+            api_key_id,
+            resource,
+            action
+        )
+        SELECT
+            $1,
+            p.resource,
+            p.action
+        FROM
+            permissions p
+        WHERE
+            p.resource || '.' || p.action = ANY($2)
+        "#,
+        api_key_id,
+        permissions
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let mut unique = permissions.to_vec();
+    unique.sort();
+    unique.dedup();
+
+    if result.rows_affected() as usize != unique.len() {
+        return Err(ApiError::InputInvalid(
+            InputInvalidReason::UnknownPermission,
+        ));
+    }
+
+    Ok(())
+}
+
+async fn api_key_from_id(conn: &mut PgConnection, id: Uuid) -> Result<ApiKey, ApiError> {
+    let api_key = query_as!(
+        ApiKey,
+        r#"
+        SELECT
+            ak.id,
+            ak.user_id,
+            ak.name,
+            ak.prefix,
+            ak.hash,
+            ak.revoked,
+            ak.created,
+            ak.expires_at,
+            ak.last_used,
+            api_key_permission_keys(ak.id) AS "permissions!"
+        FROM
+            api_keys ak
+        WHERE
+            ak.id = $1
+        "#,
+        id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(api_key)
+}
+
+pub async fn details(
+    _: Require<ApiKeyView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryApiKey>,
+) -> Result<Json<ApiKey>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let api_key = api_key_from_id(&mut conn, params.id).await?;
+
+    Ok(Json(api_key))
+}
+
+pub async fn index(
+    _: Require<ApiKeyView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryApiKeys>,
+) -> Result<Json<Vec<ApiKey>>, ApiError> {
+    let api_keys = query_as!(
+        ApiKey,
+        r#"
+        SELECT
+            ak.id,
+            ak.user_id,
+            ak.name,
+            ak.prefix,
+            ak.hash,
+            ak.revoked,
+            ak.created,
+            ak.expires_at,
+            ak.last_used,
+            api_key_permission_keys(ak.id) AS "permissions!"
+        FROM
+            api_keys ak
+        WHERE
+            ($1::UUID IS NULL OR ak.user_id = $1)
+        ORDER BY
+            ak.created
+        "#,
+        params.user_id
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(api_keys))
+}
+
+pub async fn create(
+    _: Require<ApiKeyCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewApiKey>,
+) -> Result<(StatusCode, Json<CreatedApiKey>), ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let owner = user_from_id!(body.user_id).fetch_one(&mut *tx).await?;
+
+    check_owner(&user, &owner)?;
+
+    let permissions = body.permissions.unwrap_or_default();
+
+    check_grantable(&user, &permissions)?;
+
+    let prefix = random_hex(4);
+    let secret = random_hex(24);
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(secret.as_bytes(), &salt)
+        .map(|hash| hash.to_string())?;
+
+    let api_key_id = query_scalar!(
+        r#"
+        INSERT INTO
+            api_keys
+        (
+            user_id,
+            name,
+            prefix,
+            hash,
+            expires_at
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5
+        )
+        RETURNING
+            id
+        "#,
+        body.user_id,
+        body.name,
+        prefix,
+        hash,
+        body.expires_at
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    set_permissions(&mut tx, api_key_id, &permissions).await?;
+
+    let api_key = api_key_from_id(&mut tx, api_key_id).await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiKey {
+            key: format!("{API_KEY_PREFIX}{prefix}_{secret}"),
+            api_key,
+        }),
+    ))
+}
+
+pub async fn update(
+    _: Require<ApiKeyEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateApiKey>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let target_key = api_key_from_id(&mut tx, body.id).await?;
+
+    let owner = user_from_id!(target_key.user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    check_owner(&user, &owner)?;
+
+    if let Some(permissions) = &body.permissions {
+        check_grantable(&user, permissions)?;
+    }
+
+    let fields = field_vec![
+        name => body.name,
+        expires_at => body.expires_at
+    ];
+
+    if fields.is_empty() && body.permissions.is_none() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    if !fields.is_empty() {
+        let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE api_keys SET");
+        let mut separated_list = query_builder.separated(",");
+
+        for (field, value) in fields {
+            update_field!(separated_list, field, value);
+        }
+
+        query_builder.push(" WHERE id = ");
+        query_builder.push_bind(body.id);
+
+        query_builder.build().execute(&mut *tx).await?;
+    }
+
+    if let Some(permissions) = body.permissions {
+        set_permissions(&mut tx, body.id, &permissions).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Keys are revoked rather than deleted so their usage stays traceable
+
+pub async fn delete(
+    _: Require<ApiKeyDelete>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryApiKey>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let target_key = api_key_from_id(&mut tx, params.id).await?;
+
+    let owner = user_from_id!(target_key.user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    check_owner(&user, &owner)?;
+
+    let result = query!(
+        r#"UPDATE api_keys SET revoked = TRUE WHERE id = $1"#,
+        params.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
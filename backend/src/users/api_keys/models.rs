@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{users::roles::models::Role, utils::db::Nullable};
+
+// Separates api keys from user jwts in the Authorization header
+
+pub const API_KEY_PREFIX: &str = "ssk_";
+
+#[derive(Serialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub hash: String,
+    pub revoked: bool,
+    pub created: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used: Option<DateTime<Utc>>,
+    pub permissions: Vec<String>,
+}
+
+impl ApiKey {
+    // A key can never grant more than the role of the service account owning it
+
+    pub fn restrict(&self, role: Role) -> Role {
+        Role {
+            permissions: role
+                .permissions
+                .into_iter()
+                .filter(|key| self.permissions.contains(key))
+                .collect(),
+            ..role
+        }
+    }
+}
+
+// Returned once on creation, the plain key is never stored
+
+#[derive(Serialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+// Details
+
+#[derive(Deserialize)]
+pub struct QueryApiKey {
+    pub id: Uuid,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryApiKeys {
+    pub user_id: Option<Uuid>,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewApiKey {
+    pub user_id: Uuid,
+    pub name: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub permissions: Option<Vec<String>>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateApiKey {
+    pub id: Uuid,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub expires_at: Nullable<DateTime<Utc>>,
+    pub permissions: Option<Vec<String>>,
+}
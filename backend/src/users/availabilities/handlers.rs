@@ -0,0 +1,317 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{
+        AvailabilityCreate, AvailabilityDelete, AvailabilityEdit, AvailabilityView, Require,
+    },
+    field_vec, update_field,
+    users::models::User,
+    utils::{
+        db::{Field, IntoField},
+        errors::{ApiError, ForbiddenReason, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{
+    Availability, AvailabilityKind, AvailableUser, DeleteAvailability, NewAvailability,
+    QueryAvailabilities, QueryAvailable, UpdateAvailability,
+};
+
+fn check_time_range(starts_at: DateTime<Utc>, ends_at: DateTime<Utc>) -> Result<(), ApiError> {
+    match ends_at > starts_at {
+        true => Ok(()),
+        false => Err(ApiError::InputInvalid(InputInvalidReason::InvalidTimeRange)),
+    }
+}
+
+// Records follow the facility of the user they belong to
+
+async fn check_availability_scope(
+    conn: &mut PgConnection,
+    user: &User,
+    user_id: Uuid,
+) -> Result<(), ApiError> {
+    if user.facility_scope().is_none() {
+        return Ok(());
+    }
+
+    let facility = query_scalar!(r#"SELECT facility FROM users WHERE id = $1"#, user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    user.check_facility(facility)
+}
+
+// Your own calendar can be read without availability.view
+
+pub async fn index(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryAvailabilities>,
+) -> Result<Json<Vec<Availability>>, ApiError> {
+    let is_own = params.user_id == Some(user.id);
+
+    if !user.role.has::<AvailabilityView>() && !is_own {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let scope = match is_own {
+        true => None,
+        false => user.facility_scope(),
+    };
+
+    let availabilities = query_as!(
+        Availability,
+        r#"
+        SELECT
+            a.id,
+            a.user_id,
+            a.kind AS "kind: AvailabilityKind",
+            a.starts_at,
+            a.ends_at,
+            a.note,
+            a.created_by,
+            a.created
+        FROM
+            availabilities a
+        INNER JOIN
+            users u
+        ON
+            a.user_id = u.id
+        WHERE
+            ($1::UUID IS NULL OR a.user_id = $1)
+        AND
+            ($2::TIMESTAMPTZ IS NULL OR a.ends_at > $2)
+        AND
+            ($3::TIMESTAMPTZ IS NULL OR a.starts_at < $3)
+        AND
+            ($4::UUID[] IS NULL OR u.facility = ANY($4))
+        ORDER BY
+            a.starts_at
+        "#,
+        params.user_id,
+        params.from,
+        params.to,
+        scope
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(availabilities))
+}
+
+// Users with a shift or on-call duty covering the whole window and no vacation in it,
+// the ones only on call are listed last
+
+pub async fn available(
+    _: Require<AvailabilityView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryAvailable>,
+) -> Result<Json<Vec<AvailableUser>>, ApiError> {
+    check_time_range(params.from, params.to)?;
+
+    if params.facility.is_some() {
+        user.check_facility(params.facility)?;
+    }
+
+    let available = query_as!(
+        AvailableUser,
+        r#"
+        SELECT
+            u.id,
+            u.first_name,
+            u.last_name,
+            u.email,
+            u.image,
+            s.on_call AS "on_call!"
+        FROM
+            users u
+        CROSS JOIN LATERAL
+        (
+            SELECT NOT EXISTS (
+                SELECT
+                    1
+                FROM
+                    availabilities a
+                WHERE
+                    a.user_id = u.id
+                AND
+                    a.kind = 'shift'
+                AND
+                    a.starts_at <= $1
+                AND
+                    a.ends_at >= $2
+            ) AS on_call
+        ) s
+        WHERE
+            u.active
+        AND
+            NOT u.service_account
+        AND
+            is_user_available(u.id, $1, $2)
+        AND
+            ($3::UUID IS NULL OR $3 = ANY(user_facility_ids(u.id)))
+        AND
+            ($4::UUID[] IS NULL OR u.facility = ANY($4))
+        ORDER BY
+            s.on_call,
+            u.last_name,
+            u.first_name
+        "#,
+        params.from,
+        params.to,
+        params.facility,
+        user.facility_scope()
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(available))
+}
+
+pub async fn create(
+    _: Require<AvailabilityCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewAvailability>,
+) -> Result<(StatusCode, Json<Availability>), ApiError> {
+    check_time_range(body.starts_at, body.ends_at)?;
+
+    let mut conn = app_state.db.acquire().await?;
+
+    check_availability_scope(&mut conn, &user, body.user_id).await?;
+
+    let availability = query_as!(
+        Availability,
+        r#"
+        INSERT INTO
+            availabilities
+        (
+            user_id,
+            kind,
+            starts_at,
+            ends_at,
+            note,
+            created_by
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            $6
+        )
+        RETURNING
+            id,
+            user_id,
+            kind AS "kind: AvailabilityKind",
+            starts_at,
+            ends_at,
+            note,
+            created_by,
+            created
+        "#,
+        body.user_id,
+        body.kind as AvailabilityKind,
+        body.starts_at,
+        body.ends_at,
+        body.note,
+        user.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(availability)))
+}
+
+pub async fn update(
+    _: Require<AvailabilityEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateAvailability>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let current = query!(
+        r#"SELECT user_id, starts_at, ends_at FROM availabilities WHERE id = $1 FOR UPDATE"#,
+        body.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    check_availability_scope(&mut tx, &user, current.user_id).await?;
+
+    check_time_range(
+        body.starts_at.unwrap_or(current.starts_at),
+        body.ends_at.unwrap_or(current.ends_at),
+    )?;
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE availabilities SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        starts_at => body.starts_at,
+        ends_at => body.ends_at,
+        note => body.note
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    let result = query_builder.build().execute(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    _: Require<AvailabilityDelete>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteAvailability>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let user_id = query_scalar!(
+        r#"SELECT user_id FROM availabilities WHERE id = $1"#,
+        params.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    check_availability_scope(&mut conn, &user, user_id).await?;
+
+    let result = query!(r#"DELETE FROM availabilities WHERE id = $1"#, params.id)
+        .execute(&mut *conn)
+        .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
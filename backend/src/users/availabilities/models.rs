@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
+use uuid::Uuid;
+
+use crate::utils::db::Nullable;
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug)]
+#[sqlx(type_name = "availability_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AvailabilityKind {
+    Shift,
+    Vacation,
+    OnCall,
+}
+
+#[derive(Serialize)]
+pub struct Availability {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: AvailabilityKind,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub note: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created: DateTime<Utc>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryAvailabilities {
+    pub user_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+// Available technicians
+
+#[derive(Deserialize)]
+pub struct QueryAvailable {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub facility: Option<Uuid>,
+}
+
+#[derive(Serialize)]
+pub struct AvailableUser {
+    pub id: Uuid,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub image: Option<String>,
+    pub on_call: bool,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewAvailability {
+    pub user_id: Uuid,
+    pub kind: AvailabilityKind,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub note: Option<String>,
+}
+
+// Update, the kind can't change, delete the record and create a new one instead
+
+#[derive(Deserialize)]
+pub struct UpdateAvailability {
+    pub id: Uuid,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub note: Nullable<String>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteAvailability {
+    pub id: Uuid,
+}
@@ -0,0 +1,274 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use csv::{ReaderBuilder, Trim, Writer};
+use sqlx::{query, query_as, query_scalar};
+use validator::ValidateEmail;
+
+use crate::{
+    auth::permissions::{Require, UserCreate, UserView},
+    users::models::User,
+    utils::errors::ApiError,
+    AppState,
+};
+
+use super::models::{ImportReport, ImportRowReport, ImportUser, QueryImport, UserRow};
+
+// Same checks as users::create, collected per row instead of failing on the first one
+
+pub async fn import(
+    _: Require<UserCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryImport>,
+    body: String,
+) -> Result<(StatusCode, Json<ImportReport>), ApiError> {
+    let dry_run = params.dry_run.unwrap_or(false);
+
+    let roles = query!(r#"SELECT r.id, r.name, r.level, r.has_password FROM roles r"#)
+        .fetch_all(&app_state.db)
+        .await?
+        .into_iter()
+        .map(|role| (role.name.to_lowercase(), role))
+        .collect::<HashMap<_, _>>();
+
+    let facilities = query!(r#"SELECT f.id, f.name FROM facilities f"#)
+        .fetch_all(&app_state.db)
+        .await?
+        .into_iter()
+        .map(|facility| (facility.name.to_lowercase(), facility.id))
+        .collect::<HashMap<_, _>>();
+
+    let taken_emails = query_scalar!(r#"SELECT u.email FROM users u"#)
+        .fetch_all(&app_state.db)
+        .await?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    let mut seen_emails = HashSet::new();
+    let mut new_users = Vec::new();
+    let mut rows = Vec::new();
+
+    let mut reader = ReaderBuilder::new()
+        .trim(Trim::All)
+        .from_reader(body.as_bytes());
+
+    // Line 1 is the header
+
+    for (index, record) in reader.deserialize::<UserRow>().enumerate() {
+        let line = index + 2;
+
+        let row = match record {
+            Ok(row) => row,
+            Err(error) => {
+                rows.push(ImportRowReport {
+                    row: line,
+                    email: None,
+                    errors: vec![error.to_string()],
+                });
+                continue;
+            }
+        };
+
+        let mut errors = Vec::new();
+
+        let email = row.email.to_lowercase();
+
+        if !email.validate_email() {
+            errors.push("Invalid email".to_owned());
+        }
+
+        if row.first_name.is_empty() || row.last_name.is_empty() {
+            errors.push("First and last name are required".to_owned());
+        }
+
+        if taken_emails.contains(&email) {
+            errors.push("This email is already taken".to_owned());
+        } else if !seen_emails.insert(email.clone()) {
+            errors.push("This email appears more than once in the file".to_owned());
+        }
+
+        let role = roles.get(&row.role.to_lowercase());
+
+        match role {
+            None => errors.push(format!("Unknown role '{}'", row.role)),
+            Some(role) if role.level <= user.role.level => {
+                errors.push("You lack permission to give out this role".to_owned())
+            }
+            Some(role) if role.has_password => {
+                errors.push("This role needs a password, create the user on its own".to_owned())
+            }
+            Some(_) => {}
+        }
+
+        let facility = match &row.facility {
+            None => None,
+            Some(name) => match facilities.get(&name.to_lowercase()) {
+                None => {
+                    errors.push(format!("Unknown facility '{name}'"));
+                    None
+                }
+                Some(id) => Some(*id),
+            },
+        };
+
+        if user.check_facility(facility).is_err() {
+            errors.push("This is outside of your facilities".to_owned());
+        }
+
+        if let (true, Some(role)) = (errors.is_empty(), role) {
+            new_users.push(ImportUser {
+                first_name: row.first_name,
+                last_name: row.last_name,
+                email: email.clone(),
+                phone: row.phone,
+                role: role.id,
+                facility,
+                occupation: row.occupation,
+            });
+        }
+
+        rows.push(ImportRowReport {
+            row: line,
+            email: Some(email),
+            errors,
+        });
+    }
+
+    let has_errors = rows.iter().any(|row| !row.errors.is_empty());
+
+    if dry_run || has_errors {
+        let status = match has_errors && !dry_run {
+            true => StatusCode::UNPROCESSABLE_ENTITY,
+            false => StatusCode::OK,
+        };
+
+        return Ok((
+            status,
+            Json(ImportReport {
+                dry_run,
+                created: 0,
+                rows,
+            }),
+        ));
+    }
+
+    let mut tx = app_state.db.begin().await?;
+
+    for new_user in &new_users {
+        query!(
+            r#"
+            INSERT INTO users
+            (
+                first_name,
+                last_name,
+                email,
+                phone,
+                role,
+                occupation,
+                facility
+            )
+            VALUES
+            (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7
+            )
+            "#,
+            new_user.first_name,
+            new_user.last_name,
+            new_user.email,
+            new_user.phone,
+            new_user.role,
+            new_user.occupation,
+            new_user.facility
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ImportReport {
+            dry_run,
+            created: new_users.len(),
+            rows,
+        }),
+    ))
+}
+
+// Same users as users::index, in the format import reads
+
+pub async fn export(
+    _: Require<UserView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let users = query_as!(
+        UserRow,
+        r#"
+        SELECT
+            u.first_name,
+            u.last_name,
+            u.email,
+            u.phone,
+            r.name AS role,
+            f.name AS "facility?",
+            u.occupation
+        FROM
+            users u
+        INNER JOIN
+            roles r
+        ON
+            u.role = r.id
+        LEFT JOIN
+            facilities f
+        ON
+            u.facility = f.id
+        WHERE
+            ($1::UUID[] IS NULL OR u.facility = ANY($1) OR u.id = $2)
+        ORDER BY
+            u.last_name,
+            u.first_name
+        "#,
+        user.facility_scope(),
+        user.id
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    let mut writer = Writer::from_writer(Vec::new());
+
+    for row in &users {
+        writer.serialize(row)?;
+    }
+
+    let csv = writer
+        .into_inner()
+        .map_err(|error| ApiError::GeneralOversight(error.to_string()))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"users.csv\"",
+            ),
+        ],
+        csv,
+    ))
+}
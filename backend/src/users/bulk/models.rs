@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// One line of the users csv, roles and facilities are referred to by name
+
+#[derive(Deserialize, Serialize)]
+pub struct UserRow {
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub phone: Option<String>,
+    pub role: String,
+    pub facility: Option<String>,
+    pub occupation: Option<String>,
+}
+
+// Import
+
+#[derive(Deserialize)]
+pub struct QueryImport {
+    pub dry_run: Option<bool>,
+}
+
+pub struct ImportUser {
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub phone: Option<String>,
+    pub role: Uuid,
+    pub facility: Option<Uuid>,
+    pub occupation: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportRowReport {
+    pub row: usize,
+    pub email: Option<String>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub created: usize,
+    pub rows: Vec<ImportRowReport>,
+}
@@ -7,33 +7,80 @@ use axum::{
     Extension, Json,
 };
 use rand_core::OsRng;
-use sqlx::{query, query_as, query_scalar, Postgres, QueryBuilder};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    auth::permissions::{Require, UserCreate, UserDelete, UserEdit, UserView},
     field_vec,
-    machines::facilities::Facility,
+    machines::{
+        facilities::Facility,
+        locations::handlers::{check_location, location_change},
+    },
     update_field, user_from_id,
     utils::{
-        check_permission,
-        db::{Field, IntoField},
+        db::{Field, IntoField, Nullable},
         errors::{ApiError, ConflictReason, ForbiddenReason, InputInvalidReason},
     },
     AppState,
 };
 
 use super::{
-    models::{NewUser, QueryUser, UpdateUser, User},
+    invitations::send_invitation,
+    models::{CreatedUser, NewUser, QueryUser, UpdateUser, User},
     roles::models::Role,
 };
 
+// Extra facilities can only be granted within the caller's own scope
+
+fn check_facilities(user: &User, facilities: Option<&[Uuid]>) -> Result<(), ApiError> {
+    for facility in facilities.unwrap_or_default() {
+        user.check_facility(Some(*facility))?;
+    }
+
+    Ok(())
+}
+
+async fn set_facilities(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    facilities: &[Uuid],
+) -> Result<(), ApiError> {
+    query!(r#"DELETE FROM user_facilities WHERE user_id = $1"#, user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    query!(
+        r#"
+        INSERT INTO
+            user_facilities
+        (
+            user_id,
+            facility_id
+        )
+        SELECT
+            $1,
+            facility_id
+        FROM
+            unnest($2::UUID[]) AS facility_id
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        facilities
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn details(
+    _: Require<UserView>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryUser>,
 ) -> Result<Json<User>, ApiError> {
-    check_permission(user.role.user_view)?;
-
     let user = query_as!(
         User,
         r#"
@@ -48,26 +95,8 @@ pub async fn details(
                 r.name,
                 r.level,
                 r.has_password,
-                r.user_view,
-                r.user_create,
-                r.user_edit,
-                r.user_delete,
-                r.machine_view,
-                r.machine_create,
-                r.machine_edit,
-                r.machine_delete,
-                r.task_view,
-                r.task_create,
-                r.task_edit,
-                r.task_delete,
-                r.report_view,
-                r.report_create,
-                r.report_edit,
-                r.report_delete,
-                r.facility_view,
-                r.facility_create,
-                r.facility_edit,
-                r.facility_delete
+                r.global_access,
+                role_permission_keys(r.id)
             ) AS "role!: Role",
             u.active,
             u.last_login,
@@ -77,7 +106,10 @@ pub async fn details(
                 f.id,
                 f.name,
                 f.address
-            ) AS "facility?: Facility"
+            ) AS "facility?: Facility",
+            u.location,
+            user_facility_ids(u.id) AS "facilities!",
+            u.service_account
         FROM
             users u
         INNER JOIN
@@ -90,8 +122,12 @@ pub async fn details(
             u.facility = f.id
         WHERE
             u.id = $1
+        AND
+            ($2::UUID[] IS NULL OR u.facility = ANY($2) OR u.id = $3)
         "#,
-        params.id
+        params.id,
+        user.facility_scope(),
+        user.id
     )
     .fetch_one(&app_state.db)
     .await?;
@@ -100,11 +136,10 @@ pub async fn details(
 }
 
 pub async fn index(
-    State(app_state): State<Arc<AppState>>,
+    _: Require<UserView>,
     Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<User>>, ApiError> {
-    check_permission(user.role.user_view)?;
-
     let users = query_as!(
         User,
         r#"
@@ -119,26 +154,8 @@ pub async fn index(
                 r.name,
                 r.level,
                 r.has_password,
-                r.user_view,
-                r.user_create,
-                r.user_edit,
-                r.user_delete,
-                r.machine_view,
-                r.machine_create,
-                r.machine_edit,
-                r.machine_delete,
-                r.task_view,
-                r.task_create,
-                r.task_edit,
-                r.task_delete,
-                r.report_view,
-                r.report_create,
-                r.report_edit,
-                r.report_delete,
-                r.facility_view,
-                r.facility_create,
-                r.facility_edit,
-                r.facility_delete
+                r.global_access,
+                role_permission_keys(r.id)
             ) AS "role!: Role",
             u.active,
             u.last_login,
@@ -148,7 +165,10 @@ pub async fn index(
                 f.id,
                 f.name,
                 f.address
-            ) AS "facility?: Facility"
+            ) AS "facility?: Facility",
+            u.location,
+            user_facility_ids(u.id) AS "facilities!",
+            u.service_account
         FROM
             users u
         INNER JOIN
@@ -159,7 +179,11 @@ pub async fn index(
             facilities f
         ON
             u.facility = f.id
-        "#
+        WHERE
+            ($1::UUID[] IS NULL OR u.facility = ANY($1) OR u.id = $2)
+        "#,
+        user.facility_scope(),
+        user.id
     )
     .fetch_all(&app_state.db)
     .await?;
@@ -168,12 +192,11 @@ pub async fn index(
 }
 
 pub async fn create(
+    _: Require<UserCreate>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewUser>,
-) -> Result<(StatusCode, Json<User>), ApiError> {
-    check_permission(user.role.user_create)?;
-
+) -> Result<(StatusCode, Json<CreatedUser>), ApiError> {
     body.validate()?;
 
     let email = body.email.to_lowercase();
@@ -192,7 +215,12 @@ pub async fn create(
         Role,
         r#"
         SELECT
-            *
+            r.id,
+            r.name,
+            r.level,
+            r.has_password,
+            r.global_access,
+            role_permission_keys(r.id) AS "permissions!"
         FROM
             roles r
         WHERE
@@ -207,7 +235,15 @@ pub async fn create(
         return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
     }
 
-    let password = match role.has_password {
+    user.check_facility(body.facility)?;
+
+    check_facilities(&user, body.facilities.as_deref())?;
+
+    // Invited users stay inactive and set their own password when they accept
+
+    let invite = body.invite.unwrap_or(false);
+
+    let password = match role.has_password && !invite {
         false => None,
         true => match body.password {
             None => {
@@ -225,87 +261,44 @@ pub async fn create(
         },
     };
 
-    let user = query_as!(
-        User,
+    let mut tx = app_state.db.begin().await?;
+
+    if let Some(location) = body.location {
+        check_location(&mut tx, location, body.facility).await?;
+    }
+
+    let user_id = query_scalar!(
         r#"
-        WITH new_user AS (
-            INSERT INTO users
-            (
-                first_name,
-                last_name,
-                email,
-                password,
-                phone,
-                role,
-                active,
-                occupation,
-                facility
-            )
-            VALUES
-            (
-                $1,
-                $2,
-                $3,
-                $4,
-                $5,
-                $6,
-                $7,
-                $8,
-                $9
-            )
-            RETURNING *
+        INSERT INTO users
+        (
+            first_name,
+            last_name,
+            email,
+            password,
+            phone,
+            role,
+            active,
+            occupation,
+            facility,
+            service_account,
+            location
         )
-        SELECT
-            u.id,
-            u.first_name,
-            u.last_name,
-            u.email,
-            u.phone,
-            (
-                r.id,
-                r.name,
-                r.level,
-                r.has_password,
-                r.user_view,
-                r.user_create,
-                r.user_edit,
-                r.user_delete,
-                r.machine_view,
-                r.machine_create,
-                r.machine_edit,
-                r.machine_delete,
-                r.task_view,
-                r.task_create,
-                r.task_edit,
-                r.task_delete,
-                r.report_view,
-                r.report_create,
-                r.report_edit,
-                r.report_delete,
-                r.facility_view,
-                r.facility_create,
-                r.facility_edit,
-                r.facility_delete
-            ) AS "role!: Role",
-            u.active,
-            u.last_login,
-            u.occupation,
-            u.image,
-            (
-                f.id,
-                f.name,
-                f.address
-            ) AS "facility?: Facility"
-        FROM
-            new_user u
-        INNER JOIN
-            roles r
-        ON
-            u.role = r.id
-        LEFT JOIN
-            facilities f
-        ON
-            u.facility = f.id
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5,
+            $6,
+            $7,
+            $8,
+            $9,
+            $10,
+            $11
+        )
+        RETURNING
+            id
         "#,
         body.first_name,
         body.last_name,
@@ -313,14 +306,39 @@ pub async fn create(
         password,
         body.phone,
         body.role,
-        body.active.unwrap_or(true),
+        body.active.unwrap_or(true) && !invite,
         body.occupation,
         body.facility,
+        body.service_account.unwrap_or(false),
+        body.location
     )
-    .fetch_one(&app_state.db)
+    .fetch_one(&mut *tx)
     .await?;
 
-    Ok((StatusCode::CREATED, Json(user)))
+    if let Some(facilities) = body.facilities {
+        set_facilities(&mut tx, user_id, &facilities).await?;
+    }
+
+    let invitation_link = match invite {
+        false => None,
+        true => Some(
+            send_invitation(&mut tx, &app_state, user_id, &email, user.id)
+                .await?
+                .link,
+        ),
+    };
+
+    let user = user_from_id!(user_id).fetch_one(&mut *tx).await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedUser {
+            user,
+            invitation_link,
+        }),
+    ))
 }
 
 pub async fn update(
@@ -328,12 +346,24 @@ pub async fn update(
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateUser>,
 ) -> Result<Json<User>, ApiError> {
-    let permissions_ok = user.role.user_edit || body.id == user.id;
+    let permissions_ok = user.role.has::<UserEdit>() || body.id == user.id;
 
     if !permissions_ok {
         return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
     }
 
+    // Nobody changes their own role, active flag or facilities, self service goes through /me
+
+    let changes_access = body.role.is_some()
+        || body.active.is_some()
+        || !matches!(body.facility, Nullable::Absent)
+        || body.facilities.is_some()
+        || body.service_account.is_some();
+
+    if body.id == user.id && changes_access {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
     if body.email.is_some() {
         body.validate()?;
     }
@@ -346,12 +376,42 @@ pub async fn update(
         return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
     }
 
+    if target_user.id != user.id {
+        user.check_facility(
+            target_user
+                .facility
+                .as_ref()
+                .and_then(|facility| facility.id),
+        )?;
+    }
+
+    user.check_facility_change(&body.facility)?;
+
+    check_facilities(&user, body.facilities.as_deref())?;
+
+    let location = location_change(
+        &mut tx,
+        target_user
+            .facility
+            .as_ref()
+            .and_then(|facility| facility.id),
+        target_user.location,
+        &body.facility,
+        body.location,
+    )
+    .await?;
+
     if let Some(role_id) = body.role {
         let role = query_as!(
             Role,
             r#"
             SELECT
-                *
+                r.id,
+                r.name,
+                r.level,
+                r.has_password,
+                r.global_access,
+                role_permission_keys(r.id) AS "permissions!"
             FROM
                 roles r
             WHERE
@@ -367,9 +427,6 @@ pub async fn update(
         }
     }
 
-    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE users SET");
-    let mut separated_list = query_builder.separated(",");
-
     let fields = field_vec![
         first_name => body.first_name,
         last_name => body.last_name,
@@ -379,26 +436,37 @@ pub async fn update(
         role => body.role,
         active => body.active,
         occupation => body.occupation,
-        facility => body.facility
+        facility => body.facility,
+        location => location,
+        service_account => body.service_account
     ];
 
-    if fields.len() < 1 {
+    if fields.is_empty() && body.facilities.is_none() {
         return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
     }
 
-    for (field, value) in fields {
-        update_field!(separated_list, field, value);
-    }
+    if !fields.is_empty() {
+        let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE users SET");
+        let mut separated_list = query_builder.separated(",");
+
+        for (field, value) in fields {
+            update_field!(separated_list, field, value);
+        }
+
+        query_builder.push(" WHERE id = ");
+        query_builder.push_bind(body.id);
 
-    query_builder.push(" WHERE id = ");
-    query_builder.push_bind(body.id);
+        let result = query_builder.build().execute(&mut *tx).await?;
 
-    let result = query_builder.build().execute(&mut *tx).await?;
+        if result.rows_affected() != 1 {
+            return Err(ApiError::GeneralOversight(
+                "Provided machine to update didn't exist".to_owned(),
+            ));
+        }
+    }
 
-    if result.rows_affected() != 1 {
-        return Err(ApiError::GeneralOversight(
-            "Provided machine to update didn't exist".to_owned(),
-        ));
+    if let Some(facilities) = body.facilities {
+        set_facilities(&mut tx, body.id, &facilities).await?;
     }
 
     let user = query_as!(
@@ -415,26 +483,8 @@ pub async fn update(
                 r.name,
                 r.level,
                 r.has_password,
-                r.user_view,
-                r.user_create,
-                r.user_edit,
-                r.user_delete,
-                r.machine_view,
-                r.machine_create,
-                r.machine_edit,
-                r.machine_delete,
-                r.task_view,
-                r.task_create,
-                r.task_edit,
-                r.task_delete,
-                r.report_view,
-                r.report_create,
-                r.report_edit,
-                r.report_delete,
-                r.facility_view,
-                r.facility_create,
-                r.facility_edit,
-                r.facility_delete
+                r.global_access,
+                role_permission_keys(r.id)
             ) AS "role!: Role",
             u.active,
             u.last_login,
@@ -444,7 +494,10 @@ pub async fn update(
                 f.id,
                 f.name,
                 f.address
-            ) AS "facility?: Facility"
+            ) AS "facility?: Facility",
+            u.location,
+            user_facility_ids(u.id) AS "facilities!",
+            u.service_account
         FROM
             users u
         INNER JOIN
@@ -469,12 +522,11 @@ pub async fn update(
 }
 
 pub async fn delete(
+    _: Require<UserDelete>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryUser>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.user_delete)?;
-
     let target_user = query_as!(
         User,
         r#"
@@ -489,26 +541,8 @@ pub async fn delete(
                 r.name,
                 r.level,
                 r.has_password,
-                r.user_view,
-                r.user_create,
-                r.user_edit,
-                r.user_delete,
-                r.machine_view,
-                r.machine_create,
-                r.machine_edit,
-                r.machine_delete,
-                r.task_view,
-                r.task_create,
-                r.task_edit,
-                r.task_delete,
-                r.report_view,
-                r.report_create,
-                r.report_edit,
-                r.report_delete,
-                r.facility_view,
-                r.facility_create,
-                r.facility_edit,
-                r.facility_delete
+                r.global_access,
+                role_permission_keys(r.id)
             ) AS "role!: Role",
             u.active,
             u.last_login,
@@ -518,7 +552,10 @@ pub async fn delete(
                 f.id,
                 f.name,
                 f.address
-            ) AS "facility?: Facility"
+            ) AS "facility?: Facility",
+            u.location,
+            user_facility_ids(u.id) AS "facilities!",
+            u.service_account
         FROM
             users u
         INNER JOIN
@@ -541,6 +578,8 @@ pub async fn delete(
         return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
     }
 
+    user.check_facility(target_user.facility.and_then(|facility| facility.id))?;
+
     let result = query!(r#"DELETE FROM users WHERE id = $1"#, params.id)
         .execute(&app_state.db)
         .await?;
@@ -0,0 +1,415 @@
+use std::sync::Arc;
+
+use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand_core::OsRng;
+use sqlx::{query, query_as, query_scalar, PgConnection};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::permissions::{Require, UserCreate, UserView},
+    machines::facilities::Facility,
+    user_from_id,
+    users::{models::User, roles::models::Role},
+    utils::errors::{ApiError, ForbiddenReason, InputInvalidReason},
+    AppState,
+};
+
+use super::models::{
+    AcceptInvitation, DeleteInvitation, Invitation, InvitationDetails, InvitationToken,
+    NewInvitation, QueryInvitation, QueryInvitations, SentInvitation, INVITATION_AUDIENCE,
+    INVITATION_DAYS,
+};
+
+// Revokes any pending invitation for the user so only the newest link works.
+// The link lets anyone set the password, so it only goes back to the inviting administrator, never the logs
+
+pub async fn send_invitation(
+    conn: &mut PgConnection,
+    app_state: &AppState,
+    user_id: Uuid,
+    email: &str,
+    invited_by: Uuid,
+) -> Result<SentInvitation, ApiError> {
+    query!(
+        r#"
+        UPDATE
+            invitations
+        SET
+            revoked = TRUE
+        WHERE
+            user_id = $1
+        AND
+            accepted_at IS NULL
+        AND
+            NOT revoked
+        "#,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let now = chrono::Utc::now();
+    let expires_at = now + chrono::Duration::days(INVITATION_DAYS);
+
+    let invitation = query_as!(
+        Invitation,
+        r#"
+        INSERT INTO
+            invitations
+        (
+            user_id,
+            invited_by,
+            expires_at
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3
+        )
+        RETURNING
+            id,
+            user_id,
+            invited_by,
+            created,
+            expires_at,
+            accepted_at,
+            revoked
+        "#,
+        user_id,
+        invited_by,
+        expires_at
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let claims = InvitationToken {
+        sub: invitation.id.to_string(),
+        aud: INVITATION_AUDIENCE.to_owned(),
+        iat: now.timestamp() as usize,
+        exp: expires_at.timestamp() as usize,
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(app_state.env.jwt_pwl_secret.as_ref()),
+    )?;
+
+    info!(invitation = %invitation.id, recipient = email, "Created invitation");
+
+    Ok(SentInvitation {
+        link: format!("{}/invitation?token={token}", app_state.env.public_url),
+        invitation,
+    })
+}
+
+fn invitation_id_from_token(app_state: &AppState, token: &str) -> Result<Uuid, ApiError> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[INVITATION_AUDIENCE]);
+
+    let claims = decode::<InvitationToken>(
+        token,
+        &DecodingKey::from_secret(app_state.env.jwt_pwl_secret.as_ref()),
+        &validation,
+    )?
+    .claims;
+
+    Ok(Uuid::parse_str(&claims.sub)?)
+}
+
+pub async fn details(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryInvitation>,
+) -> Result<Json<InvitationDetails>, ApiError> {
+    let invitation_id = invitation_id_from_token(&app_state, &params.token)?;
+
+    let details = query_as!(
+        InvitationDetails,
+        r#"
+        SELECT
+            u.first_name,
+            u.last_name,
+            u.email,
+            r.has_password,
+            i.expires_at
+        FROM
+            invitations i
+        INNER JOIN
+            users u
+        ON
+            i.user_id = u.id
+        INNER JOIN
+            roles r
+        ON
+            u.role = r.id
+        WHERE
+            i.id = $1
+        AND
+            NOT i.revoked
+        AND
+            i.accepted_at IS NULL
+        AND
+            i.expires_at > NOW()
+        "#,
+        invitation_id
+    )
+    .fetch_optional(&app_state.db)
+    .await?
+    .ok_or(ApiError::Forbidden(ForbiddenReason::InvitationInvalid))?;
+
+    Ok(Json(details))
+}
+
+// Sets the password for password based roles and activates the account
+
+pub async fn accept(
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<AcceptInvitation>,
+) -> Result<StatusCode, ApiError> {
+    body.validate()?;
+
+    let invitation_id = invitation_id_from_token(&app_state, &body.token)?;
+
+    let mut tx = app_state.db.begin().await?;
+
+    let invitation = query!(
+        r#"
+        SELECT
+            i.user_id,
+            r.has_password
+        FROM
+            invitations i
+        INNER JOIN
+            users u
+        ON
+            i.user_id = u.id
+        INNER JOIN
+            roles r
+        ON
+            u.role = r.id
+        WHERE
+            i.id = $1
+        AND
+            NOT i.revoked
+        AND
+            i.accepted_at IS NULL
+        AND
+            i.expires_at > NOW()
+        AND
+            u.activated_at IS NULL
+        FOR UPDATE OF i
+        "#,
+        invitation_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ApiError::Forbidden(ForbiddenReason::InvitationInvalid))?;
+
+    let password = match invitation.has_password {
+        false => None,
+        true => match body.password {
+            None => {
+                return Err(ApiError::InputInvalid(
+                    InputInvalidReason::NoPasswordSupplied,
+                ))
+            }
+            Some(password) => {
+                let salt = SaltString::generate(&mut OsRng);
+                let hashed_password = Argon2::default()
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())?;
+                Some(hashed_password)
+            }
+        },
+    };
+
+    query!(
+        r#"
+        UPDATE
+            users
+        SET
+            active = TRUE,
+            password = $2
+        WHERE
+            id = $1
+        "#,
+        invitation.user_id,
+        password
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    query!(
+        r#"UPDATE invitations SET accepted_at = NOW() WHERE id = $1"#,
+        invitation_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn index(
+    _: Require<UserView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryInvitations>,
+) -> Result<Json<Vec<Invitation>>, ApiError> {
+    let invitations = query_as!(
+        Invitation,
+        r#"
+        SELECT
+            i.id,
+            i.user_id,
+            i.invited_by,
+            i.created,
+            i.expires_at,
+            i.accepted_at,
+            i.revoked
+        FROM
+            invitations i
+        INNER JOIN
+            users u
+        ON
+            i.user_id = u.id
+        WHERE
+            ($1::UUID IS NULL OR i.user_id = $1)
+        AND
+            ($2::UUID[] IS NULL OR u.facility = ANY($2))
+        ORDER BY
+            i.created DESC
+        "#,
+        params.user_id,
+        user.facility_scope()
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(invitations))
+}
+
+// Resends the invitation of a user that hasn't activated their account yet
+
+pub async fn create(
+    _: Require<UserCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewInvitation>,
+) -> Result<(StatusCode, Json<SentInvitation>), ApiError> {
+    let mut tx = app_state.db.begin().await?;
+
+    let target_user = user_from_id!(body.user_id).fetch_one(&mut *tx).await?;
+
+    if target_user.role.level <= user.role.level {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    user.check_facility(
+        target_user
+            .facility
+            .as_ref()
+            .and_then(|facility| facility.id),
+    )?;
+
+    if target_user.active {
+        return Err(ApiError::InputInvalid(InputInvalidReason::AlreadyActive));
+    }
+
+    // Accounts that were deactivated on purpose have to be reactivated by an administrator
+
+    let activated = query_scalar!(
+        r#"SELECT activated_at IS NOT NULL AS "activated!" FROM users WHERE id = $1"#,
+        target_user.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if activated {
+        return Err(ApiError::InputInvalid(InputInvalidReason::PreviouslyActive));
+    }
+
+    let invitation = send_invitation(
+        &mut tx,
+        &app_state,
+        target_user.id,
+        &target_user.email,
+        user.id,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+pub async fn delete(
+    _: Require<UserCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteInvitation>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let target = query!(
+        r#"
+        SELECT
+            r.level,
+            u.facility
+        FROM
+            invitations i
+        INNER JOIN
+            users u
+        ON
+            i.user_id = u.id
+        INNER JOIN
+            roles r
+        ON
+            u.role = r.id
+        WHERE
+            i.id = $1
+        "#,
+        params.id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if target.level <= user.role.level {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    user.check_facility(target.facility)?;
+
+    let result = query!(
+        r#"
+        UPDATE
+            invitations
+        SET
+            revoked = TRUE
+        WHERE
+            id = $1
+        AND
+            accepted_at IS NULL
+        AND
+            NOT revoked
+        "#,
+        params.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,9 @@
+pub mod handlers;
+pub mod models;
+
+pub use handlers::accept;
+pub use handlers::create;
+pub use handlers::delete;
+pub use handlers::details;
+pub use handlers::index;
+pub use handlers::send_invitation;
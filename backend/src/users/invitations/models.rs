@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+pub const INVITATION_DAYS: i64 = 7;
+
+pub const INVITATION_AUDIENCE: &str = "invitation";
+
+#[derive(Serialize)]
+pub struct Invitation {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub invited_by: Option<Uuid>,
+    pub created: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub revoked: bool,
+}
+
+// Handed to the inviting administrator, who passes the link on to the invitee
+
+#[derive(Serialize)]
+pub struct SentInvitation {
+    pub link: String,
+    #[serde(flatten)]
+    pub invitation: Invitation,
+}
+
+// Signed into the link sent to the invitee, sub is the invitation id
+
+#[derive(Serialize, Deserialize)]
+pub struct InvitationToken {
+    pub sub: String,
+    pub aud: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+// Details, shown to the invitee before they accept
+
+#[derive(Deserialize)]
+pub struct QueryInvitation {
+    pub token: String,
+}
+
+#[derive(Serialize)]
+pub struct InvitationDetails {
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub has_password: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryInvitations {
+    pub user_id: Option<Uuid>,
+}
+
+// Create, sends a new invitation and revokes the pending one
+
+#[derive(Deserialize)]
+pub struct NewInvitation {
+    pub user_id: Uuid,
+}
+
+// Accept
+
+#[derive(Validate, Deserialize)]
+pub struct AcceptInvitation {
+    pub token: String,
+    #[validate(length(min = 1, max = 255))]
+    pub password: Option<String>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteInvitation {
+    pub id: Uuid,
+}
@@ -1,5 +1,14 @@
 // Nested modules
+pub mod api_keys;
+pub mod availabilities;
+pub mod bulk;
+pub mod invitations;
 pub mod roles;
+pub mod skills;
+pub mod team_members;
+pub mod teams;
+pub mod user_rates;
+pub mod user_skills;
 
 // Inner modules
 pub mod handlers;
@@ -4,7 +4,13 @@ use sqlx::prelude::{FromRow, Type};
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::{machines::facilities::Facility, utils::db::Nullable};
+use crate::{
+    machines::facilities::Facility,
+    utils::{
+        db::Nullable,
+        errors::{ApiError, ForbiddenReason},
+    },
+};
 
 use super::roles::models::Role;
 
@@ -21,11 +27,44 @@ pub struct User {
     pub occupation: Option<String>,
     pub image: Option<String>,
     pub facility: Option<Facility>,
+    pub location: Option<Uuid>,
+    pub facilities: Vec<Uuid>,
+    pub service_account: bool,
+}
+
+impl User {
+    // None when the role reaches every facility
+
+    pub fn facility_scope(&self) -> Option<&[Uuid]> {
+        match self.role.global_access {
+            true => None,
+            false => Some(&self.facilities),
+        }
+    }
+
+    pub fn check_facility(&self, facility: Option<Uuid>) -> Result<(), ApiError> {
+        let in_scope = self.role.global_access
+            || facility.is_some_and(|facility| self.facilities.contains(&facility));
+
+        if !in_scope {
+            return Err(ApiError::Forbidden(ForbiddenReason::OutsideFacility));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_facility_change(&self, facility: &Nullable<Uuid>) -> Result<(), ApiError> {
+        match facility {
+            Nullable::Absent => Ok(()),
+            Nullable::Null => self.check_facility(None),
+            Nullable::Value(facility) => self.check_facility(Some(*facility)),
+        }
+    }
 }
 
 // Short variant
 
-#[derive(Type, FromRow, Serialize, Debug)]
+#[derive(Type, FromRow, Serialize, Debug, Clone)]
 pub struct ShortUser {
     pub id: Option<Uuid>,
     pub first_name: Option<String>,
@@ -49,12 +88,27 @@ pub struct NewUser {
     pub last_name: String,
     #[validate(email)]
     pub email: String,
+    #[validate(length(min = 1, max = 255))]
     pub password: Option<String>,
     pub phone: Option<String>,
     pub role: Uuid,
     pub active: Option<bool>,
     pub occupation: Option<String>,
     pub facility: Option<Uuid>,
+    pub location: Option<Uuid>,
+    pub facilities: Option<Vec<Uuid>>,
+    pub service_account: Option<bool>,
+    pub invite: Option<bool>,
+}
+
+// Invited users come with the link for the administrator to pass on
+
+#[derive(Serialize)]
+pub struct CreatedUser {
+    #[serde(flatten)]
+    pub user: User,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invitation_link: Option<String>,
 }
 
 // Update
@@ -76,4 +130,8 @@ pub struct UpdateUser {
     pub occupation: Nullable<String>,
     #[serde(default)]
     pub facility: Nullable<Uuid>,
+    #[serde(default)]
+    pub location: Nullable<Uuid>,
+    pub facilities: Option<Vec<Uuid>>,
+    pub service_account: Option<bool>,
 }
@@ -6,59 +6,146 @@ use axum::{
     Extension, Json,
 };
 
-use sqlx::{query, query_as, Postgres, QueryBuilder};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
 
 use crate::{
-    field_vec, insert_fields, update_field,
+    auth::permissions::{Require, RoleCreate, RoleDelete, RoleEdit, RoleView},
+    field_vec, update_field,
     users::models::User,
     utils::{
-        check_permission,
         db::{Field, IntoField},
         errors::{ApiError, ForbiddenReason, InputInvalidReason},
     },
     AppState,
 };
 
-use super::models::{NewRole, QueryRole, Role, UpdateRole};
+use super::models::{NewRole, PermissionEntry, QueryRole, Role, UpdateRole};
 
-pub async fn details(
-    Extension(user): Extension<User>,
-    State(app_state): State<Arc<AppState>>,
-    Query(params): Query<QueryRole>,
-) -> Result<Json<Role>, ApiError> {
-    check_permission(user.role.user_view)?;
+// Only global roles can hand out global access
+
+fn check_global_access(user: &User, global_access: Option<bool>) -> Result<(), ApiError> {
+    if global_access == Some(true) && !user.role.global_access {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    Ok(())
+}
+
+// Nobody can hand out a permission they don't hold themselves
+
+pub fn check_grantable(user: &User, permissions: &[String]) -> Result<(), ApiError> {
+    if !permissions
+        .iter()
+        .all(|key| user.role.permissions.contains(key))
+    {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    Ok(())
+}
 
+async fn set_permissions(
+    conn: &mut PgConnection,
+    role_id: Uuid,
+    permissions: &[String],
+) -> Result<(), ApiError> {
+    query!(
+        r#"DELETE FROM role_permissions WHERE role_id = $1"#,
+        role_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let result = query!(
+        r#"
+        INSERT INTO
+            role_permissions
+        (
+            role_id,
+            resource,
+            action
+        )
+        SELECT
+            $1,
+            p.resource,
+            p.action
+        FROM
+            permissions p
+        WHERE
+            p.resource || '.' || p.action = ANY($2)
+        "#,
+        role_id,
+        permissions
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let mut unique = permissions.to_vec();
+    unique.sort();
+    unique.dedup();
+
+    if result.rows_affected() as usize != unique.len() {
+        return Err(ApiError::InputInvalid(
+            InputInvalidReason::UnknownPermission,
+        ));
+    }
+
+    Ok(())
+}
+
+async fn role_from_id(conn: &mut PgConnection, id: Uuid) -> Result<Role, ApiError> {
     let role = query_as!(
         Role,
         r#"
         SELECT
-            *
+            r.id,
+            r.name,
+            r.level,
+            r.has_password,
+            r.global_access,
+            role_permission_keys(r.id) AS "permissions!"
         FROM
             roles r
         WHERE
-            r.id = $1 
+            r.id = $1
         "#,
-        params.id
+        id
     )
-    .fetch_one(&app_state.db)
+    .fetch_one(&mut *conn)
     .await?;
 
+    Ok(role)
+}
+
+pub async fn details(
+    _: Require<RoleView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryRole>,
+) -> Result<Json<Role>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    let role = role_from_id(&mut conn, params.id).await?;
+
     Ok(Json(role))
 }
 
 pub async fn index(
-    Extension(user): Extension<User>,
+    _: Require<RoleView>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<Role>>, ApiError> {
-    check_permission(user.role.user_view)?;
-
     let roles = query_as!(
         Role,
         r#"
-        SELECT 
-            * 
-        FROM 
-            roles
+        SELECT
+            r.id,
+            r.name,
+            r.level,
+            r.has_password,
+            r.global_access,
+            role_permission_keys(r.id) AS "permissions!"
+        FROM
+            roles r
         "#
     )
     .fetch_all(&app_state.db)
@@ -67,78 +154,94 @@ pub async fn index(
     Ok(Json(roles))
 }
 
+pub async fn permissions(
+    _: Require<RoleView>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<PermissionEntry>>, ApiError> {
+    let permissions = query_as!(
+        PermissionEntry,
+        r#"
+        SELECT
+            p.resource || '.' || p.action AS "key!",
+            p.resource,
+            p.action
+        FROM
+            permissions p
+        ORDER BY
+            p.resource,
+            p.action
+        "#
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(permissions))
+}
+
 pub async fn create(
+    _: Require<RoleCreate>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<NewRole>,
 ) -> Result<(StatusCode, Json<Role>), ApiError> {
-    check_permission(user.role.user_create)?;
-
     if body.level <= user.role.level {
         return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission))?;
     }
 
-    let mut query_builder = QueryBuilder::<Postgres>::new("INSERT INTO roles ( ");
+    check_global_access(&user, body.global_access)?;
 
-    let fields = field_vec![
-        name => body.name,
-        level => body.level,
-        has_password => body.has_password,
-        user_view => body.user_view,
-        user_create => body.user_create,
-        user_edit => body.user_edit,
-        user_delete => body.user_delete,
-        machine_view => body.machine_view,
-        machine_create => body.machine_create,
-        machine_edit => body.machine_edit,
-        machine_delete => body.machine_delete,
-        task_view => body.task_view,
-        task_create => body.task_create,
-        task_edit => body.task_edit,
-        task_delete => body.task_delete,
-        report_view => body.report_view,
-        report_create => body.report_create,
-        report_edit => body.report_edit,
-        report_delete => body.report_delete,
-        facility_view => body.facility_view,
-        facility_create => body.facility_create,
-        facility_edit => body.facility_edit,
-        facility_delete => body.facility_delete
-    ];
+    let permissions = body.permissions.unwrap_or_default();
 
-    insert_fields!(query_builder, &fields);
+    check_grantable(&user, &permissions)?;
 
-    query_builder.push(" RETURNING *");
+    let mut tx = app_state.db.begin().await?;
 
-    let role = query_builder
-        .build_query_as::<Role>()
-        .fetch_one(&app_state.db)
-        .await?;
+    let role_id = query_scalar!(
+        r#"
+        INSERT INTO
+            roles
+        (
+            name,
+            level,
+            has_password,
+            global_access
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3,
+            $4
+        )
+        RETURNING
+            id
+        "#,
+        body.name,
+        body.level,
+        body.has_password.unwrap_or(true),
+        body.global_access.unwrap_or(false)
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    set_permissions(&mut tx, role_id, &permissions).await?;
+
+    let role = role_from_id(&mut tx, role_id).await?;
+
+    tx.commit().await?;
 
     Ok((StatusCode::CREATED, Json(role)))
 }
 
 pub async fn update(
+    _: Require<RoleEdit>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateRole>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.user_edit)?;
+    let mut tx = app_state.db.begin().await?;
 
-    let target_role = query_as!(
-        Role,
-        r#"
-        SELECT
-            *
-        FROM
-            roles r
-        WHERE
-            r.id = $1 
-        "#,
-        body.id
-    )
-    .fetch_one(&app_state.db)
-    .await?;
+    let target_role = role_from_id(&mut tx, body.id).await?;
 
     if target_role.level <= user.role.level {
         return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
@@ -150,82 +253,62 @@ pub async fn update(
         }
     }
 
-    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE roles SET");
-    let mut separated_list = query_builder.separated(",");
+    check_global_access(&user, body.global_access)?;
+
+    if let Some(permissions) = &body.permissions {
+        check_grantable(&user, permissions)?;
+    }
 
     let fields = field_vec![
         name => body.name,
         level => body.level,
         has_password => body.has_password,
-        user_view => body.user_view,
-        user_create => body.user_create,
-        user_edit => body.user_edit,
-        user_delete => body.user_delete,
-        machine_view => body.machine_view,
-        machine_create => body.machine_create,
-        machine_edit => body.machine_edit,
-        machine_delete => body.machine_delete,
-        task_view => body.task_view,
-        task_create => body.task_create,
-        task_edit => body.task_edit,
-        task_delete => body.task_delete,
-        report_view => body.report_view,
-        report_create => body.report_create,
-        report_edit => body.report_edit,
-        report_delete => body.report_delete,
-        facility_view => body.facility_view,
-        facility_create => body.facility_create,
-        facility_edit => body.facility_edit,
-        facility_delete => body.facility_delete
+        global_access => body.global_access
     ];
 
-    if fields.len() < 1 {
+    if fields.is_empty() && body.permissions.is_none() {
         return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
     }
 
-    for (field, value) in fields {
-        update_field!(separated_list, field, value);
-    }
+    if !fields.is_empty() {
+        let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE roles SET");
+        let mut separated_list = query_builder.separated(",");
 
-    query_builder.push(" WHERE id = ");
-    query_builder.push_bind(body.id);
+        for (field, value) in fields {
+            update_field!(separated_list, field, value);
+        }
 
-    let result = query_builder.build().execute(&app_state.db).await?;
+        query_builder.push(" WHERE id = ");
+        query_builder.push_bind(body.id);
 
-    match result.rows_affected() {
-        1 => Ok(StatusCode::NO_CONTENT),
-        _ => Ok(StatusCode::NOT_FOUND),
+        query_builder.build().execute(&mut *tx).await?;
     }
+
+    if let Some(permissions) = body.permissions {
+        set_permissions(&mut tx, body.id, &permissions).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn delete(
+    _: Require<RoleDelete>,
     Extension(user): Extension<User>,
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<QueryRole>,
 ) -> Result<StatusCode, ApiError> {
-    check_permission(user.role.user_delete)?;
+    let mut conn = app_state.db.acquire().await?;
 
-    let target_role = query_as!(
-        Role,
-        r#"
-        SELECT
-            *
-        FROM
-            roles r
-        WHERE
-            r.id = $1 
-        "#,
-        params.id
-    )
-    .fetch_one(&app_state.db)
-    .await?;
+    let target_role = role_from_id(&mut conn, params.id).await?;
 
     if target_role.level <= user.role.level {
         return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
     }
 
     let result = query!(r#"DELETE FROM roles WHERE id = $1"#, params.id)
-        .execute(&app_state.db)
+        .execute(&mut *conn)
         .await?;
 
     match result.rows_affected() {
@@ -7,4 +7,5 @@ pub use handlers::create;
 pub use handlers::delete;
 pub use handlers::details;
 pub use handlers::index;
+pub use handlers::permissions;
 pub use handlers::update;
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use sqlx::{query, query_as, Postgres, QueryBuilder};
+
+use crate::{
+    auth::permissions::{Require, SkillCreate, SkillDelete, SkillEdit, SkillView},
+    field_vec, update_field,
+    utils::{
+        db::{Field, IntoField},
+        errors::{ApiError, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{NewSkill, QuerySkill, QuerySkills, Skill, UpdateSkill};
+
+pub async fn details(
+    _: Require<SkillView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QuerySkill>,
+) -> Result<Json<Skill>, ApiError> {
+    let skill = query_as!(
+        Skill,
+        r#"
+        SELECT
+            s.id,
+            s.name,
+            s.description,
+            s.certification,
+            s.created
+        FROM
+            skills s
+        WHERE
+            s.id = $1
+        "#,
+        params.id
+    )
+    .fetch_one(&app_state.db)
+    .await?;
+
+    Ok(Json(skill))
+}
+
+pub async fn index(
+    _: Require<SkillView>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QuerySkills>,
+) -> Result<Json<Vec<Skill>>, ApiError> {
+    let skills = query_as!(
+        Skill,
+        r#"
+        SELECT
+            s.id,
+            s.name,
+            s.description,
+            s.certification,
+            s.created
+        FROM
+            skills s
+        WHERE
+            ($1::UUID IS NULL OR s.id IN (
+                SELECT tts.skill_id FROM task_type_skills tts WHERE tts.task_type_id = $1
+            ))
+        AND
+            ($2::UUID IS NULL OR s.id IN (
+                SELECT mts.skill_id FROM machine_type_skills mts WHERE mts.machine_type_id = $2
+            ))
+        AND
+            ($3::UUID IS NULL OR s.id IN (
+                SELECT
+                    tts.skill_id
+                FROM
+                    tasks t
+                INNER JOIN
+                    task_type_skills tts
+                ON
+                    t.task_type = tts.task_type_id
+                WHERE
+                    t.id = $3
+                UNION
+                SELECT
+                    mts.skill_id
+                FROM
+                    tasks t
+                INNER JOIN
+                    machines m
+                ON
+                    t.machine = m.id
+                INNER JOIN
+                    machine_type_skills mts
+                ON
+                    m.machine_type = mts.machine_type_id
+                WHERE
+                    t.id = $3
+            ))
+        ORDER BY
+            s.name
+        "#,
+        params.task_type_id,
+        params.machine_type_id,
+        params.task_id
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(skills))
+}
+
+pub async fn create(
+    _: Require<SkillCreate>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewSkill>,
+) -> Result<(StatusCode, Json<Skill>), ApiError> {
+    let skill = query_as!(
+        Skill,
+        r#"
+        INSERT INTO
+            skills
+        (
+            name,
+            description,
+            certification
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3
+        )
+        RETURNING
+            id,
+            name,
+            description,
+            certification,
+            created
+        "#,
+        body.name,
+        body.description,
+        body.certification.unwrap_or(false)
+    )
+    .fetch_one(&app_state.db)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(skill)))
+}
+
+pub async fn update(
+    _: Require<SkillEdit>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateSkill>,
+) -> Result<StatusCode, ApiError> {
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE skills SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        name => body.name,
+        description => body.description,
+        certification => body.certification
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    let result = query_builder.build().execute(&app_state.db).await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    _: Require<SkillDelete>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QuerySkill>,
+) -> Result<StatusCode, ApiError> {
+    let result = query!(r#"DELETE FROM skills WHERE id = $1"#, params.id)
+        .execute(&app_state.db)
+        .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
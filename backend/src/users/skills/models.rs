@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::utils::db::Nullable;
+
+#[derive(Serialize)]
+pub struct Skill {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub certification: bool,
+    pub created: DateTime<Utc>,
+}
+
+// Details
+
+#[derive(Deserialize)]
+pub struct QuerySkill {
+    pub id: Uuid,
+}
+
+// Index, the filters list the skills required by a task type, a machine type or a task
+
+#[derive(Deserialize)]
+pub struct QuerySkills {
+    pub task_type_id: Option<Uuid>,
+    pub machine_type_id: Option<Uuid>,
+    pub task_id: Option<Uuid>,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewSkill {
+    pub name: String,
+    pub description: Option<String>,
+    pub certification: Option<bool>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateSkill {
+    pub id: Uuid,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Nullable<String>,
+    pub certification: Option<bool>,
+}
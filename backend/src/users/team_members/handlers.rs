@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use sqlx::{query, query_as, query_scalar};
+
+use crate::{
+    auth::permissions::{Require, TeamEdit},
+    machines::facilities::Facility,
+    tasks::task_executors::handlers::check_executor_skills,
+    user_from_id,
+    users::{models::User, roles::models::Role, teams::handlers::check_team_scope},
+    utils::errors::ApiError,
+    AppState,
+};
+
+use super::models::TeamMember;
+
+pub async fn create(
+    _: Require<TeamEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<TeamMember>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_team_scope(&mut conn, &user, body.team_id).await?;
+
+    // Members see the tasks of the team, so they have to be in reach as well
+
+    let member = user_from_id!(body.user_id).fetch_one(&mut *conn).await?;
+
+    user.check_facility(member.facility.and_then(|facility| facility.id))?;
+
+    // Joining makes them an executor of every open task of the team
+
+    let open_tasks = query_scalar!(
+        r#"
+        SELECT
+            tt.task_id
+        FROM
+            task_teams tt
+        INNER JOIN
+            tasks t
+        ON
+            tt.task_id = t.id
+        INNER JOIN
+            task_statuses ts
+        ON
+            t.status = ts.id
+        WHERE
+            tt.team_id = $1
+        AND
+            NOT t.archived
+        AND
+            NOT ts.terminal
+        "#,
+        body.team_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for task_id in open_tasks {
+        check_executor_skills(&mut conn, task_id, &[body.user_id]).await?;
+    }
+
+    let result = query!(
+        r#"
+        INSERT INTO
+            team_members
+        (
+            team_id,
+            user_id
+        )
+        VALUES
+        (
+            $1,
+            $2
+        )
+        ON CONFLICT DO NOTHING
+        "#,
+        body.team_id,
+        body.user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::CREATED),
+        _ => Ok(StatusCode::OK),
+    }
+}
+
+pub async fn delete(
+    _: Require<TeamEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<TeamMember>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_team_scope(&mut conn, &user, body.team_id).await?;
+
+    let result = query!(
+        r#"
+        DELETE FROM
+            team_members
+        WHERE
+            team_id = $1
+        AND
+            user_id = $2
+        "#,
+        body.team_id,
+        body.user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
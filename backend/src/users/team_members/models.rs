@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct TeamMember {
+    pub team_id: Uuid,
+    pub user_id: Uuid,
+}
@@ -0,0 +1,279 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, query_scalar, PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{Require, TeamCreate, TeamDelete, TeamEdit, TeamView},
+    field_vec,
+    machines::facilities::Facility,
+    update_field,
+    users::models::{ShortUser, User},
+    utils::{
+        db::{Field, IntoField},
+        errors::{ApiError, ForbiddenReason, InputInvalidReason},
+    },
+    AppState,
+};
+
+use super::models::{NewTeam, QueryTeam, QueryTeams, Team, UpdateTeam};
+
+// Same facility rule as machines, a team without a facility needs a global role
+
+pub async fn check_team_scope(
+    conn: &mut PgConnection,
+    user: &User,
+    team_id: Uuid,
+) -> Result<(), ApiError> {
+    if user.facility_scope().is_none() {
+        return Ok(());
+    }
+
+    let facility = query_scalar!(r#"SELECT facility FROM teams WHERE id = $1"#, team_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    user.check_facility(facility)
+}
+
+async fn fetch_teams(
+    conn: &mut PgConnection,
+    team_id: Option<Uuid>,
+    member_id: Option<Uuid>,
+    scope: Option<&[Uuid]>,
+) -> Result<Vec<Team>, ApiError> {
+    let teams = query_as!(
+        Team,
+        r#"
+        SELECT
+            t.id,
+            t.name,
+            t.description,
+            (
+                f.id,
+                f.name,
+                f.address
+            ) AS "facility?: Facility",
+            (
+                SELECT array_agg(
+                    (
+                        u.id,
+                        u.first_name,
+                        u.last_name,
+                        u.email,
+                        u.image
+                    )
+                    ORDER BY u.last_name, u.first_name
+                )
+                FROM
+                    team_members tm
+                INNER JOIN
+                    users u
+                ON
+                    tm.user_id = u.id
+                WHERE
+                    tm.team_id = t.id
+            ) AS "members: Vec<ShortUser>",
+            t.created
+        FROM
+            teams t
+        LEFT JOIN
+            facilities f
+        ON
+            t.facility = f.id
+        WHERE
+            ($1::UUID IS NULL OR t.id = $1)
+        AND
+            ($2::UUID IS NULL OR t.id IN (
+                SELECT tm.team_id FROM team_members tm WHERE tm.user_id = $2
+            ))
+        AND
+            ($3::UUID[] IS NULL OR t.facility = ANY($3))
+        ORDER BY
+            t.name
+        "#,
+        team_id,
+        member_id,
+        scope
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(teams)
+}
+
+pub async fn details(
+    _: Require<TeamView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryTeam>,
+) -> Result<Json<Team>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_team_scope(&mut conn, &user, params.id).await?;
+
+    let team = fetch_teams(&mut conn, Some(params.id), None, None)
+        .await?
+        .pop()
+        .ok_or(ApiError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    Ok(Json(team))
+}
+
+// Your own teams can be listed without team.view
+
+pub async fn index(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryTeams>,
+) -> Result<Json<Vec<Team>>, ApiError> {
+    let is_own = params.member_id == Some(user.id);
+
+    if !user.role.has::<TeamView>() && !is_own {
+        return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+    }
+
+    let scope = match is_own {
+        true => None,
+        false => user.facility_scope(),
+    };
+
+    let mut conn = app_state.db.acquire().await?;
+
+    let teams = fetch_teams(&mut conn, None, params.member_id, scope).await?;
+
+    Ok(Json(teams))
+}
+
+pub async fn create(
+    _: Require<TeamCreate>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewTeam>,
+) -> Result<(StatusCode, Json<Team>), ApiError> {
+    user.check_facility(body.facility)?;
+
+    let mut tx = app_state.db.begin().await?;
+
+    let team_id = query_scalar!(
+        r#"
+        INSERT INTO
+            teams
+        (
+            name,
+            description,
+            facility
+        )
+        VALUES
+        (
+            $1,
+            $2,
+            $3
+        )
+        RETURNING
+            id
+        "#,
+        body.name,
+        body.description,
+        body.facility
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if let Some(members) = body.members {
+        query!(
+            r#"
+            INSERT INTO
+                team_members
+            (
+                team_id,
+                user_id
+            )
+            SELECT
+                $1,
+                unnest($2::UUID[])
+            ON CONFLICT DO NOTHING
+            "#,
+            team_id,
+            &members
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let team = fetch_teams(&mut tx, Some(team_id), None, None)
+        .await?
+        .pop()
+        .ok_or(ApiError::GeneralOversight(
+            "Created team wasn't found".to_owned(),
+        ))?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(team)))
+}
+
+pub async fn update(
+    _: Require<TeamEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateTeam>,
+) -> Result<StatusCode, ApiError> {
+    user.check_facility_change(&body.facility)?;
+
+    let mut conn = app_state.db.acquire().await?;
+
+    check_team_scope(&mut conn, &user, body.id).await?;
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("UPDATE teams SET");
+    let mut separated_list = query_builder.separated(",");
+
+    let fields = field_vec![
+        name => body.name,
+        description => body.description,
+        facility => body.facility
+    ];
+
+    if fields.is_empty() {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NoFieldsToUpdate));
+    }
+
+    for (field, value) in fields {
+        update_field!(separated_list, field, value);
+    }
+
+    query_builder.push(" WHERE id = ");
+    query_builder.push_bind(body.id);
+
+    let result = query_builder.build().execute(&mut *conn).await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn delete(
+    _: Require<TeamDelete>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryTeam>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_team_scope(&mut conn, &user, params.id).await?;
+
+    let result = query!(r#"DELETE FROM teams WHERE id = $1"#, params.id)
+        .execute(&mut *conn)
+        .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
+use uuid::Uuid;
+
+use crate::{machines::facilities::Facility, users::models::ShortUser, utils::db::Nullable};
+
+#[derive(Serialize)]
+pub struct Team {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub facility: Option<Facility>,
+    pub members: Option<Vec<ShortUser>>,
+    pub created: DateTime<Utc>,
+}
+
+// Short variant
+
+#[derive(Type, Serialize, Debug, Clone)]
+pub struct ShortTeam {
+    pub id: Option<Uuid>,
+    pub name: Option<String>,
+}
+
+// Details
+
+#[derive(Deserialize)]
+pub struct QueryTeam {
+    pub id: Uuid,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryTeams {
+    pub member_id: Option<Uuid>,
+}
+
+// Create
+
+#[derive(Deserialize)]
+pub struct NewTeam {
+    pub name: String,
+    pub description: Option<String>,
+    pub facility: Option<Uuid>,
+    pub members: Option<Vec<Uuid>>,
+}
+
+// Update
+
+#[derive(Deserialize)]
+pub struct UpdateTeam {
+    pub id: Uuid,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Nullable<String>,
+    #[serde(default)]
+    pub facility: Nullable<Uuid>,
+}
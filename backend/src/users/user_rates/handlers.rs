@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, query_scalar, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{CostEdit, CostView, Require},
+    users::models::{ShortUser, User},
+    utils::errors::{ApiError, InputInvalidReason},
+    AppState,
+};
+
+use super::models::{DeleteUserRate, UpdateUserRate, UserRate};
+
+// Rates follow the facility of the user they belong to
+
+async fn check_user_rate_scope(
+    conn: &mut PgConnection,
+    user: &User,
+    user_id: Uuid,
+) -> Result<(), ApiError> {
+    if user.facility_scope().is_none() {
+        return Ok(());
+    }
+
+    let facility = query_scalar!(r#"SELECT facility FROM users WHERE id = $1"#, user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    user.check_facility(facility)
+}
+
+pub async fn index(
+    _: Require<CostView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<UserRate>>, ApiError> {
+    let rates = query_as!(
+        UserRate,
+        r#"
+        SELECT
+            (
+                u.id,
+                u.first_name,
+                u.last_name,
+                u.email,
+                u.image
+            ) AS "user!: ShortUser",
+            ur.hourly_rate,
+            ur.edited
+        FROM
+            user_rates ur
+        INNER JOIN
+            users u
+        ON
+            ur.user_id = u.id
+        WHERE
+            ($1::UUID[] IS NULL OR u.facility = ANY($1))
+        ORDER BY
+            u.last_name,
+            u.first_name
+        "#,
+        user.facility_scope()
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(rates))
+}
+
+pub async fn update(
+    _: Require<CostEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateUserRate>,
+) -> Result<StatusCode, ApiError> {
+    if body.hourly_rate < 0 {
+        return Err(ApiError::InputInvalid(InputInvalidReason::NegativeAmount));
+    }
+
+    let mut conn = app_state.db.acquire().await?;
+
+    check_user_rate_scope(&mut conn, &user, body.user_id).await?;
+
+    query!(
+        r#"
+        INSERT INTO
+            user_rates
+        (
+            user_id,
+            hourly_rate
+        )
+        VALUES
+        (
+            $1,
+            $2
+        )
+        ON CONFLICT (user_id) DO UPDATE SET
+            hourly_rate = EXCLUDED.hourly_rate
+        "#,
+        body.user_id,
+        body.hourly_rate
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn delete(
+    _: Require<CostEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeleteUserRate>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_user_rate_scope(&mut conn, &user, params.user_id).await?;
+
+    let result = query!(
+        r#"DELETE FROM user_rates WHERE user_id = $1"#,
+        params.user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
@@ -0,0 +1,6 @@
+pub mod handlers;
+pub mod models;
+
+pub use handlers::delete;
+pub use handlers::index;
+pub use handlers::update;
@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::users::models::ShortUser;
+
+// Hourly rates are in cents
+
+#[derive(Serialize)]
+pub struct UserRate {
+    pub user: ShortUser,
+    pub hourly_rate: i32,
+    pub edited: DateTime<Utc>,
+}
+
+// Update, sets the rate new time entries are costed at
+
+#[derive(Deserialize)]
+pub struct UpdateUserRate {
+    pub user_id: Uuid,
+    pub hourly_rate: i32,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteUserRate {
+    pub user_id: Uuid,
+}
@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use sqlx::{query, query_as, query_scalar, PgConnection};
+use uuid::Uuid;
+
+use crate::{
+    auth::permissions::{Require, SkillEdit, SkillView},
+    users::models::{ShortUser, User},
+    utils::errors::{ApiError, ForbiddenReason, InputInvalidReason},
+    AppState,
+};
+
+use super::models::{
+    DeleteUserSkill, ExpiringSkill, NewUserSkill, QueryExpiring, QueryUserSkills, UserSkill,
+};
+
+// Skills follow the facility of the user holding them
+
+async fn check_user_skill_scope(
+    conn: &mut PgConnection,
+    user: &User,
+    user_id: Uuid,
+) -> Result<(), ApiError> {
+    if user.facility_scope().is_none() {
+        return Ok(());
+    }
+
+    let facility = query_scalar!(r#"SELECT facility FROM users WHERE id = $1"#, user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    user.check_facility(facility)
+}
+
+// Your own skills can be listed without skill.view
+
+pub async fn index(
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryUserSkills>,
+) -> Result<Json<Vec<UserSkill>>, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    if params.user_id != user.id {
+        if !user.role.has::<SkillView>() {
+            return Err(ApiError::Forbidden(ForbiddenReason::MissingPermission));
+        }
+
+        check_user_skill_scope(&mut conn, &user, params.user_id).await?;
+    }
+
+    let skills = query_as!(
+        UserSkill,
+        r#"
+        SELECT
+            us.user_id,
+            us.skill_id,
+            s.name,
+            s.certification,
+            us.obtained_at,
+            us.expires_at,
+            us.reference,
+            COALESCE(us.expires_at < CURRENT_DATE, FALSE) AS "expired!"
+        FROM
+            user_skills us
+        INNER JOIN
+            skills s
+        ON
+            us.skill_id = s.id
+        WHERE
+            us.user_id = $1
+        ORDER BY
+            s.name
+        "#,
+        params.user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(Json(skills))
+}
+
+pub async fn create(
+    _: Require<SkillEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<NewUserSkill>,
+) -> Result<(StatusCode, Json<UserSkill>), ApiError> {
+    if let (Some(obtained_at), Some(expires_at)) = (body.obtained_at, body.expires_at) {
+        if expires_at <= obtained_at {
+            return Err(ApiError::InputInvalid(InputInvalidReason::InvalidTimeRange));
+        }
+    }
+
+    let mut conn = app_state.db.acquire().await?;
+
+    check_user_skill_scope(&mut conn, &user, body.user_id).await?;
+
+    let skill = query_as!(
+        UserSkill,
+        r#"
+        WITH upserted AS (
+            INSERT INTO
+                user_skills
+            (
+                user_id,
+                skill_id,
+                obtained_at,
+                expires_at,
+                reference
+            )
+            VALUES
+            (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5
+            )
+            ON CONFLICT (user_id, skill_id) DO UPDATE SET
+                obtained_at = EXCLUDED.obtained_at,
+                expires_at = EXCLUDED.expires_at,
+                reference = EXCLUDED.reference
+            RETURNING
+                *
+        )
+        SELECT
+            us.user_id,
+            us.skill_id,
+            s.name,
+            s.certification,
+            us.obtained_at,
+            us.expires_at,
+            us.reference,
+            COALESCE(us.expires_at < CURRENT_DATE, FALSE) AS "expired!"
+        FROM
+            upserted us
+        INNER JOIN
+            skills s
+        ON
+            us.skill_id = s.id
+        "#,
+        body.user_id,
+        body.skill_id,
+        body.obtained_at,
+        body.expires_at,
+        body.reference
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(skill)))
+}
+
+pub async fn delete(
+    _: Require<SkillEdit>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<DeleteUserSkill>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = app_state.db.acquire().await?;
+
+    check_user_skill_scope(&mut conn, &user, body.user_id).await?;
+
+    let result = query!(
+        r#"
+        DELETE FROM
+            user_skills
+        WHERE
+            user_id = $1
+        AND
+            skill_id = $2
+        "#,
+        body.user_id,
+        body.skill_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    match result.rows_affected() {
+        1 => Ok(StatusCode::NO_CONTENT),
+        _ => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+// Certifications of active users running out within the next days, expired ones included
+// so nobody drops off the report before being renewed
+
+pub async fn expiring(
+    _: Require<SkillView>,
+    Extension(user): Extension<User>,
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QueryExpiring>,
+) -> Result<Json<Vec<ExpiringSkill>>, ApiError> {
+    let skills = query_as!(
+        ExpiringSkill,
+        r#"
+        SELECT
+            (
+                u.id,
+                u.first_name,
+                u.last_name,
+                u.email,
+                u.image
+            ) AS "user!: ShortUser",
+            us.skill_id,
+            s.name,
+            us.expires_at AS "expires_at!",
+            us.expires_at - CURRENT_DATE AS "days_left!"
+        FROM
+            user_skills us
+        INNER JOIN
+            skills s
+        ON
+            us.skill_id = s.id
+        INNER JOIN
+            users u
+        ON
+            us.user_id = u.id
+        WHERE
+            u.active
+        AND
+            us.expires_at < CURRENT_DATE + $1::INT
+        AND
+            ($2::UUID[] IS NULL OR u.facility = ANY($2))
+        ORDER BY
+            us.expires_at,
+            u.last_name,
+            u.first_name
+        "#,
+        params.days.unwrap_or(30),
+        user.facility_scope()
+    )
+    .fetch_all(&app_state.db)
+    .await?;
+
+    Ok(Json(skills))
+}
@@ -0,0 +1,59 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::users::models::ShortUser;
+
+#[derive(Serialize)]
+pub struct UserSkill {
+    pub user_id: Uuid,
+    pub skill_id: Uuid,
+    pub name: String,
+    pub certification: bool,
+    pub obtained_at: Option<NaiveDate>,
+    pub expires_at: Option<NaiveDate>,
+    pub reference: Option<String>,
+    pub expired: bool,
+}
+
+// Index
+
+#[derive(Deserialize)]
+pub struct QueryUserSkills {
+    pub user_id: Uuid,
+}
+
+// Create, adding a skill the user already holds replaces its dates
+
+#[derive(Deserialize)]
+pub struct NewUserSkill {
+    pub user_id: Uuid,
+    pub skill_id: Uuid,
+    pub obtained_at: Option<NaiveDate>,
+    pub expires_at: Option<NaiveDate>,
+    pub reference: Option<String>,
+}
+
+// Delete
+
+#[derive(Deserialize)]
+pub struct DeleteUserSkill {
+    pub user_id: Uuid,
+    pub skill_id: Uuid,
+}
+
+// Expiry report
+
+#[derive(Deserialize)]
+pub struct QueryExpiring {
+    pub days: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct ExpiringSkill {
+    pub user: ShortUser,
+    pub skill_id: Uuid,
+    pub name: String,
+    pub expires_at: NaiveDate,
+    pub days_left: i32,
+}
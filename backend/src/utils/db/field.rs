@@ -7,6 +7,7 @@ use super::Nullable;
 pub enum Field {
     Str(Option<String>),
     Int(Option<i32>),
+    Float(Option<f64>),
     Bool(Option<bool>),
     Uuid(Option<Uuid>),
     DateTime(Option<DateTime<Utc>>),
@@ -29,6 +30,12 @@ impl IntoField for i32 {
     }
 }
 
+impl IntoField for f64 {
+    fn into_field(self) -> Field {
+        Field::Float(Some(self))
+    }
+}
+
 impl IntoField for bool {
     fn into_field(self) -> Field {
         Field::Bool(Some(self))
@@ -84,6 +91,12 @@ impl NullableIntoField for i32 {
     }
 }
 
+impl NullableIntoField for f64 {
+    fn into_field_none() -> Field {
+        Field::Float(None)
+    }
+}
+
 impl NullableIntoField for bool {
     fn into_field_none() -> Field {
         Field::Bool(None)
@@ -1,12 +1,17 @@
 use argon2::password_hash::Error as Argon2Error;
 use axum::{
     body::Body,
+    extract::multipart::MultipartError,
     http::{Response, StatusCode},
     response::IntoResponse,
     Json,
 };
+use csv::Error as CsvError;
+use image::ImageError;
 use jsonwebtoken::errors::Error as JWTError;
+use reqwest::Error as ReqwestError;
 use sqlx::Error as SqlxError;
+use std::io::{Error as IoError, ErrorKind};
 use tracing::{error, warn};
 use uuid::Error as UuidError;
 use validator::ValidationErrors as ValidationError;
@@ -22,6 +27,11 @@ pub enum ApiError {
     ValidationError(ValidationError),
     InvalidToken(JWTError),
     DatabaseError(SqlxError),
+    ProviderError(ReqwestError),
+    CsvError(CsvError),
+    ImageError(ImageError),
+    MultipartError(MultipartError),
+    StorageError(IoError),
     GeneralOversight(String),
 }
 
@@ -31,17 +41,57 @@ pub enum ForbiddenReason {
     AccountDeactivated,
     IncorrectPassword,
     IncorrectCode,
+    ServiceAccount,
+    ApiKeyRevoked,
+    ApiKeyExpired,
+    SsoDisabled,
+    InvalidLoginState,
+    EmailNotVerified,
+    SsoRoleUnusable,
+    UnknownAccount,
+    OutsideFacility,
+    Impersonating,
+    InvitationInvalid,
 }
 
 #[derive(Debug)]
 pub enum InputInvalidReason {
     NoPasswordSupplied,
     NoFieldsToUpdate,
+    NotServiceAccount,
+    UnknownPermission,
+    AlreadyActive,
+    PreviouslyActive,
+    NoImageSupplied,
+    UnsupportedImage,
+    InvalidLanguage,
+    InvalidTimeRange,
+    MissingSkills,
+    ChecklistStepIncomplete,
+    InvalidThresholds,
+    MeasurementMismatch,
+    AmbiguousCounterUpdate,
+    NotTaskExecutor,
+    IncompatiblePart,
+    MissingFacility,
+    InvalidQuantity,
+    NegativeAmount,
+    MachineCycle,
+    MachineFacilityMismatch,
+    LocationOutsideFacility,
+    LocationCycle,
+    LocationWithoutFacility,
 }
 
 #[derive(Debug)]
 pub enum ConflictReason {
     EmailTaken,
+    PhoneTaken,
+    ChecklistIncomplete,
+    TimerRunning,
+    InsufficientStock,
+    InvalidStatusTransition,
+    LocationInUse,
 }
 
 impl From<UuidError> for ApiError {
@@ -74,6 +124,36 @@ impl From<SqlxError> for ApiError {
     }
 }
 
+impl From<ReqwestError> for ApiError {
+    fn from(err: ReqwestError) -> Self {
+        Self::ProviderError(err)
+    }
+}
+
+impl From<CsvError> for ApiError {
+    fn from(err: CsvError) -> Self {
+        Self::CsvError(err)
+    }
+}
+
+impl From<ImageError> for ApiError {
+    fn from(err: ImageError) -> Self {
+        Self::ImageError(err)
+    }
+}
+
+impl From<MultipartError> for ApiError {
+    fn from(err: MultipartError) -> Self {
+        Self::MultipartError(err)
+    }
+}
+
+impl From<IoError> for ApiError {
+    fn from(err: IoError) -> Self {
+        Self::StorageError(err)
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response<Body> {
         let error_message = format!("{:?}", self);
@@ -83,12 +163,73 @@ impl IntoResponse for ApiError {
                 let message = match reason {
                     InputInvalidReason::NoPasswordSupplied => "No password supplied",
                     InputInvalidReason::NoFieldsToUpdate => "No fields to update provided",
+                    InputInvalidReason::NotServiceAccount => "User is not a service account",
+                    InputInvalidReason::UnknownPermission => "Unknown permission",
+                    InputInvalidReason::AlreadyActive => "User has already been activated",
+                    InputInvalidReason::PreviouslyActive => {
+                        "User has been active before, reactivate them instead"
+                    }
+                    InputInvalidReason::NoImageSupplied => "No image supplied",
+                    InputInvalidReason::UnsupportedImage => "Unsupported image type",
+                    InputInvalidReason::InvalidLanguage => "Invalid language code",
+                    InputInvalidReason::InvalidTimeRange => "The end has to be after the start",
+                    InputInvalidReason::MissingSkills => {
+                        "The user lacks a skill or certification the task requires"
+                    }
+                    InputInvalidReason::ChecklistStepIncomplete => {
+                        "This step requires a photo or a measurement"
+                    }
+                    InputInvalidReason::InvalidThresholds => {
+                        "The minimum can't be above the maximum"
+                    }
+                    InputInvalidReason::MeasurementMismatch => {
+                        "The measurement doesn't belong to this machine or task"
+                    }
+                    InputInvalidReason::AmbiguousCounterUpdate => {
+                        "Either set the counter value or increment it, not both"
+                    }
+                    InputInvalidReason::NotTaskExecutor => "The user isn't assigned to this task",
+                    InputInvalidReason::IncompatiblePart => {
+                        "The part doesn't fit the machine of the task"
+                    }
+                    InputInvalidReason::MissingFacility => {
+                        "A facility is needed to take the parts from"
+                    }
+                    InputInvalidReason::InvalidQuantity => "Invalid quantity for this stock change",
+                    InputInvalidReason::NegativeAmount => "Costs and rates can't be negative",
+                    InputInvalidReason::MachineCycle => {
+                        "A machine can't be placed below itself or one of its components"
+                    }
+                    InputInvalidReason::MachineFacilityMismatch => {
+                        "A machine has to be in the same facility as its parent and its components"
+                    }
+                    InputInvalidReason::LocationOutsideFacility => {
+                        "The location isn't part of this facility"
+                    }
+                    InputInvalidReason::LocationCycle => {
+                        "A location can't be placed inside itself or one of its sub-locations"
+                    }
+                    InputInvalidReason::LocationWithoutFacility => {
+                        "A location needs a facility or a parent location"
+                    }
                 };
                 (StatusCode::BAD_REQUEST, message)
             }
             Self::Conflict(reason) => {
                 let message = match reason {
                     ConflictReason::EmailTaken => "This email is already taken",
+                    ConflictReason::PhoneTaken => "This phone number is already taken",
+                    ConflictReason::ChecklistIncomplete => {
+                        "The mandatory checklist steps aren't completed yet"
+                    }
+                    ConflictReason::TimerRunning => "A timer is already running, stop it first",
+                    ConflictReason::InsufficientStock => "There isn't enough of this part in stock",
+                    ConflictReason::InvalidStatusTransition => {
+                        "The purchase request can't move to this status"
+                    }
+                    ConflictReason::LocationInUse => {
+                        "Machines are still placed at this location, move them first"
+                    }
                 };
                 (StatusCode::CONFLICT, message)
             }
@@ -100,9 +241,13 @@ impl IntoResponse for ApiError {
                 error!(error_message);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
             }
-            Self::ValidationError(_) => {
+            Self::ValidationError(errors) => {
                 error!(error_message);
-                (StatusCode::BAD_REQUEST, "Invalid email")
+                let message = match errors.field_errors().contains_key("password") {
+                    true => "Password must be between 1 and 255 characters",
+                    false => "Invalid email",
+                };
+                (StatusCode::BAD_REQUEST, message)
             }
             Self::Forbidden(reason) => {
                 let message = match reason {
@@ -110,6 +255,21 @@ impl IntoResponse for ApiError {
                     ForbiddenReason::AccountDeactivated => "Your account has been deactivated",
                     ForbiddenReason::IncorrectPassword => "Incorrect password",
                     ForbiddenReason::IncorrectCode => "Incorrect code",
+                    ForbiddenReason::ServiceAccount => "Service accounts can't log in",
+                    ForbiddenReason::ApiKeyRevoked => "This api key has been revoked",
+                    ForbiddenReason::ApiKeyExpired => "This api key has expired",
+                    ForbiddenReason::SsoDisabled => "Single sign-on is not enabled",
+                    ForbiddenReason::InvalidLoginState => "Invalid login state",
+                    ForbiddenReason::EmailNotVerified => "Your email is not verified",
+                    ForbiddenReason::SsoRoleUnusable => {
+                        "Accounts can't be created through single sign-on right now"
+                    }
+                    ForbiddenReason::UnknownAccount => "No account exists for this email",
+                    ForbiddenReason::OutsideFacility => "This is outside of your facilities",
+                    ForbiddenReason::Impersonating => {
+                        "This can't be done while impersonating a user"
+                    }
+                    ForbiddenReason::InvitationInvalid => "This invitation is no longer valid",
                 };
                 (StatusCode::FORBIDDEN, message)
             }
@@ -122,6 +282,50 @@ impl IntoResponse for ApiError {
                 error!(error_message);
                 (StatusCode::UNAUTHORIZED, "Invalid token")
             }
+            Self::ProviderError(error) => {
+                error!(error_message);
+                match error.status() {
+                    Some(status) if status.is_client_error() => (
+                        StatusCode::UNAUTHORIZED,
+                        "Identity provider rejected the login",
+                    ),
+                    _ => (StatusCode::BAD_GATEWAY, "Identity provider unavailable"),
+                }
+            }
+            Self::CsvError(error) => match error.is_io_error() {
+                true => {
+                    error!(error_message);
+                    (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+                }
+                false => {
+                    warn!(error_message);
+                    (StatusCode::BAD_REQUEST, "Invalid CSV")
+                }
+            },
+            Self::ImageError(error) => match error {
+                ImageError::Decoding(_) | ImageError::Limits(_) | ImageError::Unsupported(_) => {
+                    warn!(error_message);
+                    (StatusCode::BAD_REQUEST, "Invalid image")
+                }
+                _ => {
+                    error!(error_message);
+                    (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+                }
+            },
+            Self::MultipartError(error) => {
+                warn!(error_message);
+                (error.status(), "Invalid upload")
+            }
+            Self::StorageError(error) => match error.kind() {
+                ErrorKind::NotFound => {
+                    warn!(error_message);
+                    (StatusCode::NOT_FOUND, "Not found")
+                }
+                _ => {
+                    error!(error_message);
+                    (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+                }
+            },
             Self::DatabaseError(error) => match error {
                 SqlxError::RowNotFound => {
                     warn!(error_message);
@@ -12,6 +12,11 @@ macro_rules! update_field {
                     .push(format!(" {} = ", $field))
                     .push_bind_unseparated(val);
             }
+            Field::Float(val) => {
+                $query_builder
+                    .push(format!(" {} = ", $field))
+                    .push_bind_unseparated(val);
+            }
             Field::Bool(val) => {
                 $query_builder
                     .push(format!(" {} = ", $field))
@@ -53,6 +58,9 @@ macro_rules! insert_fields {
                 Field::Int(val) => {
                     value_list.push_bind(val);
                 }
+                Field::Float(val) => {
+                    value_list.push_bind(val);
+                }
                 Field::Bool(val) => {
                     value_list.push_bind(val);
                 }
@@ -110,26 +118,8 @@ macro_rules! user_from_id {
                         r.name,
                         r.level,
                         r.has_password,
-                        r.user_view,
-                        r.user_create,
-                        r.user_edit,
-                        r.user_delete,
-                        r.machine_view,
-                        r.machine_create,
-                        r.machine_edit,
-                        r.machine_delete,
-                        r.task_view,
-                        r.task_create,
-                        r.task_edit,
-                        r.task_delete,
-                        r.report_view,
-                        r.report_create,
-                        r.report_edit,
-                        r.report_delete,
-                        r.facility_view,
-                        r.facility_create,
-                        r.facility_edit,
-                        r.facility_delete
+                        r.global_access,
+                        role_permission_keys(r.id)
                     ) AS "role!: Role",
                     u.active,
                     u.last_login,
@@ -139,7 +129,10 @@ macro_rules! user_from_id {
                         f.id,
                         f.name,
                         f.address
-                    ) AS "facility?: Facility"
+                    ) AS "facility?: Facility",
+                    u.location,
+                    user_facility_ids(u.id) AS "facilities!",
+                    u.service_account
                 FROM
                     users u
                 INNER JOIN
@@ -1,8 +1,7 @@
 pub mod errors;
+pub mod notifications;
+pub mod storage;
 pub mod tracing;
 #[macro_use]
 pub mod macros;
 pub mod db;
-pub mod misc;
-
-pub use misc::check_permission;
@@ -0,0 +1,98 @@
+use sqlx::{query, query_scalar, PgConnection};
+use tracing::debug;
+use uuid::Uuid;
+
+use super::errors::ApiError;
+
+// Tells the users they were assigned to the task, unless they turned it off on their profile
+
+pub async fn notify_task_assigned(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    user_ids: &[Uuid],
+) -> Result<(), ApiError> {
+    let recipients = query_scalar!(
+        r#"
+        SELECT
+            u.id
+        FROM
+            users u
+        WHERE
+            u.id = ANY($1)
+        AND
+            u.active
+        AND
+            u.notify_task_assigned
+        "#,
+        user_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for recipient in recipients {
+        debug!(task = %task_id, recipient = %recipient, "Task assigned"); // REPLACE WITH "EMAIL TO" IMPLEMENTATION
+    }
+
+    Ok(())
+}
+
+// Tells the users who manage parts in the facility that the available stock dropped to its minimum
+
+pub async fn notify_low_stock(
+    conn: &mut PgConnection,
+    part_id: Uuid,
+    facility_id: Uuid,
+) -> Result<(), ApiError> {
+    let recipients = query!(
+        r#"
+        SELECT
+            u.id,
+            ps.on_hand - ps.reserved AS "available!"
+        FROM
+            part_stock ps
+        CROSS JOIN
+            users u
+        INNER JOIN
+            roles r
+        ON
+            u.role = r.id
+        WHERE
+            ps.part_id = $1
+        AND
+            ps.facility_id = $2
+        AND
+            u.active
+        AND
+            (r.global_access OR ps.facility_id = ANY(user_facility_ids(u.id)))
+        AND
+            EXISTS (
+                SELECT
+                    1
+                FROM
+                    role_permissions rp
+                WHERE
+                    rp.role_id = r.id
+                AND
+                    rp.resource = 'part'
+                AND
+                    rp.action = 'edit'
+            )
+        "#,
+        part_id,
+        facility_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for recipient in recipients {
+        debug!(
+            part = %part_id,
+            facility = %facility_id,
+            available = recipient.available,
+            recipient = %recipient.id,
+            "Low stock"
+        ); // REPLACE WITH "EMAIL TO" IMPLEMENTATION
+    }
+
+    Ok(())
+}
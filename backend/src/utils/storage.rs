@@ -0,0 +1,65 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+// Files are addressed by keys like "users/<id>.jpg", everything below UPLOAD_PATH
+
+#[derive(Clone)]
+pub struct Storage {
+    root: PathBuf,
+}
+
+impl Storage {
+    pub fn init(upload_path: &str) -> Storage {
+        std::fs::create_dir_all(upload_path).expect("Can't create upload directory");
+
+        Storage {
+            root: PathBuf::from(upload_path),
+        }
+    }
+
+    // Keys only ever come from our own handlers, but path segments are still checked
+    // so a key taken from a request can't climb out of the upload directory
+
+    fn path(&self, key: &str) -> std::io::Result<PathBuf> {
+        let valid = !key.is_empty()
+            && key.split('/').all(|segment| {
+                !segment.is_empty()
+                    && !segment.starts_with('.')
+                    && segment
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            });
+
+        match valid {
+            true => Ok(self.root.join(Path::new(key))),
+            false => Err(std::io::Error::new(ErrorKind::NotFound, "Invalid key")),
+        }
+    }
+
+    pub async fn put(&self, key: &str, bytes: &[u8]) -> std::io::Result<()> {
+        let path = self.path(key)?;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Written next to the target first so readers never see a half written file
+
+        let temp_path = path.with_extension("part");
+        tokio::fs::write(&temp_path, bytes).await?;
+        tokio::fs::rename(&temp_path, &path).await
+    }
+
+    pub async fn get(&self, key: &str) -> std::io::Result<Vec<u8>> {
+        tokio::fs::read(self.path(key)?).await
+    }
+
+    pub async fn delete(&self, key: &str) -> std::io::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
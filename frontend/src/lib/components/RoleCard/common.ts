@@ -18,26 +18,8 @@ export const form = writable({
     name: '',
     level: '',
     has_password: true,
-    user_view: false,
-    user_create: false,
-    user_edit: false,
-    user_delete: false,
-    machine_view: false,
-    machine_create: false,
-    machine_edit: false,
-    machine_delete: false,
-    task_view: false,
-    task_create: false,
-    task_edit: false,
-    task_delete: false,
-    report_view: false,
-    report_create: false,
-    report_edit: false,
-    report_delete: false,
-    facility_view: false,
-    facility_create: false,
-    facility_edit: false,
-    facility_delete: false,
+    global_access: false,
+    permissions: [],
 });
 
 export function clearFields() {
@@ -46,10 +28,8 @@ export function clearFields() {
         formState.name = '';
         formState.level = '';
         formState.has_password = true;
-        for (const field in formState) {
-            if (field !== 'id' && field !== 'name' && field !== 'level' && field !== 'has_password')
-            formState[field] = false;
-        }
+        formState.global_access = false;
+        formState.permissions = [];
         return formState;
     });
 }
@@ -9,7 +9,7 @@ export const isCreating = writable(false);
 export const isEditing = writable(false);
 export const isViewing = writable(false);
 
-export const fieldErrors = writable({ title: '', description: '', task_type: '', status: '', archived: '', executors:'', machine:'', due_at:''});
+export const fieldErrors = writable({ title: '', description: '', task_type: '', status: '', archived: '', executors:'', teams:'', machine:'', due_at:''});
 export const hasErrors = writable(false);
 
 export const id = writable(null);
@@ -23,6 +23,7 @@ export const form = writable({
     archived: false,
     creator: '',
     executors: [],
+    teams: [],
     machine: '',
     created: '',
     edited: '',
@@ -53,6 +54,7 @@ export function loadFields() {
             formValue.archived = value?.archived;
             formValue.creator = value?.creator;
             formValue.executors = value?.executors instanceof Array ? value?.executors?.map(exec => exec?.id) : [];
+            formValue.teams = value?.teams instanceof Array ? value?.teams?.map(team => team?.id) : [];
             formValue.machine = value?.machine?.id;
             formValue.created = new Date(value?.created)?.toLocaleString();
             formValue.edited = new Date(value?.edited)?.toLocaleString();
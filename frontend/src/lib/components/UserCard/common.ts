@@ -25,6 +25,7 @@ export const form = writable({
     last_login: '',
     occupation: '',
     facility: '',
+    invite: '',
 });
 
 export function clearFields() {
@@ -23,9 +23,13 @@ export const facility = writable({});
 export const users = writable([]);
 export const user = writable({});
 
+export const teams = writable([]);
+
 export const roles = writable([]);
 export const role = writable({});
 
+export const permissions = writable([]);
+
 export const reports = writable([]);
 export const report = writable({});
 
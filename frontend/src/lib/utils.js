@@ -15,7 +15,9 @@ import {
     reportTypes,
     user,
     users,
+    teams,
     roles,
+    permissions,
 } from '$stores';
 
 export async function sendJSON(url, method, body) {
@@ -32,6 +34,12 @@ export async function sendDelete(url) {
     return fetch(url, { method: 'DELETE' });
 }
 
+export async function uploadImage(url, file) {
+    const body = new FormData();
+    body.append('image', file);
+    return fetch(url, { method: 'POST', body: body });
+}
+
 export async function getLoggedIn() {
     try {
         const response = await fetch('/api/auth/me');
@@ -45,6 +53,16 @@ export async function getLoggedIn() {
     }
 }
 
+export async function startImpersonation(userId) {
+    const response = await sendJSON('/api/auth/impersonation', 'POST', { user_id: userId });
+    return response.status === 201;
+}
+
+export async function stopImpersonation() {
+    const response = await sendDelete('/api/auth/impersonation');
+    return response.status === 204;
+}
+
 export async function fetchJson(url) {
     try {
         const response = await fetch(url);
@@ -133,11 +151,21 @@ export async function getUser(id) {
     user.set(data ?? {});
 }
 
+export async function getTeams() {
+    const data = await fetchJson('/api/auth/teams');
+    teams.set(data ?? []);
+}
+
 export async function getRoles() {
     const data = await fetchJson('/api/auth/roles');
     roles.set(data ?? []);
 }
 
+export async function getPermissions() {
+    const data = await fetchJson('/api/auth/permissions');
+    permissions.set(data ?? []);
+}
+
 export async function getMyReports(id) {
     const data = await fetchJson(`/api/auth/report?creator_id=${id}`);
     reports.set(data ?? []);
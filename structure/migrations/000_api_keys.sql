@@ -0,0 +1,42 @@
+-- ADDS SERVICE ACCOUNTS AND THEIR API KEYS
+-- Has to run before 002, which moves the permission columns of api_keys into the registry
+
+BEGIN;
+
+ALTER TABLE users ADD COLUMN service_account BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    prefix VARCHAR(16) NOT NULL UNIQUE,
+    hash VARCHAR(255) NOT NULL,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    last_used TIMESTAMPTZ,
+    user_view BOOLEAN NOT NULL DEFAULT FALSE,
+    user_create BOOLEAN NOT NULL DEFAULT FALSE,
+    user_edit BOOLEAN NOT NULL DEFAULT FALSE,
+    user_delete BOOLEAN NOT NULL DEFAULT FALSE,
+    machine_view BOOLEAN NOT NULL DEFAULT FALSE,
+    machine_create BOOLEAN NOT NULL DEFAULT FALSE,
+    machine_edit BOOLEAN NOT NULL DEFAULT FALSE,
+    machine_delete BOOLEAN NOT NULL DEFAULT FALSE,
+    task_view BOOLEAN NOT NULL DEFAULT FALSE,
+    task_create BOOLEAN NOT NULL DEFAULT FALSE,
+    task_edit BOOLEAN NOT NULL DEFAULT FALSE,
+    task_delete BOOLEAN NOT NULL DEFAULT FALSE,
+    report_view BOOLEAN NOT NULL DEFAULT FALSE,
+    report_create BOOLEAN NOT NULL DEFAULT FALSE,
+    report_edit BOOLEAN NOT NULL DEFAULT FALSE,
+    report_delete BOOLEAN NOT NULL DEFAULT FALSE,
+    facility_view BOOLEAN NOT NULL DEFAULT FALSE,
+    facility_create BOOLEAN NOT NULL DEFAULT FALSE,
+    facility_edit BOOLEAN NOT NULL DEFAULT FALSE,
+    facility_delete BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX idx_api_key_user ON api_keys(user_id);
+
+COMMIT;
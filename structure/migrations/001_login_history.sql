@@ -0,0 +1,20 @@
+-- ADDS LOGIN HISTORY
+-- Independent of the other migrations, record_login fails on every login until this has run
+
+BEGIN;
+
+CREATE TYPE login_method AS ENUM ('password', 'otp', 'oidc');
+
+CREATE TABLE login_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    method login_method NOT NULL,
+    success BOOLEAN NOT NULL,
+    ip VARCHAR(45),
+    user_agent VARCHAR(512),
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_login_history_user ON login_history(user_id, created DESC);
+
+COMMIT;
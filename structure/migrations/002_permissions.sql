@@ -0,0 +1,155 @@
+-- MOVES ROLE AND API KEY PERMISSION COLUMNS INTO THE PERMISSION REGISTRY
+-- Run once against databases created before the permissions table existed
+
+BEGIN;
+
+CREATE TABLE permissions (
+    resource VARCHAR(64) NOT NULL,
+    action VARCHAR(64) NOT NULL,
+    PRIMARY KEY (resource, action)
+);
+
+INSERT INTO permissions (resource, action)
+SELECT
+    resource,
+    action
+FROM
+    unnest(ARRAY[
+        'user', 'role', 'api_key', 'facility',
+        'machine', 'machine_type', 'machine_status',
+        'task', 'task_type', 'task_status',
+        'report', 'report_type', 'report_status'
+    ]) AS resource,
+    unnest(ARRAY['view', 'create', 'edit', 'delete']) AS action;
+
+CREATE TABLE role_permissions (
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    resource VARCHAR(64) NOT NULL,
+    action VARCHAR(64) NOT NULL,
+    PRIMARY KEY (role_id, resource, action),
+    FOREIGN KEY (resource, action) REFERENCES permissions(resource, action) ON DELETE CASCADE
+);
+
+CREATE TABLE api_key_permissions (
+    api_key_id UUID NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    resource VARCHAR(64) NOT NULL,
+    action VARCHAR(64) NOT NULL,
+    PRIMARY KEY (api_key_id, resource, action),
+    FOREIGN KEY (resource, action) REFERENCES permissions(resource, action) ON DELETE CASCADE
+);
+
+-- Every old column covers its own resource and the resources that borrowed it
+
+CREATE TEMPORARY TABLE permission_columns (
+    prefix VARCHAR(64) NOT NULL,
+    resource VARCHAR(64) NOT NULL
+) ON COMMIT DROP;
+
+INSERT INTO permission_columns (prefix, resource) VALUES
+    ('user', 'user'),
+    ('user', 'role'),
+    ('user', 'api_key'),
+    ('facility', 'facility'),
+    ('machine', 'machine'),
+    ('machine', 'machine_type'),
+    ('machine', 'machine_status'),
+    ('task', 'task'),
+    ('task', 'task_type'),
+    ('task', 'task_status'),
+    ('report', 'report'),
+    ('report', 'report_type'),
+    ('report', 'report_status');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    r.id,
+    pc.resource,
+    p.action
+FROM
+    roles r
+CROSS JOIN
+    permission_columns pc
+INNER JOIN
+    permissions p ON p.resource = pc.resource
+WHERE
+    (to_jsonb(r) ->> (pc.prefix || '_' || p.action))::BOOLEAN;
+
+INSERT INTO api_key_permissions (api_key_id, resource, action)
+SELECT
+    ak.id,
+    pc.resource,
+    p.action
+FROM
+    api_keys ak
+CROSS JOIN
+    permission_columns pc
+INNER JOIN
+    permissions p ON p.resource = pc.resource
+WHERE
+    (to_jsonb(ak) ->> (pc.prefix || '_' || p.action))::BOOLEAN;
+
+ALTER TABLE roles
+    DROP COLUMN user_view,
+    DROP COLUMN user_create,
+    DROP COLUMN user_edit,
+    DROP COLUMN user_delete,
+    DROP COLUMN machine_view,
+    DROP COLUMN machine_create,
+    DROP COLUMN machine_edit,
+    DROP COLUMN machine_delete,
+    DROP COLUMN task_view,
+    DROP COLUMN task_create,
+    DROP COLUMN task_edit,
+    DROP COLUMN task_delete,
+    DROP COLUMN report_view,
+    DROP COLUMN report_create,
+    DROP COLUMN report_edit,
+    DROP COLUMN report_delete,
+    DROP COLUMN facility_view,
+    DROP COLUMN facility_create,
+    DROP COLUMN facility_edit,
+    DROP COLUMN facility_delete;
+
+ALTER TABLE api_keys
+    DROP COLUMN user_view,
+    DROP COLUMN user_create,
+    DROP COLUMN user_edit,
+    DROP COLUMN user_delete,
+    DROP COLUMN machine_view,
+    DROP COLUMN machine_create,
+    DROP COLUMN machine_edit,
+    DROP COLUMN machine_delete,
+    DROP COLUMN task_view,
+    DROP COLUMN task_create,
+    DROP COLUMN task_edit,
+    DROP COLUMN task_delete,
+    DROP COLUMN report_view,
+    DROP COLUMN report_create,
+    DROP COLUMN report_edit,
+    DROP COLUMN report_delete,
+    DROP COLUMN facility_view,
+    DROP COLUMN facility_create,
+    DROP COLUMN facility_edit,
+    DROP COLUMN facility_delete;
+
+CREATE OR REPLACE FUNCTION role_permission_keys(role_id UUID)
+RETURNS VARCHAR[] AS $$
+    SELECT
+        COALESCE(array_agg(rp.resource || '.' || rp.action ORDER BY rp.resource, rp.action), '{}')
+    FROM
+        role_permissions rp
+    WHERE
+        rp.role_id = $1;
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION api_key_permission_keys(api_key_id UUID)
+RETURNS VARCHAR[] AS $$
+    SELECT
+        COALESCE(array_agg(akp.resource || '.' || akp.action ORDER BY akp.resource, akp.action), '{}')
+    FROM
+        api_key_permissions akp
+    WHERE
+        akp.api_key_id = $1;
+$$ LANGUAGE sql STABLE;
+
+COMMIT;
@@ -0,0 +1,64 @@
+-- SCOPES ROLE GRANTS TO FACILITIES
+-- Existing roles keep reaching every facility, narrow them by setting global_access to FALSE
+
+BEGIN;
+
+ALTER TABLE roles ADD COLUMN global_access BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE roles SET global_access = TRUE;
+
+CREATE TABLE user_facilities (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, facility_id)
+);
+
+CREATE OR REPLACE FUNCTION user_facility_ids(user_id UUID)
+RETURNS UUID[] AS $$
+    SELECT
+        COALESCE(array_agg(DISTINCT scope.facility_id), '{}')
+    FROM
+    (
+        SELECT u.facility AS facility_id FROM users u WHERE u.id = $1 AND u.facility IS NOT NULL
+        UNION
+        SELECT uf.facility_id FROM user_facilities uf WHERE uf.user_id = $1
+    ) scope;
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION task_facility(task_id UUID)
+RETURNS UUID AS $$
+    SELECT
+        COALESCE(m.facility, u.facility)
+    FROM
+        tasks t
+    INNER JOIN
+        users u
+    ON
+        t.creator = u.id
+    LEFT JOIN
+        machines m
+    ON
+        t.machine = m.id
+    WHERE
+        t.id = $1;
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION report_facility(report_id UUID)
+RETURNS UUID AS $$
+    SELECT
+        COALESCE(m.facility, u.facility)
+    FROM
+        reports r
+    INNER JOIN
+        users u
+    ON
+        r.creator = u.id
+    LEFT JOIN
+        machines m
+    ON
+        r.machine = m.id
+    WHERE
+        r.id = $1;
+$$ LANGUAGE sql STABLE;
+
+COMMIT;
@@ -0,0 +1,34 @@
+-- ADDS OWNERSHIP RULES TO THE PERMISSION REGISTRY
+-- Roles holding the global permission also get the narrower ownership rule
+
+BEGIN;
+
+INSERT INTO permissions (resource, action) VALUES
+    ('task', 'edit_own'),
+    ('task', 'delete_own'),
+    ('task', 'edit_status_assigned'),
+    ('report', 'edit_own'),
+    ('report', 'delete_own');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    rp.role_id,
+    rp.resource,
+    rule.action
+FROM
+    role_permissions rp
+INNER JOIN
+(
+    VALUES
+        ('task', 'edit', 'edit_own'),
+        ('task', 'delete', 'delete_own'),
+        ('task', 'edit', 'edit_status_assigned'),
+        ('report', 'edit', 'edit_own'),
+        ('report', 'delete', 'delete_own')
+) AS rule(resource, granted_by, action)
+ON
+    rp.resource = rule.resource
+AND
+    rp.action = rule.granted_by;
+
+COMMIT;
@@ -0,0 +1,34 @@
+-- ADDS ADMIN IMPERSONATION
+-- Only the highest role gets the new permission, hand it out to others by editing their role
+
+BEGIN;
+
+INSERT INTO permissions (resource, action) VALUES ('user', 'impersonate');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    r.id,
+    'user',
+    'impersonate'
+FROM
+    roles r
+WHERE
+    r.level = (SELECT MIN(level) FROM roles);
+
+CREATE TABLE impersonations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    admin_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    reason TEXT,
+    ip VARCHAR(45),
+    user_agent VARCHAR(512),
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ,
+    ended_by UUID REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_impersonations_admin ON impersonations(admin_id, created DESC);
+CREATE INDEX idx_impersonations_user ON impersonations(user_id, created DESC);
+
+COMMIT;
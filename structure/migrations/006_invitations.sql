@@ -0,0 +1,38 @@
+-- ADDS USER INVITATIONS
+
+BEGIN;
+
+-- Invited users are inactive until they have set their password
+
+ALTER TABLE users DROP CONSTRAINT is_password_required_constraint;
+
+DROP FUNCTION is_password_required(UUID, VARCHAR);
+
+CREATE OR REPLACE FUNCTION is_password_required(role_id UUID, user_password VARCHAR, user_active BOOLEAN)
+RETURNS BOOLEAN AS $$
+DECLARE
+    role_has_password BOOLEAN;
+BEGIN
+    SELECT INTO role_has_password has_password FROM roles WHERE id = role_id;
+    
+    RETURN (NOT role_has_password OR user_password IS NOT NULL OR NOT user_active);
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE users
+ADD CONSTRAINT is_password_required_constraint
+CHECK (is_password_required(role, password, active));
+
+CREATE TABLE invitations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    accepted_at TIMESTAMPTZ,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX idx_invitations_user ON invitations(user_id, created DESC);
+
+COMMIT;
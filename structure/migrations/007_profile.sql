@@ -0,0 +1,9 @@
+-- ADDS SELF-SERVICE PROFILE PREFERENCES
+
+BEGIN;
+
+ALTER TABLE users
+ADD COLUMN language VARCHAR(16) NOT NULL DEFAULT 'en',
+ADD COLUMN notify_task_assigned BOOLEAN NOT NULL DEFAULT TRUE;
+
+COMMIT;
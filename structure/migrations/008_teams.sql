@@ -0,0 +1,66 @@
+-- ADDS TEAMS THAT TASKS CAN BE ASSIGNED TO
+-- Roles get the team permissions matching the user permissions they already hold
+
+BEGIN;
+
+CREATE TABLE teams (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT,
+    facility UUID REFERENCES facilities(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE team_members (
+    team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (team_id, user_id)
+);
+
+CREATE INDEX idx_team_members_user ON team_members(user_id);
+
+CREATE TABLE task_teams (
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    PRIMARY KEY (task_id, team_id)
+);
+
+CREATE OR REPLACE FUNCTION is_task_executor(task_id UUID, user_id UUID)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS (
+        SELECT 1 FROM task_executors te WHERE te.task_id = $1 AND te.user_id = $2
+    ) OR EXISTS (
+        SELECT
+            1
+        FROM
+            task_teams tt
+        INNER JOIN
+            team_members tm
+        ON
+            tt.team_id = tm.team_id
+        WHERE
+            tt.task_id = $1
+        AND
+            tm.user_id = $2
+    );
+$$ LANGUAGE sql STABLE;
+
+INSERT INTO permissions (resource, action) VALUES
+    ('team', 'view'),
+    ('team', 'create'),
+    ('team', 'edit'),
+    ('team', 'delete');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    rp.role_id,
+    'team',
+    rp.action
+FROM
+    role_permissions rp
+WHERE
+    rp.resource = 'user'
+AND
+    rp.action IN ('view', 'create', 'edit', 'delete');
+
+COMMIT;
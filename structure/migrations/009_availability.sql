@@ -0,0 +1,103 @@
+-- ADDS TECHNICIAN AVAILABILITY
+-- Roles get the availability permissions matching the user permissions they already hold
+
+BEGIN;
+
+-- Shifts and on-call duty make a user available, a vacation overrides both
+
+CREATE TYPE availability_kind AS ENUM ('shift', 'vacation', 'on_call');
+
+CREATE TABLE availabilities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind availability_kind NOT NULL,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL,
+    note TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX idx_availabilities_user ON availabilities(user_id, starts_at);
+
+CREATE OR REPLACE FUNCTION is_user_available(user_id UUID, starts_at TIMESTAMPTZ, ends_at TIMESTAMPTZ)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS (
+        SELECT
+            1
+        FROM
+            availabilities a
+        WHERE
+            a.user_id = $1
+        AND
+            a.kind IN ('shift', 'on_call')
+        AND
+            a.starts_at <= $2
+        AND
+            a.ends_at >= $3
+    ) AND NOT EXISTS (
+        SELECT
+            1
+        FROM
+            availabilities a
+        WHERE
+            a.user_id = $1
+        AND
+            a.kind = 'vacation'
+        AND
+            a.starts_at <= $3
+        AND
+            a.ends_at >= $2
+    );
+$$ LANGUAGE sql STABLE;
+
+-- True when the task is due at a time none of its executors is available
+
+CREATE OR REPLACE FUNCTION is_task_outside_availability(task_id UUID)
+RETURNS BOOLEAN AS $$
+    WITH executors AS (
+        SELECT te.user_id FROM task_executors te WHERE te.task_id = $1
+        UNION
+        SELECT
+            tm.user_id
+        FROM
+            task_teams ttm
+        INNER JOIN
+            team_members tm
+        ON
+            ttm.team_id = tm.team_id
+        WHERE
+            ttm.task_id = $1
+    )
+    SELECT
+        t.due_at IS NOT NULL
+        AND EXISTS (SELECT 1 FROM executors)
+        AND NOT EXISTS (
+            SELECT 1 FROM executors e WHERE is_user_available(e.user_id, t.due_at, t.due_at)
+        )
+    FROM
+        tasks t
+    WHERE
+        t.id = $1;
+$$ LANGUAGE sql STABLE;
+
+INSERT INTO permissions (resource, action) VALUES
+    ('availability', 'view'),
+    ('availability', 'create'),
+    ('availability', 'edit'),
+    ('availability', 'delete');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    rp.role_id,
+    'availability',
+    rp.action
+FROM
+    role_permissions rp
+WHERE
+    rp.resource = 'user'
+AND
+    rp.action IN ('view', 'create', 'edit', 'delete');
+
+COMMIT;
@@ -0,0 +1,9 @@
+-- ADDS THE ASSIGNMENT TIME OF EXECUTORS, USED BY THE ROUND-ROBIN AUTO-ASSIGNMENT
+
+BEGIN;
+
+ALTER TABLE task_executors ADD COLUMN assigned TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX task_executors_user_id_idx ON task_executors (user_id, assigned);
+
+COMMIT;
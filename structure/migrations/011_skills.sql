@@ -0,0 +1,110 @@
+-- ADDS SKILLS AND CERTIFICATIONS OF USERS AND THE SKILLS TASKS REQUIRE
+-- Roles get the skill permissions matching the user permissions they already hold
+
+BEGIN;
+
+-- Skills are anything a task can require, certifications are skills that expire
+
+CREATE TABLE skills (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT,
+    certification BOOLEAN NOT NULL DEFAULT FALSE,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE user_skills (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    skill_id UUID NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+    obtained_at DATE,
+    expires_at DATE,
+    reference VARCHAR(255),
+    PRIMARY KEY (user_id, skill_id)
+);
+
+CREATE INDEX user_skills_expires_at_idx ON user_skills (expires_at) WHERE expires_at IS NOT NULL;
+
+-- Required skills come from the task type and from the type of the task's machine
+
+CREATE TABLE task_type_skills (
+    task_type_id UUID NOT NULL REFERENCES task_types(id) ON DELETE CASCADE,
+    skill_id UUID NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+    PRIMARY KEY (task_type_id, skill_id)
+);
+
+CREATE TABLE machine_type_skills (
+    machine_type_id UUID NOT NULL REFERENCES machine_types(id) ON DELETE CASCADE,
+    skill_id UUID NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+    PRIMARY KEY (machine_type_id, skill_id)
+);
+
+-- Names of the required skills the user doesn't hold, expired certifications don't count
+
+CREATE OR REPLACE FUNCTION task_missing_skills(task_id UUID, user_id UUID)
+RETURNS TEXT[] AS $$
+    SELECT
+        COALESCE(array_agg(s.name::TEXT ORDER BY s.name), '{}')
+    FROM
+        skills s
+    WHERE
+        s.id IN (
+            SELECT
+                tts.skill_id
+            FROM
+                tasks t
+            INNER JOIN
+                task_type_skills tts
+            ON
+                t.task_type = tts.task_type_id
+            WHERE
+                t.id = $1
+            UNION
+            SELECT
+                mts.skill_id
+            FROM
+                tasks t
+            INNER JOIN
+                machines m
+            ON
+                t.machine = m.id
+            INNER JOIN
+                machine_type_skills mts
+            ON
+                m.machine_type = mts.machine_type_id
+            WHERE
+                t.id = $1
+        )
+    AND
+        NOT EXISTS (
+            SELECT
+                1
+            FROM
+                user_skills us
+            WHERE
+                us.user_id = $2
+            AND
+                us.skill_id = s.id
+            AND
+                (us.expires_at IS NULL OR us.expires_at >= CURRENT_DATE)
+        );
+$$ LANGUAGE sql STABLE;
+
+INSERT INTO permissions (resource, action) VALUES
+    ('skill', 'view'),
+    ('skill', 'create'),
+    ('skill', 'edit'),
+    ('skill', 'delete');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    rp.role_id,
+    'skill',
+    rp.action
+FROM
+    role_permissions rp
+WHERE
+    rp.resource = 'user'
+AND
+    rp.action IN ('view', 'create', 'edit', 'delete');
+
+COMMIT;
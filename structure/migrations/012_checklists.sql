@@ -0,0 +1,98 @@
+-- ADDS CHECKLIST TEMPLATES, TASK CHECKLISTS AND TERMINAL TASK STATUSES
+-- Roles get the checklist permissions matching the task type permissions they already hold
+
+BEGIN;
+
+ALTER TABLE task_statuses ADD COLUMN terminal BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Checklist templates are copied onto tasks, so changing a template doesn't alter
+-- the steps of existing tasks
+
+CREATE TABLE checklist_templates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE checklist_template_steps (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    template_id UUID NOT NULL REFERENCES checklist_templates(id) ON DELETE CASCADE,
+    position INT NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    instructions TEXT,
+    mandatory BOOLEAN NOT NULL DEFAULT TRUE,
+    requires_photo BOOLEAN NOT NULL DEFAULT FALSE,
+    requires_measurement BOOLEAN NOT NULL DEFAULT FALSE,
+    measurement_unit VARCHAR(32)
+);
+
+CREATE INDEX checklist_template_steps_template_id_idx ON checklist_template_steps (template_id, position);
+
+CREATE TABLE task_type_checklists (
+    task_type_id UUID NOT NULL REFERENCES task_types(id) ON DELETE CASCADE,
+    template_id UUID NOT NULL REFERENCES checklist_templates(id) ON DELETE CASCADE,
+    PRIMARY KEY (task_type_id, template_id)
+);
+
+CREATE TABLE machine_type_checklists (
+    machine_type_id UUID NOT NULL REFERENCES machine_types(id) ON DELETE CASCADE,
+    template_id UUID NOT NULL REFERENCES checklist_templates(id) ON DELETE CASCADE,
+    PRIMARY KEY (machine_type_id, template_id)
+);
+
+CREATE TABLE task_checklist_steps (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    template_id UUID REFERENCES checklist_templates(id) ON DELETE SET NULL,
+    position INT NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    instructions TEXT,
+    mandatory BOOLEAN NOT NULL,
+    requires_photo BOOLEAN NOT NULL,
+    requires_measurement BOOLEAN NOT NULL,
+    measurement_unit VARCHAR(32),
+    photo VARCHAR(512),
+    measurement DOUBLE PRECISION,
+    note TEXT,
+    completed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX task_checklist_steps_task_id_idx ON task_checklist_steps (task_id, position);
+
+CREATE OR REPLACE FUNCTION is_task_checklist_incomplete(task_id UUID)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS (
+        SELECT
+            1
+        FROM
+            task_checklist_steps tcs
+        WHERE
+            tcs.task_id = $1
+        AND
+            tcs.mandatory
+        AND
+            tcs.completed_at IS NULL
+    );
+$$ LANGUAGE sql STABLE;
+
+INSERT INTO permissions (resource, action) VALUES
+    ('checklist', 'view'),
+    ('checklist', 'create'),
+    ('checklist', 'edit'),
+    ('checklist', 'delete');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    rp.role_id,
+    'checklist',
+    rp.action
+FROM
+    role_permissions rp
+WHERE
+    rp.resource = 'task_type'
+AND
+    rp.action IN ('view', 'create', 'edit', 'delete');
+
+COMMIT;
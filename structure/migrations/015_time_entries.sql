@@ -0,0 +1,57 @@
+-- ADDS TIME LOGGING ON TASKS
+-- Roles get the time entry permissions matching the task permissions they already hold
+
+BEGIN;
+
+-- Labour logged by executors, an entry without an end is a running timer and nobody
+-- runs more than one at a time
+
+CREATE TABLE task_time_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ,
+    note TEXT,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ended_at IS NULL OR ended_at > started_at)
+);
+
+CREATE INDEX task_time_entries_task_id_idx ON task_time_entries (task_id);
+CREATE INDEX task_time_entries_user_id_idx ON task_time_entries (user_id, started_at);
+CREATE UNIQUE INDEX task_time_entries_running_idx ON task_time_entries (user_id) WHERE ended_at IS NULL;
+
+-- Running timers count up to now
+
+CREATE OR REPLACE FUNCTION task_logged_minutes(task_id UUID)
+RETURNS BIGINT AS $$
+    SELECT
+        COALESCE(
+            floor(sum(EXTRACT(EPOCH FROM COALESCE(tte.ended_at, NOW()) - tte.started_at)) / 60),
+            0
+        )::BIGINT
+    FROM
+        task_time_entries tte
+    WHERE
+        tte.task_id = $1;
+$$ LANGUAGE sql STABLE;
+
+INSERT INTO permissions (resource, action) VALUES
+    ('time_entry', 'view'),
+    ('time_entry', 'create'),
+    ('time_entry', 'edit'),
+    ('time_entry', 'delete');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    rp.role_id,
+    'time_entry',
+    rp.action
+FROM
+    role_permissions rp
+WHERE
+    rp.resource = 'task'
+AND
+    rp.action IN ('view', 'create', 'edit', 'delete');
+
+COMMIT;
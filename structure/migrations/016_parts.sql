@@ -0,0 +1,93 @@
+-- ADDS SPARE PARTS, STOCK PER FACILITY AND STOCK MOVEMENTS
+-- Roles get the part permissions matching the machine permissions they already hold
+
+BEGIN;
+
+-- Spare parts catalogue, parts without machine types fit any machine
+
+CREATE TABLE parts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    part_number VARCHAR(64) NOT NULL UNIQUE,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    unit VARCHAR(32) NOT NULL DEFAULT 'pcs',
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    edited TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_parts_edited
+BEFORE UPDATE ON parts
+FOR EACH ROW
+EXECUTE PROCEDURE update_edited_column();
+
+CREATE TABLE machine_type_parts (
+    machine_type_id UUID NOT NULL REFERENCES machine_types(id) ON DELETE CASCADE,
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    PRIMARY KEY (machine_type_id, part_id)
+);
+
+-- Stock per facility, reserved parts are still on hand but promised to a task
+
+CREATE TABLE part_stock (
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    on_hand INT NOT NULL DEFAULT 0,
+    reserved INT NOT NULL DEFAULT 0,
+    min_quantity INT NOT NULL DEFAULT 0 CHECK (min_quantity >= 0),
+    PRIMARY KEY (part_id, facility_id),
+    CHECK (reserved >= 0 AND reserved <= on_hand)
+);
+
+-- Parts reserved for a task, consumed once they are used in the repair
+
+CREATE TABLE task_parts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    reserved_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    consumed_at TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX task_parts_task_id_idx ON task_parts (task_id);
+
+-- Every change of the stock on hand, consumption keeps the machine of the task so the
+-- history shows what was used where
+
+CREATE TABLE part_movements (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    change INT NOT NULL,
+    kind VARCHAR(32) NOT NULL CHECK (kind IN ('receipt', 'adjustment', 'consumption', 'return')),
+    task_id UUID REFERENCES tasks(id) ON DELETE SET NULL,
+    machine_id UUID REFERENCES machines(id) ON DELETE SET NULL,
+    note TEXT,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX part_movements_part_id_idx ON part_movements (part_id, facility_id, created);
+CREATE INDEX part_movements_machine_id_idx ON part_movements (machine_id, created);
+
+INSERT INTO permissions (resource, action) VALUES
+    ('part', 'view'),
+    ('part', 'create'),
+    ('part', 'edit'),
+    ('part', 'delete');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    rp.role_id,
+    'part',
+    rp.action
+FROM
+    role_permissions rp
+WHERE
+    rp.resource = 'machine'
+AND
+    rp.action IN ('view', 'create', 'edit', 'delete');
+
+COMMIT;
@@ -0,0 +1,88 @@
+-- ADDS SUPPLIERS AND PURCHASE REQUESTS
+-- Roles get the supplier and purchase request permissions matching their part permissions,
+-- roles that may delete parts may approve purchase requests
+
+BEGIN;
+
+ALTER TABLE part_stock ADD COLUMN reorder_quantity INT NOT NULL DEFAULT 0 CHECK (reorder_quantity >= 0);
+
+CREATE TABLE suppliers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    contact_name VARCHAR(255),
+    email VARCHAR(255),
+    phone VARCHAR(64),
+    address VARCHAR(255),
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Requests go from requested over approved and ordered to received, which books the parts
+-- into the stock. Automatic ones come from low stock or from a task short of parts
+
+CREATE TABLE purchase_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    supplier_id UUID REFERENCES suppliers(id) ON DELETE SET NULL,
+    status VARCHAR(32) NOT NULL DEFAULT 'requested' CHECK (
+        status IN ('requested', 'approved', 'rejected', 'ordered', 'received', 'cancelled')
+    ),
+    source VARCHAR(32) NOT NULL CHECK (source IN ('manual', 'low_stock', 'task')),
+    task_id UUID REFERENCES tasks(id) ON DELETE SET NULL,
+    note TEXT,
+    requested_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    decided_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    decided_at TIMESTAMPTZ,
+    ordered_at TIMESTAMPTZ,
+    received_at TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    edited TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_purchase_requests_edited
+BEFORE UPDATE ON purchase_requests
+FOR EACH ROW
+EXECUTE PROCEDURE update_edited_column();
+
+CREATE INDEX purchase_requests_open_idx ON purchase_requests (part_id, facility_id)
+WHERE status IN ('requested', 'approved', 'ordered');
+
+INSERT INTO permissions (resource, action) VALUES
+    ('supplier', 'view'),
+    ('supplier', 'create'),
+    ('supplier', 'edit'),
+    ('supplier', 'delete'),
+    ('purchase_request', 'view'),
+    ('purchase_request', 'create'),
+    ('purchase_request', 'edit'),
+    ('purchase_request', 'delete'),
+    ('purchase_request', 'approve');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    rp.role_id,
+    resources.resource,
+    rp.action
+FROM
+    role_permissions rp
+CROSS JOIN
+    unnest(ARRAY['supplier', 'purchase_request']) AS resources(resource)
+WHERE
+    rp.resource = 'part'
+AND
+    rp.action IN ('view', 'create', 'edit', 'delete');
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    rp.role_id,
+    'purchase_request',
+    'approve'
+FROM
+    role_permissions rp
+WHERE
+    rp.resource = 'part'
+AND
+    rp.action = 'delete';
+
+COMMIT;
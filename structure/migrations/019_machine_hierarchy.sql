@@ -0,0 +1,69 @@
+-- ADDS THE MACHINE HIERARCHY AND MACHINE DOWNTIME
+-- Existing machines stay at the top level and start their status history now
+
+BEGIN;
+
+ALTER TABLE machine_statuses ADD COLUMN down BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE machines ADD COLUMN parent UUID REFERENCES machines(id) ON DELETE SET NULL;
+ALTER TABLE machines ADD CHECK (parent <> id);
+
+CREATE INDEX machines_parent_idx ON machines (parent);
+
+-- Guards against a machine ending up below itself, which would make the tree endless
+
+CREATE OR REPLACE FUNCTION check_machine_cycle() RETURNS TRIGGER AS $$
+BEGIN
+  IF NEW.parent IS NOT NULL AND EXISTS (
+    WITH RECURSIVE ancestors AS (
+      SELECT NEW.parent AS id
+      UNION
+      SELECT m.parent FROM machines m INNER JOIN ancestors a ON m.id = a.id WHERE m.parent IS NOT NULL
+    )
+    SELECT 1 FROM ancestors WHERE id = NEW.id
+  ) THEN
+    RAISE EXCEPTION 'Machine % can''t be placed below itself', NEW.id;
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_machine_cycle
+BEFORE INSERT OR UPDATE OF parent ON machines
+FOR EACH ROW EXECUTE PROCEDURE check_machine_cycle();
+
+-- Every status a machine has been in, the open row is the current one
+
+CREATE TABLE machine_status_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    machine_id UUID NOT NULL REFERENCES machines(id) ON DELETE CASCADE,
+    status UUID NOT NULL REFERENCES machine_statuses(id) ON DELETE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ended_at TIMESTAMPTZ
+);
+
+CREATE INDEX machine_status_history_machine_id_idx ON machine_status_history (machine_id, started_at);
+
+CREATE OR REPLACE FUNCTION log_machine_status() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'UPDATE' AND NEW.status = OLD.status THEN
+    RETURN NEW;
+  END IF;
+  UPDATE machine_status_history SET ended_at = NOW() WHERE machine_id = NEW.id AND ended_at IS NULL;
+  INSERT INTO machine_status_history (machine_id, status) VALUES (NEW.id, NEW.status);
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_machine_status_history
+AFTER INSERT OR UPDATE OF status ON machines
+FOR EACH ROW EXECUTE PROCEDURE log_machine_status();
+
+INSERT INTO machine_status_history (machine_id, status)
+SELECT
+    id,
+    status
+FROM
+    machines;
+
+COMMIT;
@@ -0,0 +1,73 @@
+-- ADDS LOCATIONS INSIDE FACILITIES
+-- Locations are managed with the facility permissions, existing machines and users start
+-- without a location
+
+BEGIN;
+
+-- Places inside a facility, buildings hold floors which hold rooms and areas
+
+CREATE TABLE locations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    parent UUID REFERENCES locations(id) ON DELETE CASCADE,
+    kind VARCHAR(32) NOT NULL CHECK (kind IN ('building', 'floor', 'area')),
+    name VARCHAR(255) NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (parent <> id)
+);
+
+CREATE INDEX locations_facility_id_idx ON locations (facility_id);
+CREATE INDEX locations_parent_idx ON locations (parent);
+
+CREATE OR REPLACE FUNCTION check_location_cycle() RETURNS TRIGGER AS $$
+BEGIN
+  IF NEW.parent IS NOT NULL AND EXISTS (
+    WITH RECURSIVE ancestors AS (
+      SELECT NEW.parent AS id
+      UNION
+      SELECT l.parent FROM locations l INNER JOIN ancestors a ON l.id = a.id WHERE l.parent IS NOT NULL
+    )
+    SELECT 1 FROM ancestors WHERE id = NEW.id
+  ) THEN
+    RAISE EXCEPTION 'Location % can''t be placed inside itself', NEW.id;
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_location_cycle
+BEFORE INSERT OR UPDATE OF parent ON locations
+FOR EACH ROW EXECUTE PROCEDURE check_location_cycle();
+
+-- The location and everything inside it, for filtering by location
+
+CREATE OR REPLACE FUNCTION location_subtree(location_id UUID)
+RETURNS SETOF UUID AS $$
+    WITH RECURSIVE subtree AS (
+        SELECT $1 AS id
+        UNION ALL
+        SELECT l.id FROM locations l INNER JOIN subtree s ON l.parent = s.id
+    )
+    SELECT id FROM subtree;
+$$ LANGUAGE sql STABLE;
+
+ALTER TABLE machines ADD COLUMN location UUID REFERENCES locations(id) ON DELETE SET NULL;
+
+CREATE INDEX machines_location_idx ON machines (location);
+
+ALTER TABLE users ADD COLUMN location UUID REFERENCES locations(id) ON DELETE SET NULL;
+
+-- Every move of a machine between locations, a missing location is outside of any
+
+CREATE TABLE machine_location_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    machine_id UUID NOT NULL REFERENCES machines(id) ON DELETE CASCADE,
+    from_location UUID REFERENCES locations(id) ON DELETE SET NULL,
+    to_location UUID REFERENCES locations(id) ON DELETE SET NULL,
+    moved_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    moved_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX machine_location_history_machine_id_idx ON machine_location_history (machine_id, moved_at);
+
+COMMIT;
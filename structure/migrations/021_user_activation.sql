@@ -0,0 +1,41 @@
+-- REMEMBERS WHEN USERS WERE FIRST ACTIVE
+-- Inactive users with only open invitations never got in, every other user counts as activated
+
+BEGIN;
+
+ALTER TABLE users ADD COLUMN activated_at TIMESTAMPTZ;
+
+UPDATE
+    users u
+SET
+    activated_at = u.last_login
+WHERE
+    u.active
+OR
+    NOT EXISTS (
+        SELECT
+            1
+        FROM
+            invitations i
+        WHERE
+            i.user_id = u.id
+        AND
+            i.accepted_at IS NULL
+    );
+
+-- Set the first time a user is active, invitations only go to accounts that never were
+
+CREATE OR REPLACE FUNCTION set_user_activated() RETURNS TRIGGER AS $$
+BEGIN
+  IF NEW.active AND NEW.activated_at IS NULL THEN
+    NEW.activated_at = NOW();
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_user_activated
+BEFORE INSERT OR UPDATE OF active ON users
+FOR EACH ROW EXECUTE PROCEDURE set_user_activated();
+
+COMMIT;
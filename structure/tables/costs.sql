@@ -0,0 +1,45 @@
+-- Maintenance costs, all amounts are in cents
+
+-- What an hour of a user costs, time entries keep the rate they were logged at so
+-- changing it doesn't rewrite past costs
+
+CREATE TABLE user_rates (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    hourly_rate INT NOT NULL CHECK (hourly_rate >= 0),
+    edited TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_user_rates_edited
+BEFORE UPDATE ON user_rates
+FOR EACH ROW
+EXECUTE PROCEDURE update_edited_column();
+
+CREATE OR REPLACE FUNCTION set_time_entry_rate() RETURNS TRIGGER AS $$
+BEGIN
+  IF NEW.hourly_rate IS NULL THEN
+    SELECT hourly_rate INTO NEW.hourly_rate FROM user_rates WHERE user_id = NEW.user_id;
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_time_entry_rate
+BEFORE INSERT ON task_time_entries
+FOR EACH ROW EXECUTE PROCEDURE set_time_entry_rate();
+
+-- Costs that aren't labour or stock parts, like external services
+
+CREATE TABLE task_costs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    kind VARCHAR(32) NOT NULL CHECK (kind IN ('service', 'other')),
+    description TEXT NOT NULL,
+    amount INT NOT NULL CHECK (amount >= 0),
+    supplier_id UUID REFERENCES suppliers(id) ON DELETE SET NULL,
+    incurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX task_costs_task_id_idx ON task_costs (task_id);
+CREATE INDEX task_costs_incurred_at_idx ON task_costs (incurred_at);
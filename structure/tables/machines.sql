@@ -3,9 +3,12 @@ CREATE TABLE machine_types (
     name VARCHAR(255) NOT NULL UNIQUE
 );
 
+-- Time spent in a down status counts as downtime
+
 CREATE TABLE machine_statuses (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-    name VARCHAR(255) NOT NULL UNIQUE
+    name VARCHAR(255) NOT NULL UNIQUE,
+    down BOOLEAN NOT NULL DEFAULT FALSE
 );
 
 CREATE TABLE facilities (
@@ -14,6 +17,55 @@ CREATE TABLE facilities (
     address VARCHAR(255)
 );
 
+-- Places inside a facility, buildings hold floors which hold rooms and areas
+
+CREATE TABLE locations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    parent UUID REFERENCES locations(id) ON DELETE CASCADE,
+    kind VARCHAR(32) NOT NULL CHECK (kind IN ('building', 'floor', 'area')),
+    name VARCHAR(255) NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (parent <> id)
+);
+
+CREATE INDEX locations_facility_id_idx ON locations (facility_id);
+CREATE INDEX locations_parent_idx ON locations (parent);
+
+CREATE OR REPLACE FUNCTION check_location_cycle() RETURNS TRIGGER AS $$
+BEGIN
+  IF NEW.parent IS NOT NULL AND EXISTS (
+    WITH RECURSIVE ancestors AS (
+      SELECT NEW.parent AS id
+      UNION
+      SELECT l.parent FROM locations l INNER JOIN ancestors a ON l.id = a.id WHERE l.parent IS NOT NULL
+    )
+    SELECT 1 FROM ancestors WHERE id = NEW.id
+  ) THEN
+    RAISE EXCEPTION 'Location % can''t be placed inside itself', NEW.id;
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_location_cycle
+BEFORE INSERT OR UPDATE OF parent ON locations
+FOR EACH ROW EXECUTE PROCEDURE check_location_cycle();
+
+-- The location and everything inside it, for filtering by location
+
+CREATE OR REPLACE FUNCTION location_subtree(location_id UUID)
+RETURNS SETOF UUID AS $$
+    WITH RECURSIVE subtree AS (
+        SELECT $1 AS id
+        UNION ALL
+        SELECT l.id FROM locations l INNER JOIN subtree s ON l.parent = s.id
+    )
+    SELECT id FROM subtree;
+$$ LANGUAGE sql STABLE;
+
+-- Machines form a hierarchy, a line holds machines which hold their components
+
 CREATE TABLE machines (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     name VARCHAR(255) NOT NULL,
@@ -23,10 +75,78 @@ CREATE TABLE machines (
     created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     edited TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     facility UUID REFERENCES facilities(id) ON DELETE SET NULL,
-    image VARCHAR(512)
+    image VARCHAR(512),
+    parent UUID REFERENCES machines(id) ON DELETE SET NULL,
+    location UUID REFERENCES locations(id) ON DELETE SET NULL,
+    CHECK (parent <> id)
 );
 
+CREATE INDEX machines_parent_idx ON machines (parent);
+CREATE INDEX machines_location_idx ON machines (location);
+
 CREATE TRIGGER update_machines_edited
 BEFORE UPDATE ON machines
 FOR EACH ROW
-EXECUTE PROCEDURE update_edited_column();
\ No newline at end of file
+EXECUTE PROCEDURE update_edited_column();
+
+-- Guards against a machine ending up below itself, which would make the tree endless
+
+CREATE OR REPLACE FUNCTION check_machine_cycle() RETURNS TRIGGER AS $$
+BEGIN
+  IF NEW.parent IS NOT NULL AND EXISTS (
+    WITH RECURSIVE ancestors AS (
+      SELECT NEW.parent AS id
+      UNION
+      SELECT m.parent FROM machines m INNER JOIN ancestors a ON m.id = a.id WHERE m.parent IS NOT NULL
+    )
+    SELECT 1 FROM ancestors WHERE id = NEW.id
+  ) THEN
+    RAISE EXCEPTION 'Machine % can''t be placed below itself', NEW.id;
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_machine_cycle
+BEFORE INSERT OR UPDATE OF parent ON machines
+FOR EACH ROW EXECUTE PROCEDURE check_machine_cycle();
+
+-- Every status a machine has been in, the open row is the current one
+
+CREATE TABLE machine_status_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    machine_id UUID NOT NULL REFERENCES machines(id) ON DELETE CASCADE,
+    status UUID NOT NULL REFERENCES machine_statuses(id) ON DELETE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ended_at TIMESTAMPTZ
+);
+
+CREATE INDEX machine_status_history_machine_id_idx ON machine_status_history (machine_id, started_at);
+
+CREATE OR REPLACE FUNCTION log_machine_status() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP = 'UPDATE' AND NEW.status = OLD.status THEN
+    RETURN NEW;
+  END IF;
+  UPDATE machine_status_history SET ended_at = NOW() WHERE machine_id = NEW.id AND ended_at IS NULL;
+  INSERT INTO machine_status_history (machine_id, status) VALUES (NEW.id, NEW.status);
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_machine_status_history
+AFTER INSERT OR UPDATE OF status ON machines
+FOR EACH ROW EXECUTE PROCEDURE log_machine_status();
+
+-- Every move of a machine between locations, a missing location is outside of any
+
+CREATE TABLE machine_location_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    machine_id UUID NOT NULL REFERENCES machines(id) ON DELETE CASCADE,
+    from_location UUID REFERENCES locations(id) ON DELETE SET NULL,
+    to_location UUID REFERENCES locations(id) ON DELETE SET NULL,
+    moved_by UUID,
+    moved_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX machine_location_history_machine_id_idx ON machine_location_history (machine_id, moved_at);
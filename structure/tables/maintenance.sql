@@ -0,0 +1,39 @@
+-- Usage counters of a machine, like operating hours or cycles, reported through the API
+
+CREATE TABLE machine_counters (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    machine_id UUID NOT NULL REFERENCES machines(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    unit VARCHAR(32) NOT NULL,
+    value DOUBLE PRECISION NOT NULL DEFAULT 0,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    edited TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (machine_id, name)
+);
+
+CREATE TRIGGER update_machine_counters_edited
+BEFORE UPDATE ON machine_counters
+FOR EACH ROW
+EXECUTE PROCEDURE update_edited_column();
+
+-- Once the counter reaches next_due a task is opened from the rule and next_due moves on
+-- by the usage interval
+
+CREATE TABLE maintenance_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    counter_id UUID NOT NULL REFERENCES machine_counters(id) ON DELETE CASCADE,
+    usage_interval DOUBLE PRECISION NOT NULL CHECK (usage_interval > 0),
+    next_due DOUBLE PRECISION NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    description TEXT NOT NULL,
+    task_type UUID NOT NULL REFERENCES task_types(id) ON DELETE CASCADE,
+    status UUID NOT NULL REFERENCES task_statuses(id) ON DELETE CASCADE,
+    due_in_days INT CHECK (due_in_days >= 0),
+    auto_assign BOOLEAN NOT NULL DEFAULT FALSE,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    last_task UUID REFERENCES tasks(id) ON DELETE SET NULL,
+    last_triggered TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX maintenance_rules_counter_idx ON maintenance_rules (counter_id);
@@ -0,0 +1,31 @@
+-- Measurements are defined per machine type, readings outside the thresholds are flagged
+-- and, when the definition names a report type and status, raise a report
+
+CREATE TABLE measurement_definitions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    machine_type UUID NOT NULL REFERENCES machine_types(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    unit VARCHAR(32) NOT NULL,
+    min_value DOUBLE PRECISION,
+    max_value DOUBLE PRECISION,
+    alert_report_type UUID REFERENCES report_types(id) ON DELETE SET NULL,
+    alert_report_status UUID REFERENCES report_statuses(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (machine_type, name),
+    CHECK (min_value IS NULL OR max_value IS NULL OR min_value <= max_value)
+);
+
+CREATE TABLE measurement_readings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    definition_id UUID NOT NULL REFERENCES measurement_definitions(id) ON DELETE CASCADE,
+    machine_id UUID NOT NULL REFERENCES machines(id) ON DELETE CASCADE,
+    task_id UUID REFERENCES tasks(id) ON DELETE SET NULL,
+    value DOUBLE PRECISION NOT NULL,
+    out_of_range BOOLEAN NOT NULL,
+    report_id UUID REFERENCES reports(id) ON DELETE SET NULL,
+    note TEXT,
+    recorded_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX measurement_readings_series_idx ON measurement_readings (machine_id, definition_id, recorded_at);
@@ -0,0 +1,116 @@
+-- Spare parts catalogue, parts without machine types fit any machine. The unit cost is
+-- in cents
+
+CREATE TABLE parts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    part_number VARCHAR(64) NOT NULL UNIQUE,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    unit VARCHAR(32) NOT NULL DEFAULT 'pcs',
+    unit_cost INT CHECK (unit_cost >= 0),
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    edited TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_parts_edited
+BEFORE UPDATE ON parts
+FOR EACH ROW
+EXECUTE PROCEDURE update_edited_column();
+
+CREATE TABLE machine_type_parts (
+    machine_type_id UUID NOT NULL REFERENCES machine_types(id) ON DELETE CASCADE,
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    PRIMARY KEY (machine_type_id, part_id)
+);
+
+-- Stock per facility, reserved parts are still on hand but promised to a task. Dropping to
+-- the minimum raises a purchase request over at least the reorder quantity
+
+CREATE TABLE part_stock (
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    on_hand INT NOT NULL DEFAULT 0,
+    reserved INT NOT NULL DEFAULT 0,
+    min_quantity INT NOT NULL DEFAULT 0 CHECK (min_quantity >= 0),
+    reorder_quantity INT NOT NULL DEFAULT 0 CHECK (reorder_quantity >= 0),
+    PRIMARY KEY (part_id, facility_id),
+    CHECK (reserved >= 0 AND reserved <= on_hand)
+);
+
+-- Parts reserved for a task, consumed once they are used in the repair. Consuming keeps
+-- the unit cost of the part at that time
+
+CREATE TABLE task_parts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    reserved_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    consumed_at TIMESTAMPTZ,
+    unit_cost INT,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX task_parts_task_id_idx ON task_parts (task_id);
+
+-- Every change of the stock on hand, consumption keeps the machine of the task so the
+-- history shows what was used where
+
+CREATE TABLE part_movements (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    change INT NOT NULL,
+    kind VARCHAR(32) NOT NULL CHECK (kind IN ('receipt', 'adjustment', 'consumption', 'return')),
+    task_id UUID REFERENCES tasks(id) ON DELETE SET NULL,
+    machine_id UUID REFERENCES machines(id) ON DELETE SET NULL,
+    note TEXT,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX part_movements_part_id_idx ON part_movements (part_id, facility_id, created);
+CREATE INDEX part_movements_machine_id_idx ON part_movements (machine_id, created);
+
+CREATE TABLE suppliers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    contact_name VARCHAR(255),
+    email VARCHAR(255),
+    phone VARCHAR(64),
+    address VARCHAR(255),
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Requests go from requested over approved and ordered to received, which books the parts
+-- into the stock. Automatic ones come from low stock or from a task short of parts
+
+CREATE TABLE purchase_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    part_id UUID NOT NULL REFERENCES parts(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    supplier_id UUID REFERENCES suppliers(id) ON DELETE SET NULL,
+    status VARCHAR(32) NOT NULL DEFAULT 'requested' CHECK (
+        status IN ('requested', 'approved', 'rejected', 'ordered', 'received', 'cancelled')
+    ),
+    source VARCHAR(32) NOT NULL CHECK (source IN ('manual', 'low_stock', 'task')),
+    task_id UUID REFERENCES tasks(id) ON DELETE SET NULL,
+    note TEXT,
+    requested_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    decided_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    decided_at TIMESTAMPTZ,
+    ordered_at TIMESTAMPTZ,
+    received_at TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    edited TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_purchase_requests_edited
+BEFORE UPDATE ON purchase_requests
+FOR EACH ROW
+EXECUTE PROCEDURE update_edited_column();
+
+CREATE INDEX purchase_requests_open_idx ON purchase_requests (part_id, facility_id)
+WHERE status IN ('requested', 'approved', 'ordered');
@@ -26,6 +26,26 @@ BEFORE UPDATE ON reports
 FOR EACH ROW
 EXECUTE PROCEDURE update_edited_column();
 
+-- A report belongs to the facility of its machine, or of its creator when there is no machine
+
+CREATE OR REPLACE FUNCTION report_facility(report_id UUID)
+RETURNS UUID AS $$
+    SELECT
+        COALESCE(m.facility, u.facility)
+    FROM
+        reports r
+    INNER JOIN
+        users u
+    ON
+        r.creator = u.id
+    LEFT JOIN
+        machines m
+    ON
+        r.machine = m.id
+    WHERE
+        r.id = $1;
+$$ LANGUAGE sql STABLE;
+
 CREATE TABLE report_documents (
     report_id UUID NOT NULL REFERENCES reports(id),
     uri VARCHAR(512) NOT NULL,
@@ -3,34 +3,80 @@ CREATE TABLE roles (
     name VARCHAR(255) NOT NULL UNIQUE,
     level INT NOT NULL UNIQUE CHECK (level >= 0),
     has_password BOOLEAN NOT NULL DEFAULT TRUE,
-    user_view BOOLEAN NOT NULL DEFAULT FALSE,
-    user_create BOOLEAN NOT NULL DEFAULT FALSE,
-    user_edit BOOLEAN NOT NULL DEFAULT FALSE,
-    user_delete BOOLEAN NOT NULL DEFAULT FALSE,
-    machine_view BOOLEAN NOT NULL DEFAULT FALSE,
-    machine_create BOOLEAN NOT NULL DEFAULT FALSE,
-    machine_edit BOOLEAN NOT NULL DEFAULT FALSE,
-    machine_delete BOOLEAN NOT NULL DEFAULT FALSE,
-    task_view BOOLEAN NOT NULL DEFAULT FALSE,
-    task_create BOOLEAN NOT NULL DEFAULT FALSE,
-    task_edit BOOLEAN NOT NULL DEFAULT FALSE,
-    task_delete BOOLEAN NOT NULL DEFAULT FALSE,
-    report_view BOOLEAN NOT NULL DEFAULT FALSE,
-    report_create BOOLEAN NOT NULL DEFAULT FALSE,
-    report_edit BOOLEAN NOT NULL DEFAULT FALSE,
-    report_delete BOOLEAN NOT NULL DEFAULT FALSE,
-    facility_view BOOLEAN NOT NULL DEFAULT FALSE,
-    facility_create BOOLEAN NOT NULL DEFAULT FALSE,
-    facility_edit BOOLEAN NOT NULL DEFAULT FALSE,
-    facility_delete BOOLEAN NOT NULL DEFAULT FALSE
+    global_access BOOLEAN NOT NULL DEFAULT FALSE
 );
 
-INSERT INTO roles (
-    name, level, has_password, user_view, user_create, user_edit, user_delete, 
-    machine_view, machine_create, machine_edit, machine_delete, task_view, task_create, task_edit, task_delete, 
-    report_view, report_create, report_edit, report_delete, facility_view, facility_create, facility_edit, facility_delete
-) VALUES (
-    'Super', 0, FALSE, TRUE, TRUE, TRUE, TRUE, 
-    TRUE, TRUE, TRUE, TRUE, TRUE, TRUE, TRUE, TRUE, 
-    TRUE, TRUE, TRUE,  TRUE, TRUE, TRUE, TRUE, TRUE
+-- PERMISSION REGISTRY, EVERY RESOURCE HAS A VIEW, CREATE, EDIT AND DELETE PERMISSION
+
+CREATE TABLE permissions (
+    resource VARCHAR(64) NOT NULL,
+    action VARCHAR(64) NOT NULL,
+    PRIMARY KEY (resource, action)
+);
+
+INSERT INTO permissions (resource, action)
+SELECT
+    resource,
+    action
+FROM
+    unnest(ARRAY[
+        'user', 'role', 'api_key', 'facility',
+        'machine', 'machine_type', 'machine_status',
+        'task', 'task_type', 'task_status',
+        'report', 'report_type', 'report_status',
+        'team', 'availability', 'skill', 'checklist', 'measurement',
+        'maintenance', 'time_entry', 'part', 'supplier', 'purchase_request',
+        'cost'
+    ]) AS resource,
+    unnest(ARRAY['view', 'create', 'edit', 'delete']) AS action;
+
+-- Ownership rules, granting these lets users manage their own rows without the global permission
+
+INSERT INTO permissions (resource, action) VALUES
+    ('task', 'edit_own'),
+    ('task', 'delete_own'),
+    ('task', 'edit_status_assigned'),
+    ('report', 'edit_own'),
+    ('report', 'delete_own');
+
+-- Deciding on purchase requests is kept apart from raising them
+
+INSERT INTO permissions (resource, action) VALUES ('purchase_request', 'approve');
+
+-- Acting as another user, every session is kept in the impersonations table
+
+INSERT INTO permissions (resource, action) VALUES ('user', 'impersonate');
+
+CREATE TABLE role_permissions (
+    role_id UUID NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    resource VARCHAR(64) NOT NULL,
+    action VARCHAR(64) NOT NULL,
+    PRIMARY KEY (role_id, resource, action),
+    FOREIGN KEY (resource, action) REFERENCES permissions(resource, action) ON DELETE CASCADE
 );
+
+-- Permissions of a role as 'resource.action' keys
+
+CREATE OR REPLACE FUNCTION role_permission_keys(role_id UUID)
+RETURNS VARCHAR[] AS $$
+    SELECT
+        COALESCE(array_agg(rp.resource || '.' || rp.action ORDER BY rp.resource, rp.action), '{}')
+    FROM
+        role_permissions rp
+    WHERE
+        rp.role_id = $1;
+$$ LANGUAGE sql STABLE;
+
+INSERT INTO roles (name, level, has_password, global_access) VALUES ('Super', 0, FALSE, TRUE);
+
+INSERT INTO role_permissions (role_id, resource, action)
+SELECT
+    r.id,
+    p.resource,
+    p.action
+FROM
+    roles r
+CROSS JOIN
+    permissions p
+WHERE
+    r.name = 'Super';
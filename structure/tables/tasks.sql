@@ -3,9 +3,12 @@ CREATE TABLE task_types (
     name VARCHAR(255) NOT NULL UNIQUE
 );
 
+-- Terminal statuses close a task, like done or cancelled
+
 CREATE TABLE task_statuses (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-    name VARCHAR(255) NOT NULL UNIQUE
+    name VARCHAR(255) NOT NULL UNIQUE,
+    terminal BOOLEAN NOT NULL DEFAULT FALSE
 );
 
 CREATE TABLE tasks (
@@ -27,12 +30,265 @@ BEFORE UPDATE ON tasks
 FOR EACH ROW
 EXECUTE PROCEDURE update_edited_column();
 
+-- A task belongs to the facility of its machine, or of its creator when there is no machine
+
+CREATE OR REPLACE FUNCTION task_facility(task_id UUID)
+RETURNS UUID AS $$
+    SELECT
+        COALESCE(m.facility, u.facility)
+    FROM
+        tasks t
+    INNER JOIN
+        users u
+    ON
+        t.creator = u.id
+    LEFT JOIN
+        machines m
+    ON
+        t.machine = m.id
+    WHERE
+        t.id = $1;
+$$ LANGUAGE sql STABLE;
+
 CREATE TABLE task_executors (
     task_id UUID NOT NULL REFERENCES tasks(id),
     user_id UUID NOT NULL REFERENCES users(id),
+    assigned TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     PRIMARY KEY (task_id, user_id)
 );
 
+CREATE INDEX task_executors_user_id_idx ON task_executors (user_id, assigned);
+
+-- Every member of an assigned team counts as an executor of the task
+
+CREATE TABLE task_teams (
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    PRIMARY KEY (task_id, team_id)
+);
+
+CREATE OR REPLACE FUNCTION is_task_executor(task_id UUID, user_id UUID)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS (
+        SELECT 1 FROM task_executors te WHERE te.task_id = $1 AND te.user_id = $2
+    ) OR EXISTS (
+        SELECT
+            1
+        FROM
+            task_teams tt
+        INNER JOIN
+            team_members tm
+        ON
+            tt.team_id = tm.team_id
+        WHERE
+            tt.task_id = $1
+        AND
+            tm.user_id = $2
+    );
+$$ LANGUAGE sql STABLE;
+
+-- True when the task is due at a time none of its executors is available
+
+CREATE OR REPLACE FUNCTION is_task_outside_availability(task_id UUID)
+RETURNS BOOLEAN AS $$
+    WITH executors AS (
+        SELECT te.user_id FROM task_executors te WHERE te.task_id = $1
+        UNION
+        SELECT
+            tm.user_id
+        FROM
+            task_teams ttm
+        INNER JOIN
+            team_members tm
+        ON
+            ttm.team_id = tm.team_id
+        WHERE
+            ttm.task_id = $1
+    )
+    SELECT
+        t.due_at IS NOT NULL
+        AND EXISTS (SELECT 1 FROM executors)
+        AND NOT EXISTS (
+            SELECT 1 FROM executors e WHERE is_user_available(e.user_id, t.due_at, t.due_at)
+        )
+    FROM
+        tasks t
+    WHERE
+        t.id = $1;
+$$ LANGUAGE sql STABLE;
+
+-- Required skills come from the task type and from the type of the task's machine
+
+CREATE TABLE task_type_skills (
+    task_type_id UUID NOT NULL REFERENCES task_types(id) ON DELETE CASCADE,
+    skill_id UUID NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+    PRIMARY KEY (task_type_id, skill_id)
+);
+
+CREATE TABLE machine_type_skills (
+    machine_type_id UUID NOT NULL REFERENCES machine_types(id) ON DELETE CASCADE,
+    skill_id UUID NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+    PRIMARY KEY (machine_type_id, skill_id)
+);
+
+-- Names of the required skills the user doesn't hold, expired certifications don't count
+
+CREATE OR REPLACE FUNCTION task_missing_skills(task_id UUID, user_id UUID)
+RETURNS TEXT[] AS $$
+    SELECT
+        COALESCE(array_agg(s.name::TEXT ORDER BY s.name), '{}')
+    FROM
+        skills s
+    WHERE
+        s.id IN (
+            SELECT
+                tts.skill_id
+            FROM
+                tasks t
+            INNER JOIN
+                task_type_skills tts
+            ON
+                t.task_type = tts.task_type_id
+            WHERE
+                t.id = $1
+            UNION
+            SELECT
+                mts.skill_id
+            FROM
+                tasks t
+            INNER JOIN
+                machines m
+            ON
+                t.machine = m.id
+            INNER JOIN
+                machine_type_skills mts
+            ON
+                m.machine_type = mts.machine_type_id
+            WHERE
+                t.id = $1
+        )
+    AND
+        NOT EXISTS (
+            SELECT
+                1
+            FROM
+                user_skills us
+            WHERE
+                us.user_id = $2
+            AND
+                us.skill_id = s.id
+            AND
+                (us.expires_at IS NULL OR us.expires_at >= CURRENT_DATE)
+        );
+$$ LANGUAGE sql STABLE;
+
+-- Checklist templates are copied onto tasks, so changing a template doesn't alter
+-- the steps of existing tasks
+
+CREATE TABLE checklist_templates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE checklist_template_steps (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    template_id UUID NOT NULL REFERENCES checklist_templates(id) ON DELETE CASCADE,
+    position INT NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    instructions TEXT,
+    mandatory BOOLEAN NOT NULL DEFAULT TRUE,
+    requires_photo BOOLEAN NOT NULL DEFAULT FALSE,
+    requires_measurement BOOLEAN NOT NULL DEFAULT FALSE,
+    measurement_unit VARCHAR(32)
+);
+
+CREATE INDEX checklist_template_steps_template_id_idx ON checklist_template_steps (template_id, position);
+
+CREATE TABLE task_type_checklists (
+    task_type_id UUID NOT NULL REFERENCES task_types(id) ON DELETE CASCADE,
+    template_id UUID NOT NULL REFERENCES checklist_templates(id) ON DELETE CASCADE,
+    PRIMARY KEY (task_type_id, template_id)
+);
+
+CREATE TABLE machine_type_checklists (
+    machine_type_id UUID NOT NULL REFERENCES machine_types(id) ON DELETE CASCADE,
+    template_id UUID NOT NULL REFERENCES checklist_templates(id) ON DELETE CASCADE,
+    PRIMARY KEY (machine_type_id, template_id)
+);
+
+CREATE TABLE task_checklist_steps (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    template_id UUID REFERENCES checklist_templates(id) ON DELETE SET NULL,
+    position INT NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    instructions TEXT,
+    mandatory BOOLEAN NOT NULL,
+    requires_photo BOOLEAN NOT NULL,
+    requires_measurement BOOLEAN NOT NULL,
+    measurement_unit VARCHAR(32),
+    photo VARCHAR(512),
+    measurement DOUBLE PRECISION,
+    note TEXT,
+    completed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX task_checklist_steps_task_id_idx ON task_checklist_steps (task_id, position);
+
+CREATE OR REPLACE FUNCTION is_task_checklist_incomplete(task_id UUID)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS (
+        SELECT
+            1
+        FROM
+            task_checklist_steps tcs
+        WHERE
+            tcs.task_id = $1
+        AND
+            tcs.mandatory
+        AND
+            tcs.completed_at IS NULL
+    );
+$$ LANGUAGE sql STABLE;
+
+-- Labour logged by executors, an entry without an end is a running timer and nobody
+-- runs more than one at a time. The hourly rate of the user is filled in on insert, see
+-- costs.sql
+
+CREATE TABLE task_time_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ,
+    note TEXT,
+    hourly_rate INT,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ended_at IS NULL OR ended_at > started_at)
+);
+
+CREATE INDEX task_time_entries_task_id_idx ON task_time_entries (task_id);
+CREATE INDEX task_time_entries_user_id_idx ON task_time_entries (user_id, started_at);
+CREATE UNIQUE INDEX task_time_entries_running_idx ON task_time_entries (user_id) WHERE ended_at IS NULL;
+
+-- Running timers count up to now
+
+CREATE OR REPLACE FUNCTION task_logged_minutes(task_id UUID)
+RETURNS BIGINT AS $$
+    SELECT
+        COALESCE(
+            floor(sum(EXTRACT(EPOCH FROM COALESCE(tte.ended_at, NOW()) - tte.started_at)) / 60),
+            0
+        )::BIGINT
+    FROM
+        task_time_entries tte
+    WHERE
+        tte.task_id = $1;
+$$ LANGUAGE sql STABLE;
+
 CREATE TABLE task_documents (
     task_id UUID NOT NULL REFERENCES tasks(id),
     uri VARCHAR(512) NOT NULL,
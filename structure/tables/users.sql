@@ -7,28 +7,241 @@ CREATE TABLE users (
     phone VARCHAR(255) UNIQUE,
     role UUID NOT NULL REFERENCES roles(id) ON DELETE RESTRICT,
     active BOOLEAN NOT NULL DEFAULT TRUE,
+    activated_at TIMESTAMPTZ,
     last_login TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     occupation VARCHAR(255),
     image VARCHAR(512),
-    facility UUID REFERENCES facilities(id) ON DELETE SET NULL
+    facility UUID REFERENCES facilities(id) ON DELETE SET NULL,
+    location UUID REFERENCES locations(id) ON DELETE SET NULL,
+    service_account BOOLEAN NOT NULL DEFAULT FALSE,
+    language VARCHAR(16) NOT NULL DEFAULT 'en',
+    notify_task_assigned BOOLEAN NOT NULL DEFAULT TRUE
 );
 
-CREATE OR REPLACE FUNCTION is_password_required(role_id UUID, user_password VARCHAR)
+-- Invited users are inactive until they have set their password
+
+CREATE OR REPLACE FUNCTION is_password_required(role_id UUID, user_password VARCHAR, user_active BOOLEAN)
 RETURNS BOOLEAN AS $$
 DECLARE
     role_has_password BOOLEAN;
 BEGIN
     SELECT INTO role_has_password has_password FROM roles WHERE id = role_id;
     
-    RETURN (NOT role_has_password OR user_password IS NOT NULL);
+    RETURN (NOT role_has_password OR user_password IS NOT NULL OR NOT user_active);
 END;
 $$ LANGUAGE plpgsql;
 
 ALTER TABLE users
 ADD CONSTRAINT is_password_required_constraint
-CHECK (is_password_required(role, password));
+CHECK (is_password_required(role, password, active));
 
 CREATE INDEX idx_email ON users(email);
 
+-- Set the first time a user is active, invitations only go to accounts that never were
+
+CREATE OR REPLACE FUNCTION set_user_activated() RETURNS TRIGGER AS $$
+BEGIN
+  IF NEW.active AND NEW.activated_at IS NULL THEN
+    NEW.activated_at = NOW();
+  END IF;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_user_activated
+BEFORE INSERT OR UPDATE OF active ON users
+FOR EACH ROW EXECUTE PROCEDURE set_user_activated();
+
+-- The machine tables come before the users, so the mover is linked here
+
+ALTER TABLE machine_location_history
+ADD FOREIGN KEY (moved_by) REFERENCES users(id) ON DELETE SET NULL;
+
 INSERT INTO users (first_name, last_name, email, phone, role, occupation)
 VALUES ('Service', 'Systems', 'henning@email.com', '123456789', (SELECT id FROM roles WHERE name = 'Super'), 'Administration');
+
+-- Facilities a user's role applies to besides their own facility, ignored for global roles
+
+CREATE TABLE user_facilities (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    facility_id UUID NOT NULL REFERENCES facilities(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, facility_id)
+);
+
+CREATE OR REPLACE FUNCTION user_facility_ids(user_id UUID)
+RETURNS UUID[] AS $$
+    SELECT
+        COALESCE(array_agg(DISTINCT scope.facility_id), '{}')
+    FROM
+    (
+        SELECT u.facility AS facility_id FROM users u WHERE u.id = $1 AND u.facility IS NOT NULL
+        UNION
+        SELECT uf.facility_id FROM user_facilities uf WHERE uf.user_id = $1
+    ) scope;
+$$ LANGUAGE sql STABLE;
+
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    prefix VARCHAR(16) NOT NULL UNIQUE,
+    hash VARCHAR(255) NOT NULL,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    last_used TIMESTAMPTZ
+);
+
+CREATE INDEX idx_api_key_user ON api_keys(user_id);
+
+CREATE TABLE api_key_permissions (
+    api_key_id UUID NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    resource VARCHAR(64) NOT NULL,
+    action VARCHAR(64) NOT NULL,
+    PRIMARY KEY (api_key_id, resource, action),
+    FOREIGN KEY (resource, action) REFERENCES permissions(resource, action) ON DELETE CASCADE
+);
+
+CREATE OR REPLACE FUNCTION api_key_permission_keys(api_key_id UUID)
+RETURNS VARCHAR[] AS $$
+    SELECT
+        COALESCE(array_agg(akp.resource || '.' || akp.action ORDER BY akp.resource, akp.action), '{}')
+    FROM
+        api_key_permissions akp
+    WHERE
+        akp.api_key_id = $1;
+$$ LANGUAGE sql STABLE;
+
+CREATE TYPE login_method AS ENUM ('password', 'otp', 'oidc');
+
+CREATE TABLE login_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    method login_method NOT NULL,
+    success BOOLEAN NOT NULL,
+    ip VARCHAR(45),
+    user_agent VARCHAR(512),
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_login_history_user ON login_history(user_id, created DESC);
+
+-- Sessions where an administrator acts as another user
+
+CREATE TABLE impersonations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    admin_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    reason TEXT,
+    ip VARCHAR(45),
+    user_agent VARCHAR(512),
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ,
+    ended_by UUID REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_impersonations_admin ON impersonations(admin_id, created DESC);
+CREATE INDEX idx_impersonations_user ON impersonations(user_id, created DESC);
+
+-- Invitations to set up an account an administrator created on someone's behalf
+
+CREATE TABLE invitations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    accepted_at TIMESTAMPTZ,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX idx_invitations_user ON invitations(user_id, created DESC);
+
+-- Groups of users like "Electrical shift B" that tasks can be assigned to as a whole
+
+CREATE TABLE teams (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT,
+    facility UUID REFERENCES facilities(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE team_members (
+    team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (team_id, user_id)
+);
+
+CREATE INDEX idx_team_members_user ON team_members(user_id);
+
+-- Shifts and on-call duty make a user available, a vacation overrides both
+
+CREATE TYPE availability_kind AS ENUM ('shift', 'vacation', 'on_call');
+
+CREATE TABLE availabilities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind availability_kind NOT NULL,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL,
+    note TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX idx_availabilities_user ON availabilities(user_id, starts_at);
+
+CREATE OR REPLACE FUNCTION is_user_available(user_id UUID, starts_at TIMESTAMPTZ, ends_at TIMESTAMPTZ)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS (
+        SELECT
+            1
+        FROM
+            availabilities a
+        WHERE
+            a.user_id = $1
+        AND
+            a.kind IN ('shift', 'on_call')
+        AND
+            a.starts_at <= $2
+        AND
+            a.ends_at >= $3
+    ) AND NOT EXISTS (
+        SELECT
+            1
+        FROM
+            availabilities a
+        WHERE
+            a.user_id = $1
+        AND
+            a.kind = 'vacation'
+        AND
+            a.starts_at <= $3
+        AND
+            a.ends_at >= $2
+    );
+$$ LANGUAGE sql STABLE;
+
+-- Skills are anything a task can require, certifications are skills that expire
+
+CREATE TABLE skills (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT,
+    certification BOOLEAN NOT NULL DEFAULT FALSE,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE user_skills (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    skill_id UUID NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+    obtained_at DATE,
+    expires_at DATE,
+    reference VARCHAR(255),
+    PRIMARY KEY (user_id, skill_id)
+);
+
+CREATE INDEX user_skills_expires_at_idx ON user_skills (expires_at) WHERE expires_at IS NOT NULL;